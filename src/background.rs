@@ -0,0 +1,135 @@
+//! The background of a scene gives the light arriving along rays that don't hit
+//! anything in the world.
+use std::{fmt::Debug, io};
+
+use crate::{
+    color::{self, Color},
+    geometry::{ray::Ray, vec3::Vec3},
+    scene::description::{self, BackgroundDescription},
+    utils::rand::RTRng,
+};
+
+pub mod environment;
+
+/// The light arriving from outside of the world.
+pub trait Background: Debug + Send + Sync {
+    /// The light arriving along a ray that escapes the world.
+    fn color(&self, ray: &Ray) -> Color;
+
+    /// Sample a direction in proportion to the light arriving from it, returning the
+    /// direction and its probability density with respect to solid angle. Returns
+    /// `None` when the background does not support importance sampling, in which
+    /// case it is only found by rays scattered from surfaces.
+    fn sample(&self, _rng: &RTRng) -> Option<(Vec3, f64)> {
+        None
+    }
+
+    /// The probability density, with respect to solid angle, that
+    /// [`Background::sample`] returns a direction.
+    fn pdf(&self, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Describe the background for saving a scene. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self) -> io::Result<BackgroundDescription> {
+        Err(description::invalid_input(
+            "the scene's background can't be saved",
+        ))
+    }
+}
+
+/// A background of a single color in every direction.
+#[derive(Debug)]
+pub struct Solid {
+    pub color: Color,
+}
+
+impl Solid {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+
+    /// A background that gives off no light. Useful for scenes lit only by emissive
+    /// materials.
+    pub fn black() -> Self {
+        Self::new(color::BLACK)
+    }
+}
+
+impl Background for Solid {
+    fn color(&self, _ray: &Ray) -> Color {
+        self.color
+    }
+
+    fn describe(&self) -> io::Result<BackgroundDescription> {
+        Ok(BackgroundDescription::Solid {
+            color: description::rgb(self.color),
+        })
+    }
+}
+
+/// A background that blends linearly between two colors, from straight down to
+/// straight up.
+#[derive(Debug)]
+pub struct Gradient {
+    pub bottom: Color,
+    pub top: Color,
+}
+
+impl Gradient {
+    pub fn new(bottom: Color, top: Color) -> Self {
+        Self { bottom, top }
+    }
+
+    /// A white to blue gradient resembling the sky.
+    pub fn sky() -> Self {
+        Self::new(color::WHITE, Color::new(0.5, 0.7, 1.0))
+    }
+}
+
+impl Background for Gradient {
+    fn color(&self, ray: &Ray) -> Color {
+        let unit_dir = ray.direction().normalized();
+        let t = 0.5 * (unit_dir.y() + 1.0);
+        (1.0 - t) * self.bottom + t * self.top
+    }
+
+    fn describe(&self) -> io::Result<BackgroundDescription> {
+        Ok(BackgroundDescription::Gradient {
+            bottom: description::rgb(self.bottom),
+            top: description::rgb(self.top),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Background, Gradient, Solid};
+    use crate::{
+        color::{self, Color},
+        geometry::{ray::Ray, vec3::Vec3},
+    };
+
+    #[test]
+    fn solid() {
+        let background = Solid::new(Color::new(0.1, 0.2, 0.3));
+        let ray = Ray::new(Vec3::new(1, 2, 3), Vec3::new(-1, 4, 2));
+        assert_eq!(Color::new(0.1, 0.2, 0.3), background.color(&ray));
+        assert_eq!(color::BLACK, Solid::black().color(&ray));
+    }
+
+    #[test]
+    fn gradient() {
+        let background = Gradient::new(color::BLACK, Color::new(2.0, 4.0, 8.0));
+        let origin = Vec3::new(0, 0, 0);
+
+        let up = Ray::new(origin, Vec3::new(0, 3, 0));
+        let down = Ray::new(origin, Vec3::new(0, -0.5, 0));
+        let horizontal = Ray::new(origin, Vec3::new(1, 0, 1));
+
+        assert_eq!(Color::new(2.0, 4.0, 8.0), background.color(&up));
+        assert_eq!(color::BLACK, background.color(&down));
+        assert_eq!(Color::new(1.0, 2.0, 4.0), background.color(&horizontal));
+    }
+}
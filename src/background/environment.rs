@@ -0,0 +1,386 @@
+//! Image-based lighting from an equirectangular environment map.
+use std::{
+    f64::consts::PI,
+    io,
+    path::{Path, PathBuf},
+};
+
+use super::Background;
+use crate::{
+    color::Color,
+    geometry::{ray::Ray, vec3::Vec3},
+    image,
+    scene::description::{self, BackgroundDescription},
+    tracer::ImagePixels,
+    utils::rand::RTRng,
+};
+
+/// A background given by an equirectangular (latitude-longitude) image. The top row
+/// of the image is straight up, the bottom row straight down, and the columns span
+/// a full turn about the vertical axis.
+///
+/// Directions are importance sampled in proportion to the luminance of the map, so
+/// small, bright features such as the sun are found quickly.
+#[derive(Debug)]
+pub struct EnvironmentMap {
+    width: usize,
+    height: usize,
+    /// Pixels ordered top to bottom, left to right.
+    pixels: Vec<Color>,
+    /// Rotation of the map about the vertical axis, in radians.
+    rotation: f64,
+    /// The same rotation, in degrees as it was given.
+    rotation_degrees: f64,
+    intensity: f64,
+    distribution: Distribution2D,
+    /// The file the map was loaded from, if any.
+    path: Option<PathBuf>,
+}
+
+impl EnvironmentMap {
+    /// Create an environment map from an image. The map is rotated about the vertical
+    /// axis by `rotation_degrees`, and its colors are scaled by `intensity`.
+    ///
+    /// # Panics
+    /// Panics if the image has no pixels or its scanlines differ in length.
+    pub fn new(image: ImagePixels, rotation_degrees: f64, intensity: f64) -> Self {
+        let height = image.len();
+        let width = image.first().map_or(0, Vec::len);
+        if width == 0 || image.iter().any(|row| row.len() != width) {
+            panic!("Environment map must be a non-empty rectangular image");
+        }
+
+        // Weight each pixel by the solid angle it covers, which shrinks towards
+        // the poles.
+        let pixels: Vec<Color> = image.into_iter().flatten().collect();
+        let weights: Vec<Vec<f64>> = pixels
+            .chunks(width)
+            .enumerate()
+            .map(|(row, colors)| {
+                let sin_theta = (PI * (row as f64 + 0.5) / height as f64).sin();
+                colors
+                    .iter()
+                    .map(|c| c.luminance().max(0.0) * sin_theta)
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            width,
+            height,
+            pixels,
+            rotation: rotation_degrees.to_radians(),
+            rotation_degrees,
+            intensity,
+            distribution: Distribution2D::new(weights),
+            path: None,
+        }
+    }
+
+    /// Load an environment map from an image file, such as a `.hdr` or `.pfm` image.
+    ///
+    /// # Errors
+    /// Returns an error if the image can't be read.
+    pub fn load(path: impl AsRef<Path>, rotation_degrees: f64, intensity: f64) -> io::Result<Self> {
+        let path = path.as_ref();
+        let image = image::read(path)?;
+        if image.is_empty() || image[0].is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "Environment map has no pixels",
+            ));
+        }
+        Ok(Self {
+            path: Some(path.to_path_buf()),
+            ..Self::new(image, rotation_degrees, intensity)
+        })
+    }
+
+    /// The path of the file the map was loaded from, or `None` if it was created
+    /// from an image in memory.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// The rotation of the map about the vertical axis, in degrees.
+    pub fn rotation_degrees(&self) -> f64 {
+        self.rotation_degrees
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// Map a direction to image coordinates in the unit square.
+    fn direction_to_uv(&self, direction: Vec3) -> (f64, f64) {
+        let d = direction.normalized();
+        let theta = d.y().clamp(-1.0, 1.0).acos();
+        let phi = d.z().atan2(d.x()) - self.rotation;
+        ((phi / (2.0 * PI)).rem_euclid(1.0), theta / PI)
+    }
+
+    fn uv_to_direction(&self, u: f64, v: f64) -> Vec3 {
+        let phi = 2.0 * PI * u + self.rotation;
+        let theta = PI * v;
+        Vec3::new(
+            theta.sin() * phi.cos(),
+            theta.cos(),
+            theta.sin() * phi.sin(),
+        )
+    }
+
+    fn pixel_index(&self, u: f64, v: f64) -> (usize, usize) {
+        let col = ((u * self.width as f64) as usize).min(self.width - 1);
+        let row = ((v * self.height as f64) as usize).min(self.height - 1);
+        (row, col)
+    }
+
+    /// Convert a density over the image to a density over solid angle.
+    fn solid_angle_pdf(uv_pdf: f64, v: f64) -> f64 {
+        let sin_theta = (PI * v).sin();
+        if sin_theta <= 0.0 {
+            0.0
+        } else {
+            uv_pdf / (2.0 * PI * PI * sin_theta)
+        }
+    }
+}
+
+impl Background for EnvironmentMap {
+    fn color(&self, ray: &Ray) -> Color {
+        let (u, v) = self.direction_to_uv(ray.direction());
+        let (row, col) = self.pixel_index(u, v);
+        self.pixels[row * self.width + col] * self.intensity
+    }
+
+    fn sample(&self, rng: &RTRng) -> Option<(Vec3, f64)> {
+        let (u, v, uv_pdf) = self
+            .distribution
+            .sample(rng.random_f64(), rng.random_f64())?;
+        let pdf = Self::solid_angle_pdf(uv_pdf, v);
+        (pdf > 0.0).then(|| (self.uv_to_direction(u, v), pdf))
+    }
+
+    fn pdf(&self, direction: Vec3) -> f64 {
+        let (u, v) = self.direction_to_uv(direction);
+        let (row, col) = self.pixel_index(u, v);
+        Self::solid_angle_pdf(self.distribution.pdf(row, col), v)
+    }
+
+    fn describe(&self) -> io::Result<BackgroundDescription> {
+        let path = self.path().ok_or_else(|| {
+            description::invalid_input("only environment maps loaded from a file can be saved")
+        })?;
+        Ok(BackgroundDescription::Environment {
+            path: description::absolute_path(path)?,
+            rotation: self.rotation_degrees(),
+            intensity: self.intensity(),
+        })
+    }
+}
+
+/// A piecewise-constant probability distribution over `[0,1)`.
+#[derive(Debug)]
+struct Distribution1D {
+    func: Vec<f64>,
+    /// Cumulative distribution, with `cdf[0] == 0` and `cdf[func.len()] == 1`.
+    cdf: Vec<f64>,
+    integral: f64,
+}
+
+impl Distribution1D {
+    fn new(func: Vec<f64>) -> Self {
+        let n = func.len() as f64;
+        let mut cdf = Vec::with_capacity(func.len() + 1);
+        cdf.push(0.0);
+        for (i, f) in func.iter().enumerate() {
+            cdf.push(cdf[i] + f / n);
+        }
+
+        let integral = cdf[func.len()];
+        for (i, c) in cdf.iter_mut().enumerate() {
+            *c = if integral > 0.0 {
+                *c / integral
+            } else {
+                i as f64 / n
+            };
+        }
+
+        Self {
+            func,
+            cdf,
+            integral,
+        }
+    }
+
+    /// Sample the distribution with a uniform random number in `[0,1)`. Returns the
+    /// sampled value, its density and the index of the piece it lies in.
+    fn sample(&self, x: f64) -> (f64, f64, usize) {
+        let i = (self.cdf.partition_point(|&c| c <= x) - 1).min(self.func.len() - 1);
+        let width = self.cdf[i + 1] - self.cdf[i];
+        let offset = if width > 0.0 {
+            (x - self.cdf[i]) / width
+        } else {
+            0.0
+        };
+        let value = (i as f64 + offset) / self.func.len() as f64;
+        (value, self.pdf(i), i)
+    }
+
+    /// The density of the distribution over the `i`th piece.
+    fn pdf(&self, i: usize) -> f64 {
+        if self.integral > 0.0 {
+            self.func[i] / self.integral
+        } else {
+            1.0
+        }
+    }
+}
+
+/// A piecewise-constant probability distribution over the unit square, sampled by
+/// first choosing a row from the marginal distribution, then a column from that
+/// row's conditional distribution.
+#[derive(Debug)]
+struct Distribution2D {
+    conditional: Vec<Distribution1D>,
+    marginal: Distribution1D,
+}
+
+impl Distribution2D {
+    /// Create a distribution from weights indexed by row then column.
+    fn new(weights: Vec<Vec<f64>>) -> Self {
+        let conditional: Vec<_> = weights.into_iter().map(Distribution1D::new).collect();
+        let marginal = Distribution1D::new(conditional.iter().map(|d| d.integral).collect());
+        Self {
+            conditional,
+            marginal,
+        }
+    }
+
+    /// Sample a point `(u, v)`, where `v` runs across rows and `u` across columns, and
+    /// return it with its density. Returns `None` if the weights are all zero.
+    fn sample(&self, x: f64, y: f64) -> Option<(f64, f64, f64)> {
+        if self.marginal.integral <= 0.0 {
+            return None;
+        }
+
+        let (v, pdf_v, row) = self.marginal.sample(y);
+        let (u, pdf_u, _) = self.conditional[row].sample(x);
+        Some((u, v, pdf_u * pdf_v))
+    }
+
+    fn pdf(&self, row: usize, col: usize) -> f64 {
+        if self.marginal.integral <= 0.0 {
+            return 0.0;
+        }
+        self.conditional[row].func[col] / self.marginal.integral
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::EnvironmentMap;
+    use crate::{
+        background::Background,
+        color::{self, Color},
+        geometry::{ray::Ray, vec3::Vec3},
+        tracer::ImagePixels,
+        utils::rand::RTRng,
+    };
+
+    fn constant_map(width: usize, height: usize, color: Color) -> ImagePixels {
+        vec![vec![color; width]; height]
+    }
+
+    #[test]
+    fn direction_round_trip() {
+        let map = EnvironmentMap::new(constant_map(8, 4, color::WHITE), 30.0, 1.0);
+        for (u, v) in [(0.1, 0.2), (0.5, 0.5), (0.9, 0.75), (0.0, 0.3)] {
+            let (u2, v2) = map.direction_to_uv(map.uv_to_direction(u, v));
+            assert!((u - u2).abs() < 1e-9, "u: {u} != {u2}");
+            assert!((v - v2).abs() < 1e-9, "v: {v} != {v2}");
+        }
+    }
+
+    #[test]
+    fn top_row_is_up() {
+        let mut image = constant_map(4, 2, color::BLACK);
+        image[0] = vec![color::WHITE; 4];
+        let map = EnvironmentMap::new(image, 0.0, 2.0);
+        let origin = Vec3::new(0, 0, 0);
+
+        let up = Ray::new(origin, Vec3::new(0.1, 1.0, 0.0));
+        let down = Ray::new(origin, Vec3::new(0.1, -1.0, 0.0));
+        assert_eq!(Color::new(2.0, 2.0, 2.0), map.color(&up));
+        assert_eq!(color::BLACK, map.color(&down));
+    }
+
+    #[test]
+    fn rotation() {
+        let mut image = constant_map(4, 1, color::BLACK);
+        image[0][0] = color::WHITE;
+        let unrotated = EnvironmentMap::new(image.clone(), 0.0, 1.0);
+        let rotated = EnvironmentMap::new(image, 180.0, 1.0);
+
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1.0, 0.0, 0.1));
+        assert_eq!(color::WHITE, unrotated.color(&ray));
+        assert_eq!(color::BLACK, rotated.color(&ray));
+
+        let opposite = Ray::new(Vec3::new(0, 0, 0), Vec3::new(-1.0, 0.0, -0.1));
+        assert_eq!(color::WHITE, rotated.color(&opposite));
+    }
+
+    #[test]
+    fn samples_are_consistent_with_pdf() {
+        let rng = RTRng::new();
+        let mut image = constant_map(16, 8, Color::new(0.1, 0.1, 0.1));
+        image[3][5] = Color::new(50.0, 40.0, 30.0);
+        let map = EnvironmentMap::new(image, 45.0, 1.0);
+
+        for _ in 0..1000 {
+            let (direction, pdf) = map.sample(&rng).expect("Map should be sampled");
+            let expected = map.pdf(direction);
+            assert!((pdf - expected).abs() <= 1e-6 * pdf, "{pdf} != {expected}");
+        }
+    }
+
+    #[test]
+    fn bright_pixels_are_sampled_often() {
+        let rng = RTRng::new();
+        let mut image = constant_map(16, 8, Color::new(0.01, 0.01, 0.01));
+        let sun = Color::new(1000.0, 1000.0, 1000.0);
+        image[2][10] = sun;
+        let map = EnvironmentMap::new(image, 0.0, 1.0);
+
+        let samples = 1000;
+        let hits = (0..samples)
+            .filter_map(|_| map.sample(&rng))
+            .filter(|&(direction, _)| map.color(&Ray::new(Vec3::default(), direction)) == sun)
+            .count();
+        assert!(hits > 9 * samples / 10, "Only {hits} samples hit the sun");
+    }
+
+    #[test]
+    fn pdf_integrates_to_one() {
+        let rng = RTRng::new();
+        let mut image = constant_map(16, 8, Color::new(0.5, 0.5, 0.5));
+        image[6][1] = Color::new(20.0, 20.0, 20.0);
+        let map = EnvironmentMap::new(image, 10.0, 1.0);
+
+        // Monte Carlo estimate of the integral of the pdf over the sphere.
+        let samples = 200_000;
+        let total: f64 = (0..samples)
+            .map(|_| map.pdf(rng.random_unit_vector()) * 4.0 * PI)
+            .sum();
+        let integral = total / samples as f64;
+        assert!((integral - 1.0).abs() < 0.02, "Integral was {integral}");
+    }
+
+    #[test]
+    fn black_map_is_not_sampled() {
+        let map = EnvironmentMap::new(constant_map(4, 4, color::BLACK), 0.0, 1.0);
+        assert!(map.sample(&RTRng::new()).is_none());
+    }
+}
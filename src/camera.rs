@@ -17,6 +17,8 @@ pub struct Camera {
     w: Vec3,
 
     lens_radius: f64,
+    shutter_open: f64,
+    shutter_close: f64,
 }
 
 /// Configuration used when constructing a [`Camera`].
@@ -44,6 +46,14 @@ pub struct Config {
     /// The focus distance of the camera. Objects at this distance from the camera
     /// will be in perfect focus. Thin lens approximation is used.
     pub focus_dist: f64,
+
+    /// The time that the shutter opens. Rays are cast at random times while the
+    /// shutter is open, so moving objects are blurred along their path.
+    pub shutter_open: f64,
+
+    /// The time that the shutter closes. Use the same time as `shutter_open` for an
+    /// instantaneous exposure.
+    pub shutter_close: f64,
 }
 
 impl Camera {
@@ -71,6 +81,8 @@ impl Camera {
             v,
             w,
             lens_radius: config.aperture_diameter / 2.0,
+            shutter_open: config.shutter_open,
+            shutter_close: config.shutter_close,
         }
     }
 }
@@ -78,17 +90,29 @@ impl Camera {
 impl Camera {
     /// Get a ray from the origin of the camera, to a point `(s, t)` on its viewport.
     /// `s` and `t` are the horizontal and vertical proportions of the viewport,
-    /// respectively, starting from its bottom left corner.
+    /// respectively, starting from its bottom left corner. The ray is cast at a
+    /// random time while the shutter is open.
     ///
     /// # Examples
     /// Get rays at different viewport positions
     /// ```
-    /// # use ray_tracing_1::camera::Camera;
-    /// let camera = Camera::new(16.0 / 9.0);
-    /// let ray_at_viewport_bottom_left = camera.get_ray(0.0, 0.0);
-    /// let ray_at_viewport_center = camera.get_ray(0.5, 0.5);
-    /// let ray_at_viewport_top_right = camera.get_ray(1.0, 1.0);
-    /// let ray_outside_viewport = camera.get_ray(1.1, 1.0);
+    /// # use ray_tracing_1::{camera::{Camera, Config}, geometry::vec3::Vec3, utils::rand::RTRng};
+    /// let camera = Camera::new(Config {
+    ///     look_from: Vec3::new(0, 0, 0),
+    ///     look_at: Vec3::new(0, 0, -1),
+    ///     vup: Vec3::new(0, 1, 0),
+    ///     vfov_degrees: 90.0,
+    ///     aspect_ratio: 16.0 / 9.0,
+    ///     aperture_diameter: 0.0,
+    ///     focus_dist: 1.0,
+    ///     shutter_open: 0.0,
+    ///     shutter_close: 0.0,
+    /// });
+    /// let rng = RTRng::new();
+    /// let ray_at_viewport_bottom_left = camera.get_ray(0.0, 0.0, &rng);
+    /// let ray_at_viewport_center = camera.get_ray(0.5, 0.5, &rng);
+    /// let ray_at_viewport_top_right = camera.get_ray(1.0, 1.0, &rng);
+    /// let ray_outside_viewport = camera.get_ray(1.1, 1.0, &rng);
     /// ```
     pub fn get_ray(&self, s: f64, t: f64, rng: &RTRng) -> Ray {
         let rd = self.lens_radius * rng.random_in_unit_disk();
@@ -96,6 +120,13 @@ impl Camera {
 
         let dir =
             self.lower_left_corner + s * self.horizontal + t * self.vertical - self.origin - offset;
-        Ray::new(self.origin + offset, dir)
+        // An instantaneous exposure doesn't use the generator, so still scenes render
+        // the same as before cameras had shutters.
+        let time = if self.shutter_close > self.shutter_open {
+            rng.random_f64_range(self.shutter_open..self.shutter_close)
+        } else {
+            self.shutter_open
+        };
+        Ray::new(self.origin + offset, dir).with_time(time)
     }
 }
@@ -0,0 +1,451 @@
+//! Command-line arguments of the renderer.
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use log::LevelFilter;
+
+use ray_tracing_1::{
+    camera::Config as CameraConfig,
+    geometry::vec3::Vec3,
+    image::{exr, png, ppm, Format},
+    tracer::ImageConfig,
+};
+
+/// Render a ray traced scene.
+///
+/// The scene is loaded from a scene file, or is a random collection of spheres if no
+/// file is given. The image is written to the output path, in the format given by its
+/// extension, or printed to stdout as an ASCII PPM if no path is given.
+#[derive(Debug, Parser)]
+#[command(version)]
+pub struct Args {
+    /// Scene file to render.
+    #[arg(long)]
+    pub scene: Option<PathBuf>,
+
+    /// Save the scene, with the image and camera options applied, to a scene file
+    /// instead of rendering it.
+    #[arg(long, value_name = "PATH")]
+    pub save_scene: Option<PathBuf>,
+
+    /// Path the image is written to.
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// Format of the image, overriding the extension of the output path.
+    #[arg(short, long, value_enum)]
+    pub format: Option<FormatArg>,
+
+    /// Width and height of the image in pixels, overriding the scene file [default:
+    /// 1200x800].
+    #[arg(short, long, value_name = "WIDTHxHEIGHT", value_parser = parse_resolution)]
+    pub resolution: Option<(u32, u32)>,
+
+    /// Number of rays traced through each pixel, overriding the scene file [default:
+    /// 500].
+    #[arg(short, long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub samples: Option<u32>,
+
+    /// Maximum number of times a ray can bounce, overriding the scene file [default:
+    /// 50].
+    #[arg(short = 'd', long)]
+    pub max_depth: Option<u32>,
+
+    /// Number of render threads. Uses every available core if 0.
+    #[arg(short = 'j', long, default_value_t = 0)]
+    pub threads: usize,
+
+    /// Seed for generating the scene and rendering.
+    #[arg(long, default_value_t = 0)]
+    pub seed: u64,
+
+    /// Position of the camera.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3)]
+    pub look_from: Option<Vec3>,
+
+    /// Point the camera looks at.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3)]
+    pub look_at: Option<Vec3>,
+
+    /// Up direction of the camera.
+    #[arg(long, value_name = "X,Y,Z", value_parser = parse_vec3)]
+    pub vup: Option<Vec3>,
+
+    /// Vertical field of view of the camera in degrees.
+    #[arg(long, value_name = "DEGREES", value_parser = parse_vfov)]
+    pub vfov: Option<f64>,
+
+    /// Diameter of the camera aperture. Use 0 for a pinhole camera.
+    #[arg(long, value_parser = parse_non_negative)]
+    pub aperture: Option<f64>,
+
+    /// Distance from the camera at which objects are in focus.
+    #[arg(long, value_parser = parse_positive)]
+    pub focus_dist: Option<f64>,
+
+    /// Times that the camera shutter opens and closes. Moving objects are blurred
+    /// along their path while the shutter is open.
+    #[arg(long, value_name = "OPEN,CLOSE", value_parser = parse_shutter)]
+    pub shutter: Option<(f64, f64)>,
+
+    /// Log more detail. Repeat for even more detail (-vv, -vvv).
+    #[arg(short, long, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+}
+
+/// Image formats that can be chosen on the command line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatArg {
+    /// OpenEXR with zip compression, including AOVs.
+    Exr,
+    /// Portable FloatMap.
+    Pfm,
+    /// PNG with 8 bits per channel.
+    Png,
+    /// PNG with 16 bits per channel.
+    Png16,
+    /// Binary PPM.
+    Ppm,
+    /// ASCII PPM.
+    PpmAscii,
+}
+
+impl From<FormatArg> for Format {
+    fn from(format: FormatArg) -> Self {
+        match format {
+            FormatArg::Exr => Format::Exr(exr::Compression::Zip),
+            FormatArg::Pfm => Format::Pfm,
+            FormatArg::Png => Format::Png(png::BitDepth::Eight),
+            FormatArg::Png16 => Format::Png(png::BitDepth::Sixteen),
+            FormatArg::Ppm => Format::Ppm(ppm::Encoding::Binary),
+            FormatArg::PpmAscii => Format::Ppm(ppm::Encoding::Ascii),
+        }
+    }
+}
+
+impl Args {
+    /// Apply the image arguments to an image configuration.
+    pub fn image_config(&self, defaults: ImageConfig) -> ImageConfig {
+        let (width, height) = self.resolution.unwrap_or((defaults.width, defaults.height));
+        ImageConfig {
+            width,
+            height,
+            samples_per_pixel: self.samples.unwrap_or(defaults.samples_per_pixel),
+            ray_bounce_limit: self.max_depth.unwrap_or(defaults.ray_bounce_limit),
+            threads: self.threads,
+            seed: self.seed,
+            ..defaults
+        }
+    }
+
+    /// The format of the image, from the `--format` argument or the extension of
+    /// the output path.
+    ///
+    /// # Errors
+    /// Returns an error if the format can't be determined from the output path.
+    pub fn format(&self) -> Result<Format, String> {
+        match (self.format, &self.output) {
+            (Some(format), _) => Ok(format.into()),
+            (None, Some(path)) => Format::from_path(path).ok_or_else(|| {
+                format!(
+                    "can't determine the image format of '{}', use --format to choose one",
+                    path.display()
+                )
+            }),
+            (None, None) => Ok(Format::Ppm(ppm::Encoding::Ascii)),
+        }
+    }
+
+    /// Apply the camera arguments to a camera configuration. The aspect ratio is
+    /// taken from the resolution, if it is given.
+    ///
+    /// # Errors
+    /// Returns an error if the resulting camera has no view direction.
+    pub fn camera_config(&self, defaults: CameraConfig) -> Result<CameraConfig, String> {
+        let config = CameraConfig {
+            look_from: self.look_from.unwrap_or(defaults.look_from),
+            look_at: self.look_at.unwrap_or(defaults.look_at),
+            vup: self.vup.unwrap_or(defaults.vup),
+            vfov_degrees: self.vfov.unwrap_or(defaults.vfov_degrees),
+            aspect_ratio: self
+                .resolution
+                .map_or(defaults.aspect_ratio, |(w, h)| w as f64 / h as f64),
+            aperture_diameter: self.aperture.unwrap_or(defaults.aperture_diameter),
+            focus_dist: self.focus_dist.unwrap_or(defaults.focus_dist),
+            shutter_open: self.shutter.map_or(defaults.shutter_open, |(open, _)| open),
+            shutter_close: self
+                .shutter
+                .map_or(defaults.shutter_close, |(_, close)| close),
+        };
+
+        let view = config.look_at - config.look_from;
+        if view.length() == 0.0 {
+            return Err("the camera must look at a point other than its position".into());
+        }
+        if view.cross(config.vup).length() == 0.0 {
+            return Err("the camera's up direction must not be parallel to its view".into());
+        }
+        Ok(config)
+    }
+
+    /// The log level chosen by the verbosity arguments, or `None` if the level
+    /// should be taken from the environment.
+    pub fn log_level(&self) -> Option<LevelFilter> {
+        match self.verbose {
+            0 => None,
+            1 => Some(LevelFilter::Info),
+            2 => Some(LevelFilter::Debug),
+            _ => Some(LevelFilter::Trace),
+        }
+    }
+}
+
+fn parse_resolution(s: &str) -> Result<(u32, u32), String> {
+    let (width, height) = s
+        .split_once('x')
+        .ok_or("expected a resolution like 1200x800")?;
+    let parse = |n: &str| n.trim().parse::<u32>().map_err(|e| e.to_string());
+    let (width, height) = (parse(width)?, parse(height)?);
+    // Pixels are mapped to the viewport by dividing by one less than the size.
+    if width < 2 || height < 2 {
+        return Err("the image must be at least 2 pixels wide and high".into());
+    }
+    Ok((width, height))
+}
+
+fn parse_vec3(s: &str) -> Result<Vec3, String> {
+    let components = s
+        .split(',')
+        .map(|c| c.trim().parse::<f64>().map_err(|e| e.to_string()))
+        .collect::<Result<Vec<_>, _>>()?;
+    match components[..] {
+        [x, y, z] if components.iter().all(|c| c.is_finite()) => Ok(Vec3::new(x, y, z)),
+        [_, _, _] => Err("components must be finite".into()),
+        _ => Err("expected three comma separated numbers".into()),
+    }
+}
+
+fn parse_shutter(s: &str) -> Result<(f64, f64), String> {
+    let (open, close) = s
+        .split_once(',')
+        .ok_or("expected two comma separated times")?;
+    let (open, close) = (parse_f64(open.trim())?, parse_f64(close.trim())?);
+    if open <= close {
+        Ok((open, close))
+    } else {
+        Err("the shutter must open before it closes".into())
+    }
+}
+
+fn parse_f64(s: &str) -> Result<f64, String> {
+    match s.parse::<f64>() {
+        Ok(n) if n.is_finite() => Ok(n),
+        Ok(_) => Err("must be finite".into()),
+        Err(e) => Err(e.to_string()),
+    }
+}
+
+fn parse_vfov(s: &str) -> Result<f64, String> {
+    let vfov = parse_f64(s)?;
+    if vfov > 0.0 && vfov < 180.0 {
+        Ok(vfov)
+    } else {
+        Err("must be between 0 and 180 degrees".into())
+    }
+}
+
+fn parse_non_negative(s: &str) -> Result<f64, String> {
+    let n = parse_f64(s)?;
+    if n >= 0.0 {
+        Ok(n)
+    } else {
+        Err("must not be negative".into())
+    }
+}
+
+fn parse_positive(s: &str) -> Result<f64, String> {
+    let n = parse_f64(s)?;
+    if n > 0.0 {
+        Ok(n)
+    } else {
+        Err("must be positive".into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use clap::{error::ErrorKind, CommandFactory, Parser};
+    use log::LevelFilter;
+    use ray_tracing_1::{
+        background::Solid,
+        camera::Config as CameraConfig,
+        geometry::vec3::Vec3,
+        image::{png, ppm, Format},
+        tracer::ImageConfig,
+    };
+
+    use super::Args;
+
+    fn parse(args: &[&str]) -> Result<Args, clap::Error> {
+        Args::try_parse_from(["ray-tracing-1"].iter().chain(args))
+    }
+
+    fn default_camera() -> CameraConfig {
+        CameraConfig {
+            look_from: Vec3::new(13, 2, 3),
+            look_at: Vec3::new(0, 0, 0),
+            vup: Vec3::new(0, 1, 0),
+            vfov_degrees: 20.0,
+            aspect_ratio: 1.5,
+            aperture_diameter: 0.1,
+            focus_dist: 10.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        }
+    }
+
+    fn default_image() -> ImageConfig {
+        ImageConfig {
+            width: 60,
+            height: 40,
+            samples_per_pixel: 10,
+            ray_bounce_limit: 5,
+            threads: 1,
+            seed: 1,
+            background: Arc::new(Solid::black()),
+        }
+    }
+
+    #[test]
+    fn valid_command() {
+        Args::command().debug_assert();
+    }
+
+    #[test]
+    fn defaults() {
+        let args = parse(&[]).unwrap();
+        assert_eq!(None, args.scene);
+        assert_eq!(None, args.save_scene);
+        assert_eq!(Ok(Format::Ppm(ppm::Encoding::Ascii)), args.format());
+        assert_eq!(None, args.log_level());
+
+        // Scene settings are kept unless overridden.
+        let config = args.image_config(default_image());
+        assert_eq!((60, 40), (config.width, config.height));
+        assert_eq!(10, config.samples_per_pixel);
+        assert_eq!(5, config.ray_bounce_limit);
+        assert_eq!(0, config.threads);
+        assert_eq!(0, config.seed);
+
+        let camera = args.camera_config(default_camera()).unwrap();
+        assert_eq!(1.5, camera.aspect_ratio);
+    }
+
+    #[test]
+    fn render_settings() {
+        let args = parse(&[
+            "--scene",
+            "scene.toml",
+            "-r",
+            "640x480",
+            "-s",
+            "16",
+            "-d",
+            "8",
+            "-j",
+            "3",
+            "--seed",
+            "42",
+            "-vv",
+        ])
+        .unwrap();
+        assert_eq!(Some("scene.toml".into()), args.scene);
+        assert_eq!(Some(LevelFilter::Debug), args.log_level());
+
+        let config = args.image_config(default_image());
+        assert_eq!((640, 480), (config.width, config.height));
+        assert_eq!(16, config.samples_per_pixel);
+        assert_eq!(8, config.ray_bounce_limit);
+        assert_eq!(3, config.threads);
+        assert_eq!(42, config.seed);
+    }
+
+    #[test]
+    fn output_format() {
+        let args = parse(&["-o", "out.png"]).unwrap();
+        assert_eq!(Ok(Format::Png(png::BitDepth::Eight)), args.format());
+
+        let args = parse(&["-o", "out.png", "--format", "png16"]).unwrap();
+        assert_eq!(Ok(Format::Png(png::BitDepth::Sixteen)), args.format());
+
+        let args = parse(&["-o", "out.img"]).unwrap();
+        assert!(args.format().is_err());
+    }
+
+    #[test]
+    fn camera_overrides() {
+        let args = parse(&[
+            "-r",
+            "300x100",
+            "--look-from",
+            "1,2.5,-3",
+            "--vfov",
+            "45",
+            "--aperture",
+            "0",
+            "--shutter",
+            "0.5,1",
+        ])
+        .unwrap();
+        let config = args.camera_config(default_camera()).unwrap();
+
+        assert_eq!(Vec3::new(1, 2.5, -3), config.look_from);
+        assert_eq!(Vec3::new(0, 0, 0), config.look_at);
+        assert_eq!(45.0, config.vfov_degrees);
+        assert_eq!(3.0, config.aspect_ratio);
+        assert_eq!(0.0, config.aperture_diameter);
+        assert_eq!(10.0, config.focus_dist);
+        assert_eq!((0.5, 1.0), (config.shutter_open, config.shutter_close));
+    }
+
+    #[test]
+    fn invalid_camera() {
+        let args = parse(&["--look-from", "0,0,0"]).unwrap();
+        assert!(args.camera_config(default_camera()).is_err());
+
+        let args = parse(&["--vup", "13,2,3"]).unwrap();
+        assert!(args.camera_config(default_camera()).is_err());
+    }
+
+    #[test]
+    fn invalid_values() {
+        for args in [
+            &["-r", "1200"][..],
+            &["-r", "1x800"],
+            &["-r", "axb"],
+            &["-s", "0"],
+            &["-j", "-1"],
+            &["--look-at", "1,2"],
+            &["--look-at", "1,2,inf"],
+            &["--vfov", "180"],
+            &["--aperture", "-0.5"],
+            &["--focus-dist", "0"],
+            &["--shutter", "1"],
+            &["--shutter", "1,0"],
+            &["--format", "jpeg"],
+            &["--unknown"],
+        ] {
+            assert!(parse(args).is_err(), "{args:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn help() {
+        let error = parse(&["--help"]).unwrap_err();
+        assert_eq!(ErrorKind::DisplayHelp, error.kind());
+        assert_eq!(0, error.exit_code());
+        assert_eq!(2, parse(&["-s", "0"]).unwrap_err().exit_code());
+    }
+}
@@ -1,78 +1,139 @@
+//! This module contains the [`Color`] type, used to represent light as it is
+//! traced through a scene.
+use std::ops;
+
 use crate::geometry::vec3::Vec3;
 
-pub const RED: Color = Color {
-    red: 255,
-    green: 0,
-    blue: 0,
-};
-pub const GREEN: Color = Color {
-    red: 0,
-    green: 255,
-    blue: 0,
-};
-pub const BLUE: Color = Color {
-    red: 0,
-    green: 0,
-    blue: 255,
-};
+pub const BLACK: Color = Color::new(0.0, 0.0, 0.0);
+pub const WHITE: Color = Color::new(1.0, 1.0, 1.0);
+pub const RED: Color = Color::new(1.0, 0.0, 0.0);
+pub const GREEN: Color = Color::new(0.0, 1.0, 0.0);
+pub const BLUE: Color = Color::new(0.0, 0.0, 1.0);
 
+/// Linear RGB radiance. Components are unbounded above, so colors brighter than
+/// white can be represented. Colors are only quantized when an image is written.
 #[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Color {
-    pub red: u8,
-    pub green: u8,
-    pub blue: u8,
+    pub red: f64,
+    pub green: f64,
+    pub blue: f64,
 }
 
 impl Color {
+    pub const fn new(red: f64, green: f64, blue: f64) -> Self {
+        Self { red, green, blue }
+    }
+
     /// Scale a color.
     ///
     /// # Panics
-    /// Panics if the scale factor is not in the range [0,1].
+    /// Panics if the scale factor is not in the range `[0, 1]`.
     pub fn scaled(&self, scale: f64) -> Color {
         if !(0.0..=1.0).contains(&scale) {
             panic!("Illegal color scale value");
         }
 
-        Color {
-            red: (self.red as f64 * scale) as u8,
-            green: (self.green as f64 * scale) as u8,
-            blue: (self.blue as f64 * scale) as u8,
-        }
+        *self * scale
+    }
+
+    /// Decode a color encoded for display with gamma 2, with components in the
+    /// range `[0, 1]`. This is the inverse of the encoding applied when quantizing.
+    pub fn from_encoded(red: f64, green: f64, blue: f64) -> Self {
+        Self::new(red * red, green * green, blue * blue)
+    }
+
+    /// The relative luminance of the color.
+    pub fn luminance(&self) -> f64 {
+        0.2126 * self.red + 0.7152 * self.green + 0.0722 * self.blue
+    }
+
+    /// Encode the color for display with gamma 2, and quantize each component to
+    /// 8 bits. Components outside of the displayable range are clamped.
+    pub fn to_rgb8(&self) -> [u8; 3] {
+        self.encoded(u8::MAX as f64).map(|c| c as u8)
+    }
+
+    /// Encode the color for display with gamma 2, and quantize each component to
+    /// 16 bits. Components outside of the displayable range are clamped.
+    pub fn to_rgb16(&self) -> [u16; 3] {
+        self.encoded(u16::MAX as f64).map(|c| c as u16)
+    }
+
+    fn encoded(&self, max: f64) -> [f64; 3] {
+        [self.red, self.green, self.blue].map(|c| (c.max(0.0).sqrt().min(1.0) * max).round())
     }
 }
 
-impl TryFrom<Vec3> for Color {
-    type Error = &'static str;
+impl ops::Add for Color {
+    type Output = Self;
 
-    /// Convert a vector located within the unit cube into a color value. A
-    /// vector (x,y,z) lies in the unit cube if and only if 0 <= x,y,z <= 1.
-    ///
-    /// # Errors
-    /// Returns an error if the input vector is not located in the unit cube.
-    fn try_from(v: Vec3) -> Result<Self, Self::Error> {
-        let (red, green, blue) = (v.x(), v.y(), v.z());
-
-        for v in [red, green, blue] {
-            if !(0.0..=1.0).contains(&v) {
-                return Err("Cannot convert value outside of the unit cube into a Color.");
-            }
-        }
+    fn add(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.red + rhs.red,
+            self.green + rhs.green,
+            self.blue + rhs.blue,
+        )
+    }
+}
 
-        Ok(Color {
-            red: ((red * 255.0) as u8),
-            green: ((green * 255.0) as u8),
-            blue: ((blue * 255.0) as u8),
-        })
+impl ops::AddAssign for Color {
+    fn add_assign(&mut self, rhs: Self) {
+        *self = *self + rhs;
     }
 }
 
-impl From<(u8, u8, u8)> for Color {
-    fn from(value: (u8, u8, u8)) -> Self {
-        Color {
-            red: value.0,
-            green: value.1,
-            blue: value.2,
-        }
+impl ops::Mul for Color {
+    type Output = Self;
+
+    /// Attenuate a color by another, component by component.
+    fn mul(self, rhs: Self) -> Self::Output {
+        Self::new(
+            self.red * rhs.red,
+            self.green * rhs.green,
+            self.blue * rhs.blue,
+        )
+    }
+}
+
+impl ops::Mul<f64> for Color {
+    type Output = Self;
+
+    fn mul(self, rhs: f64) -> Self::Output {
+        Self::new(self.red * rhs, self.green * rhs, self.blue * rhs)
+    }
+}
+
+impl ops::Mul<Color> for f64 {
+    type Output = Color;
+
+    fn mul(self, rhs: Color) -> Self::Output {
+        rhs * self
+    }
+}
+
+impl ops::Div<f64> for Color {
+    type Output = Self;
+
+    fn div(self, rhs: f64) -> Self::Output {
+        Self::new(self.red / rhs, self.green / rhs, self.blue / rhs)
+    }
+}
+
+impl From<Vec3> for Color {
+    /// Interpret the components of a vector as red, green and blue.
+    fn from(v: Vec3) -> Self {
+        Color::new(v.x(), v.y(), v.z())
+    }
+}
+
+impl<T, U, V> From<(T, U, V)> for Color
+where
+    T: Into<f64>,
+    U: Into<f64>,
+    V: Into<f64>,
+{
+    fn from(value: (T, U, V)) -> Self {
+        Color::new(value.0.into(), value.1.into(), value.2.into())
     }
 }
 
@@ -80,7 +141,7 @@ impl From<(u8, u8, u8)> for Color {
 mod tests {
     use rstest::rstest;
 
-    use super::Color;
+    use super::{Color, BLACK, WHITE};
 
     #[test]
     fn scale_color() {
@@ -102,4 +163,45 @@ mod tests {
         let color = Color::from((10, 20, 30));
         color.scaled(scale_factor);
     }
+
+    #[test]
+    fn arithmetic() {
+        let a = Color::new(0.5, 1.0, 2.0);
+        let b = Color::new(2.0, 0.5, 0.25);
+        assert_eq!(Color::new(2.5, 1.5, 2.25), a + b);
+        assert_eq!(Color::new(1.0, 0.5, 0.5), a * b);
+        assert_eq!(Color::new(1.0, 2.0, 4.0), 2.0 * a);
+        assert_eq!(Color::new(0.25, 0.5, 1.0), a / 2.0);
+    }
+
+    #[test]
+    fn values_above_one_are_representable() {
+        let bright = WHITE * 4.0;
+        assert_eq!(Color::new(4.0, 4.0, 4.0), bright);
+        assert_eq!(4.0, bright.luminance());
+    }
+
+    #[test]
+    fn quantize_to_8_bit() {
+        assert_eq!([0, 0, 0], BLACK.to_rgb8());
+        assert_eq!([255, 255, 255], WHITE.to_rgb8());
+        assert_eq!([255, 0, 255], Color::new(100.0, -1.0, 1.0).to_rgb8());
+
+        // Gamma 2 encoding: 0.25 is displayed at half intensity.
+        assert_eq!([128, 128, 128], Color::new(0.25, 0.25, 0.25).to_rgb8());
+    }
+
+    #[test]
+    fn decode_quantized() {
+        for value in 0..=255u8 {
+            let encoded = value as f64 / 255.0;
+            let color = Color::from_encoded(encoded, encoded, encoded);
+            assert_eq!([value; 3], color.to_rgb8());
+        }
+    }
+
+    #[test]
+    fn quantize_to_16_bit() {
+        assert_eq!([0, 65535, 32768], Color::new(0.0, 1.0, 0.25).to_rgb16());
+    }
 }
@@ -1,6 +1,27 @@
 //! Module to assist with geometric calculations.
 
+pub mod aabb;
+pub mod bvh;
+pub mod cone;
+pub mod constant_medium;
+pub mod csg;
+pub mod cuboid;
+pub mod cylinder;
+pub mod density;
+pub mod disk;
+pub mod frame;
+pub mod heterogeneous_medium;
 pub mod hittable;
+pub mod mat4;
+pub mod mesh;
+pub mod moving_sphere;
+pub mod plane;
+pub mod polynomial;
+pub mod quad;
 pub mod ray;
+pub mod sdf;
 pub mod sphere;
+pub mod torus;
+pub mod transformed;
+pub mod triangle;
 pub mod vec3;
@@ -0,0 +1,174 @@
+//! This module contains the [`Aabb`] type.
+use super::{ray::Ray, vec3::Vec3};
+
+/// The distance [`Aabb::padded`] grows boxes by.
+const PADDING: f64 = 1e-9;
+
+/// Axis-aligned bounding box, described by its minimum and maximum corners.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    min: Vec3,
+    max: Vec3,
+}
+
+impl Aabb {
+    /// Create the smallest box containing the two given points.
+    pub fn new(a: Vec3, b: Vec3) -> Self {
+        Self {
+            min: Vec3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Vec3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+    }
+
+    /// The corner of the box with the smallest coordinates.
+    pub fn min(&self) -> Vec3 {
+        self.min
+    }
+
+    /// The corner of the box with the largest coordinates.
+    pub fn max(&self) -> Vec3 {
+        self.max
+    }
+
+    /// The smallest box containing both boxes.
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: Self::new(self.min, other.min).min,
+            max: Self::new(self.max, other.max).max,
+        }
+    }
+
+    /// The smallest box containing the box and a point.
+    pub fn union_point(&self, point: Vec3) -> Self {
+        self.union(&Self::new(point, point))
+    }
+
+    /// Grow the box slightly in every direction, so that flat primitives lying in an
+    /// axis-aligned plane have boxes with some thickness.
+    pub fn padded(&self) -> Self {
+        let padding = Vec3::new(PADDING, PADDING, PADDING);
+        Self {
+            min: self.min - padding,
+            max: self.max + padding,
+        }
+    }
+
+    pub fn centroid(&self) -> Vec3 {
+        0.5 * (self.min + self.max)
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        let d = self.max - self.min;
+        2.0 * (d.x() * d.y() + d.y() * d.z() + d.z() * d.x())
+    }
+
+    /// The index of the axis along which the box is longest.
+    pub fn longest_axis(&self) -> usize {
+        let d = self.max - self.min;
+        if d.x() >= d.y() && d.x() >= d.z() {
+            0
+        } else if d.y() >= d.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// Determine whether a [`Ray`] passes through the box for some `t` in the range
+    /// `(t_min, t_max)`, using the slab method.
+    pub fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> bool {
+        self.interval(ray, t_min, t_max).is_some()
+    }
+
+    /// The range of `t` within `(t_min, t_max)` for which a [`Ray`] is inside the
+    /// box, or `None` if it misses the box.
+    pub fn interval(&self, ray: &Ray, mut t_min: f64, mut t_max: f64) -> Option<(f64, f64)> {
+        let origin = ray.origin();
+        let direction = ray.direction();
+        for axis in 0..3 {
+            let inv_d = 1.0 / direction[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            // `f64::max` and `f64::min` ignore NaN, which occurs when the ray lies
+            // exactly in the plane of a slab.
+            t_min = t_min.max(t0);
+            t_max = t_max.min(t1);
+            if t_max < t_min {
+                return None;
+            }
+        }
+
+        Some((t_min, t_max))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{ray::Ray, vec3::Vec3};
+    use super::Aabb;
+
+    #[test]
+    fn new_orders_corners() {
+        let aabb = Aabb::new(Vec3::new(1, -1, 3), Vec3::new(-1, 2, 0));
+        assert_eq!(Vec3::new(-1, -1, 0), aabb.min());
+        assert_eq!(Vec3::new(1, 2, 3), aabb.max());
+    }
+
+    #[test]
+    fn union() {
+        let a = Aabb::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+        let b = Aabb::new(Vec3::new(-1, 0.5, 0.5), Vec3::new(0.5, 3, 0.5));
+        let u = a.union(&b);
+        assert_eq!(Vec3::new(-1, 0, 0), u.min());
+        assert_eq!(Vec3::new(1, 3, 1), u.max());
+    }
+
+    #[test]
+    fn surface_area_and_axis() {
+        let aabb = Aabb::new(Vec3::new(0, 0, 0), Vec3::new(1, 2, 3));
+        assert_eq!(22.0, aabb.surface_area());
+        assert_eq!(2, aabb.longest_axis());
+        assert_eq!(Vec3::new(0.5, 1, 1.5), aabb.centroid());
+    }
+
+    #[test]
+    fn ray_hits_and_misses() {
+        let aabb = Aabb::new(Vec3::new(-1, -1, 4), Vec3::new(1, 1, 6));
+        let hit_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 1, 1));
+
+        assert!(aabb.hit(&hit_ray, 0.0, f64::INFINITY));
+        assert!(!aabb.hit(&miss_ray, 0.0, f64::INFINITY));
+
+        assert_eq!(
+            Some((4.0, 6.0)),
+            aabb.interval(&hit_ray, 0.0, f64::INFINITY)
+        );
+        assert_eq!(
+            Some((5.0, 6.0)),
+            aabb.interval(&hit_ray, 5.0, f64::INFINITY)
+        );
+        assert_eq!(None, aabb.interval(&miss_ray, 0.0, f64::INFINITY));
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let aabb = Aabb::new(Vec3::new(-1, -1, 4), Vec3::new(1, 1, 6));
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(!aabb.hit(&ray, 0.0, 3.9));
+        assert!(!aabb.hit(&ray, 6.1, 10.0));
+        assert!(aabb.hit(&ray, 5.0, 5.5));
+    }
+
+    #[test]
+    fn ray_in_slab_plane() {
+        let aabb = Aabb::new(Vec3::new(-1, -1, 4), Vec3::new(1, 1, 6));
+        let ray = Ray::new(Vec3::new(1, 0, 0), Vec3::new(0, 0, 1));
+        assert!(aabb.hit(&ray, 0.0, f64::INFINITY));
+    }
+}
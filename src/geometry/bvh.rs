@@ -0,0 +1,552 @@
+//! A bounding volume hierarchy (BVH) is a tree of nested [`Aabb`]s used to avoid
+//! testing a ray against every object in a scene. A ray only needs to be tested
+//! against the objects whose bounding boxes it passes through.
+use std::sync::Arc;
+
+use crate::utils::rand::RTRng;
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// The largest number of primitives stored in a leaf node.
+const MAX_LEAF_SIZE: usize = 4;
+
+/// The number of buckets primitive centroids are sorted into when evaluating
+/// the surface area heuristic.
+const SAH_BUCKETS: usize = 12;
+
+/// A node of a [`Bvh`]. Nodes are stored in depth-first order, so the first child
+/// of an interior node immediately follows it.
+#[derive(Debug)]
+pub enum BvhNode {
+    Interior {
+        bbox: Aabb,
+        /// The index of the second child node.
+        second_child: usize,
+        /// The axis the children were split along.
+        axis: usize,
+    },
+    Leaf {
+        bbox: Aabb,
+        /// The position of the leaf's first primitive in [`Bvh::primitive_indices`].
+        first: usize,
+        count: usize,
+    },
+}
+
+impl BvhNode {
+    pub fn bbox(&self) -> &Aabb {
+        match self {
+            BvhNode::Interior { bbox, .. } | BvhNode::Leaf { bbox, .. } => bbox,
+        }
+    }
+}
+
+/// A bounding volume hierarchy over a set of primitives, built using the surface
+/// area heuristic (SAH).
+///
+/// The hierarchy only stores the indices of the primitives it was built from, so it
+/// can accelerate any collection of primitives that can be hit individually.
+#[derive(Debug, Default)]
+pub struct Bvh {
+    nodes: Vec<BvhNode>,
+    primitive_indices: Vec<usize>,
+}
+
+struct BuildPrimitive {
+    index: usize,
+    bbox: Aabb,
+    centroid: Vec3,
+}
+
+impl Bvh {
+    /// Build a hierarchy over primitives with the given bounding boxes. Primitives
+    /// are referred to by their index in `boxes`.
+    pub fn new(boxes: &[Aabb]) -> Self {
+        let mut primitives: Vec<_> = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, bbox)| BuildPrimitive {
+                index,
+                bbox: *bbox,
+                centroid: bbox.centroid(),
+            })
+            .collect();
+
+        let mut bvh = Self {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+            primitive_indices: Vec::with_capacity(boxes.len()),
+        };
+        if !primitives.is_empty() {
+            bvh.build(&mut primitives);
+        }
+        bvh
+    }
+
+    /// The bounding box of all primitives in the hierarchy, or `None` if it is empty.
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        self.nodes.first().map(|node| *node.bbox())
+    }
+
+    /// The nodes of the hierarchy in depth-first order.
+    pub fn nodes(&self) -> &[BvhNode] {
+        &self.nodes
+    }
+
+    /// Primitive indices referred to by the leaves of the hierarchy.
+    pub fn primitive_indices(&self) -> &[usize] {
+        &self.primitive_indices
+    }
+
+    /// Recursively build the subtree containing `primitives` and return the index
+    /// of its root node.
+    fn build(&mut self, primitives: &mut [BuildPrimitive]) -> usize {
+        let bbox = primitives
+            .iter()
+            .skip(1)
+            .fold(primitives[0].bbox, |acc, p| acc.union(&p.bbox));
+
+        let node_index = self.nodes.len();
+        let centroid_bounds = primitives.iter().fold(
+            Aabb::new(primitives[0].centroid, primitives[0].centroid),
+            |acc, p| acc.union_point(p.centroid),
+        );
+        let axis = centroid_bounds.longest_axis();
+        let (c_min, c_max) = (centroid_bounds.min()[axis], centroid_bounds.max()[axis]);
+
+        // Primitives with coincident centroids cannot be separated, so they are
+        // placed in a single leaf.
+        if primitives.len() <= MAX_LEAF_SIZE || c_max <= c_min {
+            self.push_leaf(bbox, primitives);
+            return node_index;
+        }
+
+        let bucket_of = |p: &BuildPrimitive| {
+            let b = ((p.centroid[axis] - c_min) / (c_max - c_min) * SAH_BUCKETS as f64) as usize;
+            b.min(SAH_BUCKETS - 1)
+        };
+
+        let mut bucket_counts = [0usize; SAH_BUCKETS];
+        let mut bucket_boxes: [Option<Aabb>; SAH_BUCKETS] = [None; SAH_BUCKETS];
+        for p in primitives.iter() {
+            let b = bucket_of(p);
+            bucket_counts[b] += 1;
+            bucket_boxes[b] = Some(bucket_boxes[b].map_or(p.bbox, |bb| bb.union(&p.bbox)));
+        }
+
+        // Pick the split between buckets that minimizes the surface area heuristic.
+        let union_all = |boxes: &[Option<Aabb>]| {
+            boxes.iter().flatten().fold(None, |acc: Option<Aabb>, b| {
+                Some(acc.map_or(*b, |a| a.union(b)))
+            })
+        };
+        let cost = |split: usize| {
+            let left_count: usize = bucket_counts[..split].iter().sum();
+            let right_count: usize = bucket_counts[split..].iter().sum();
+            match (
+                union_all(&bucket_boxes[..split]),
+                union_all(&bucket_boxes[split..]),
+            ) {
+                (Some(left), Some(right)) => {
+                    left.surface_area() * left_count as f64
+                        + right.surface_area() * right_count as f64
+                }
+                _ => f64::INFINITY,
+            }
+        };
+        let split = (1..SAH_BUCKETS)
+            .min_by(|&a, &b| cost(a).total_cmp(&cost(b)))
+            .expect("There is more than one bucket");
+
+        let mut mid = 0;
+        for i in 0..primitives.len() {
+            if bucket_of(&primitives[i]) < split {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+
+        // Reserve the interior node; the second child index is filled in once the
+        // first subtree has been built.
+        self.nodes.push(BvhNode::Interior {
+            bbox,
+            second_child: 0,
+            axis,
+        });
+        let (left, right) = primitives.split_at_mut(mid);
+        self.build(left);
+        let second = self.build(right);
+        if let BvhNode::Interior { second_child, .. } = &mut self.nodes[node_index] {
+            *second_child = second;
+        }
+
+        node_index
+    }
+
+    fn push_leaf(&mut self, bbox: Aabb, primitives: &[BuildPrimitive]) {
+        self.nodes.push(BvhNode::Leaf {
+            bbox,
+            first: self.primitive_indices.len(),
+            count: primitives.len(),
+        });
+        self.primitive_indices
+            .extend(primitives.iter().map(|p| p.index));
+    }
+
+    /// Find the closest hit of a ray with the primitives in the hierarchy.
+    ///
+    /// `hit_primitive` is called with a primitive index and the current `(t_min, t_max)`
+    /// range for each primitive whose bounding box the ray passes through.
+    pub fn hit<F>(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        mut t_max: f64,
+        mut hit_primitive: F,
+    ) -> Option<HitRecord>
+    where
+        F: FnMut(usize, f64, f64) -> Option<HitRecord>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let mut closest = None;
+        let mut stack = vec![0];
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bbox().hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { first, count, .. } => {
+                    for &index in &self.primitive_indices[first..first + count] {
+                        if let Some(hr) = hit_primitive(index, t_min, t_max) {
+                            t_max = hr.t;
+                            closest = Some(hr);
+                        }
+                    }
+                }
+                BvhNode::Interior {
+                    second_child, axis, ..
+                } => {
+                    // Visit the child nearest the ray origin first, so that farther
+                    // nodes are more likely to be culled by a smaller `t_max`.
+                    if ray.direction()[axis] < 0.0 {
+                        stack.push(node_index + 1);
+                        stack.push(second_child);
+                    } else {
+                        stack.push(second_child);
+                        stack.push(node_index + 1);
+                    }
+                }
+            }
+        }
+
+        closest
+    }
+
+    /// Call `visit_primitive` with the index of each primitive whose bounding box a
+    /// ray passes through between `t_min` and `t_max`. Stops early if
+    /// `visit_primitive` returns `false`.
+    pub fn visit<F>(&self, ray: &Ray, t_min: f64, t_max: f64, mut visit_primitive: F)
+    where
+        F: FnMut(usize) -> bool,
+    {
+        let mut stack = if self.nodes.is_empty() {
+            vec![]
+        } else {
+            vec![0]
+        };
+        while let Some(node_index) = stack.pop() {
+            let node = &self.nodes[node_index];
+            if !node.bbox().hit(ray, t_min, t_max) {
+                continue;
+            }
+
+            match *node {
+                BvhNode::Leaf { first, count, .. } => {
+                    for &index in &self.primitive_indices[first..first + count] {
+                        if !visit_primitive(index) {
+                            return;
+                        }
+                    }
+                }
+                BvhNode::Interior { second_child, .. } => {
+                    stack.push(second_child);
+                    stack.push(node_index + 1);
+                }
+            }
+        }
+    }
+}
+
+/// A collection of hittables accelerated by a [`Bvh`]. Hittables without a bounding
+/// box are tested individually against every ray.
+pub struct HittableBvh {
+    hittables: Vec<Arc<dyn Hittable>>,
+    /// Indices of bounded hittables, in the order the [`Bvh`] refers to them.
+    bounded: Vec<usize>,
+    unbounded: Vec<usize>,
+    bvh: Bvh,
+}
+
+impl HittableBvh {
+    pub fn new(hittables: Vec<Arc<dyn Hittable>>) -> Self {
+        let mut bounded = vec![];
+        let mut boxes = vec![];
+        let mut unbounded = vec![];
+        for (i, hittable) in hittables.iter().enumerate() {
+            match hittable.bounding_box() {
+                Some(bbox) => {
+                    bounded.push(i);
+                    boxes.push(bbox);
+                }
+                None => unbounded.push(i),
+            }
+        }
+
+        Self {
+            bvh: Bvh::new(&boxes),
+            hittables,
+            bounded,
+            unbounded,
+        }
+    }
+
+    /// The hittables in the collection, in the order they were provided.
+    pub fn hittables(&self) -> &[Arc<dyn Hittable>] {
+        &self.hittables
+    }
+
+    /// Find the closest hit of a ray, along with the index of the hittable that was
+    /// hit in [`HittableBvh::hittables`].
+    pub fn hit_with_index(
+        &self,
+        ray: &Ray,
+        t_min: f64,
+        mut t_max: f64,
+    ) -> Option<(usize, HitRecord)> {
+        let mut closest = None;
+        for &i in &self.unbounded {
+            if let Some(hr) = self.hittables[i].hit(ray, t_min, t_max) {
+                t_max = hr.t;
+                closest = Some((i, hr));
+            }
+        }
+
+        // Each hit found in the hierarchy is closer than the last.
+        let mut closest_index = None;
+        let bvh_hit = self.bvh.hit(ray, t_min, t_max, |index, t_min, t_max| {
+            let hittable_index = self.bounded[index];
+            let hr = self.hittables[hittable_index].hit(ray, t_min, t_max)?;
+            closest_index = Some(hittable_index);
+            Some(hr)
+        });
+
+        match (bvh_hit, closest_index) {
+            (Some(hr), Some(i)) => Some((i, hr)),
+            _ => closest,
+        }
+    }
+}
+
+impl Hittable for HittableBvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.hit_with_index(ray, t_min, t_max).map(|(_, hr)| hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        if self.unbounded.is_empty() {
+            self.bvh.bounding_box()
+        } else {
+            None
+        }
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, rng: &RTRng) -> f64 {
+        let mut transmittance = 1.0;
+        for &i in &self.unbounded {
+            transmittance *= self.hittables[i].transmittance(ray, t_min, t_max, rng);
+        }
+        self.bvh.visit(ray, t_min, t_max, |index| {
+            if transmittance > 0.0 {
+                let hittable = &self.hittables[self.bounded[index]];
+                transmittance *= hittable.transmittance(ray, t_min, t_max, rng);
+            }
+            transmittance > 0.0
+        });
+        transmittance
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Bvh, BvhNode, HittableBvh};
+    use crate::{
+        geometry::{
+            aabb::Aabb, constant_medium::ConstantMedium, hittable::Hittable, ray::Ray,
+            sphere::Sphere, vec3::Vec3,
+        },
+        material::{Isotropic, Lambertian, Material},
+        utils::rand::RTRng,
+    };
+
+    fn random_spheres(rng: &RTRng, count: usize) -> Vec<Arc<dyn Hittable>> {
+        (0..count)
+            .map(|_| {
+                let material: Arc<dyn Material> = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+                let center = rng.random_vec3_range(-20.0..20.0);
+                let radius = rng.random_f64_range(0.1..3.0);
+                Arc::new(Sphere::new(center, radius, material)) as Arc<dyn Hittable>
+            })
+            .collect()
+    }
+
+    #[test]
+    fn empty_bvh() {
+        let bvh = HittableBvh::new(vec![]);
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(bvh.hit(&ray, 0.0, f64::INFINITY).is_none());
+        assert!(bvh.bounding_box().is_none());
+    }
+
+    #[test]
+    fn leaves_contain_every_primitive_once() {
+        let rng = RTRng::new();
+        let boxes: Vec<_> = (0..100)
+            .map(|_| {
+                let p = rng.random_vec3_range(-10.0..10.0);
+                Aabb::new(p, p + rng.random_vec3())
+            })
+            .collect();
+        let bvh = Bvh::new(&boxes);
+
+        let mut indices = bvh.primitive_indices().to_vec();
+        indices.sort();
+        assert_eq!((0..100).collect::<Vec<_>>(), indices);
+
+        // Every leaf bounds its primitives.
+        for node in bvh.nodes() {
+            if let BvhNode::Leaf { bbox, first, count } = node {
+                for &i in &bvh.primitive_indices()[*first..first + count] {
+                    assert_eq!(*bbox, bbox.union(&boxes[i]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn coincident_primitives() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+        let spheres: Vec<Arc<dyn Hittable>> = (0..20)
+            .map(|_| {
+                Arc::new(Sphere::new(Vec3::new(0, 0, 5), 1, Arc::clone(&material)))
+                    as Arc<dyn Hittable>
+            })
+            .collect();
+        let bvh = HittableBvh::new(spheres);
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        let hr = bvh.hit(&ray, 0.0, f64::INFINITY).expect("Ray should hit");
+        assert_eq!(4.0, hr.t);
+    }
+
+    #[test]
+    fn index_of_closest_hittable() {
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+        let spheres: Vec<Arc<dyn Hittable>> = (0..10)
+            .map(|i| {
+                Arc::new(Sphere::new(
+                    Vec3::new(i, 0, 10),
+                    0.25,
+                    Arc::clone(&material),
+                )) as Arc<dyn Hittable>
+            })
+            .collect();
+        let bvh = HittableBvh::new(spheres);
+
+        for i in 0..10 {
+            let ray = Ray::new(Vec3::new(i, 0, 0), Vec3::new(0, 0, 1));
+            let (index, hr) = bvh.hit_with_index(&ray, 0.0, f64::INFINITY).unwrap();
+            assert_eq!(i as usize, index);
+            assert_eq!(9.75, hr.t);
+        }
+    }
+
+    #[test]
+    fn same_closest_hit_as_linear_search() {
+        let rng = RTRng::new();
+        for count in [1, 2, 5, 50, 500] {
+            let spheres = random_spheres(&rng, count);
+            let bvh = HittableBvh::new(spheres.clone());
+
+            for _ in 0..2000 {
+                let origin = rng.random_vec3_range(-30.0..30.0);
+                let direction = rng.random_in_unit_sphere();
+                let ray = Ray::new(origin, direction);
+
+                let expected = spheres.as_slice().hit(&ray, 0.001, f64::INFINITY);
+                let actual = bvh.hit(&ray, 0.001, f64::INFINITY);
+                match (expected, actual) {
+                    (None, None) => {}
+                    (Some(expected), Some(actual)) => {
+                        assert_eq!(expected.t, actual.t);
+                        assert_eq!(expected.point, actual.point);
+                        assert_eq!(expected.normal, actual.normal);
+                        assert_eq!(expected.front_face, actual.front_face);
+                        assert!(Arc::ptr_eq(&expected.material, &actual.material));
+                    }
+                    (expected, actual) => panic!(
+                        "BVH disagrees with linear search: expected hit {}, got hit {}",
+                        expected.is_some(),
+                        actual.is_some()
+                    ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn same_transmittance_as_linear_search() {
+        // Half of the spheres are filled with fog, which lets some light through.
+        let rng = RTRng::new();
+        let hittables: Vec<Arc<dyn Hittable>> = random_spheres(&rng, 100)
+            .into_iter()
+            .enumerate()
+            .map(|(i, sphere)| match i % 2 {
+                0 => sphere,
+                _ => Arc::new(ConstantMedium::new(
+                    sphere,
+                    0.1,
+                    Arc::new(Isotropic::new(0.5, 0.5, 0.5)),
+                )),
+            })
+            .collect();
+        let bvh = HittableBvh::new(hittables.clone());
+
+        let mut partly_shadowed = 0;
+        for _ in 0..2000 {
+            let origin = rng.random_vec3_range(-30.0..30.0);
+            let ray = Ray::new(origin, rng.random_in_unit_sphere());
+
+            let expected = hittables
+                .as_slice()
+                .transmittance(&ray, 0.001, f64::INFINITY, &rng);
+            let actual = bvh.transmittance(&ray, 0.001, f64::INFINITY, &rng);
+            assert!((expected - actual).abs() < 1e-12, "{expected} {actual}");
+            if expected > 0.0 && expected < 1.0 {
+                partly_shadowed += 1;
+            }
+        }
+        assert!(partly_shadowed > 0);
+    }
+}
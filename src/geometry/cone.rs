@@ -0,0 +1,210 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    disk,
+    frame::{self, Frame},
+    hittable::{HitRecord, Hittable},
+    polynomial,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A circular cone, narrowing from a base of `radius` to a point at its apex. A
+/// capped cone is solid, and an uncapped cone is open at its base.
+#[derive(Debug)]
+pub struct Cone {
+    pub base: Vec3,
+    pub apex: Vec3,
+    pub radius: f64,
+
+    /// Whether the base of the cone is closed with a disk.
+    pub capped: bool,
+
+    pub material: Arc<dyn Material>,
+}
+
+impl Cone {
+    /// Create a capped cone.
+    pub fn new<T: Into<f64>>(
+        base: Vec3,
+        apex: Vec3,
+        radius: T,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            base,
+            apex,
+            radius: radius.into(),
+            capped: true,
+            material,
+        }
+    }
+
+    /// Leave the base of the cone open.
+    pub fn uncapped(self) -> Self {
+        Self {
+            capped: false,
+            ..self
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        Frame::from_axis(self.base, self.apex - self.base)
+    }
+}
+
+impl Hittable for Cone {
+    /// On the side, texture coordinates are the angle about the axis as a fraction
+    /// of a turn, and the height as a fraction of the cone's height. The base has
+    /// polar coordinates, like a [`super::disk::Disk`].
+    fn hit(&self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<HitRecord> {
+        let frame = self.frame();
+        let height = (self.apex - self.base).length();
+        let local = frame.ray_to_local(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        // The closest hit, with its local outward normal and texture coordinates.
+        let mut closest = None;
+
+        // The side is where `x² + y² = (k (height - z))²`, and `k` is the slope of the
+        // radius with height.
+        let k2 = (self.radius / height).powi(2);
+        let oz = height - o.z();
+        let a = d.x() * d.x() + d.y() * d.y() - k2 * d.z() * d.z();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y() + k2 * oz * d.z());
+        let c = o.x() * o.x() + o.y() * o.y() - k2 * oz * oz;
+        for &t in polynomial::quadratic_roots(a, b, c).iter() {
+            let p = local.at(t);
+            // The equation also describes a mirrored cone beyond the apex.
+            if t > t_min && t < t_max && (0.0..=height).contains(&p.z()) {
+                let gradient = Vec3::new(p.x(), p.y(), k2 * (height - p.z()));
+                let normal = if gradient.near_zero() {
+                    Vec3::new(0, 0, 1)
+                } else {
+                    gradient.normalized()
+                };
+                closest = Some((t, normal, (frame::turns(p.y(), p.x()), p.z() / height)));
+                t_max = t;
+                break;
+            }
+        }
+
+        if self.capped {
+            if let Some((t, uv)) = disk::intersect_local(&local, 0.0, self.radius, t_min, t_max) {
+                closest = Some((t, Vec3::new(0, 0, -1), uv));
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        let outward_normal = frame.vector_to_world(normal);
+        let mut hr = HitRecord::new(ray.at(t), outward_normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, outward_normal);
+        hr.uv = Some(uv);
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (r, height) = (self.radius, (self.apex - self.base).length());
+        let local = Aabb::new(Vec3::new(-r, -r, 0), Vec3::new(r, r, height));
+        Some(self.frame().bounding_box(local))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Cone {
+            base: description::tuple(self.base),
+            apex: description::tuple(self.apex),
+            radius: self.radius,
+            capped: self.capped,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Cone;
+    use crate::material::Lambertian;
+
+    /// A cone with its base of radius 1 about (0, -1, 1), and its apex at (0, 1, 1),
+    /// so its side crosses the z axis at z = 0.5.
+    fn test_cone() -> Cone {
+        Cone::new(
+            Vec3::new(0, -1, 1),
+            Vec3::new(0, 1, 1),
+            1,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_misses_cone() {
+        let cone = test_cone();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+        assert!(cone.hit(&miss_ray, -10.0, 10.0).is_none());
+
+        // The mirrored cone beyond the apex isn't part of the cone.
+        let above_ray = Ray::new(Vec3::new(0, 2, 0), Vec3::new(0, 0, 1));
+        assert!(cone.hit(&above_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_cone_bullseye() {
+        let cone = test_cone();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        let hr = cone
+            .hit(&bullseye_ray, -1.0, 1.0)
+            .expect("Ray should hit cone");
+        assert_eq!(0.5, hr.t);
+        assert_eq!(Vec3::new(0, 0, 0.5), hr.point);
+        let expected = Vec3::new(0, 1, -2).normalized();
+        assert!((hr.normal - expected).length() < 1e-12);
+        assert_eq!(0.5, hr.uv.unwrap().1);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let cone = test_cone();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(cone.hit(&bullseye_ray, -1.0, 0.4999).is_none());
+        assert!(cone.hit(&bullseye_ray, -1.0, 0.5001).is_some());
+    }
+
+    #[test]
+    fn ray_scrapes_cone() {
+        let cone = test_cone();
+        // Rays parallel to the side, just inside and outside it.
+        let scrape_ray = |x: f64| Ray::new(Vec3::new(x, -3, 1), Vec3::new(-1, 2, 0));
+
+        assert!(cone.hit(&scrape_ray(1.9999), -10.0, 10.0).is_some());
+        assert!(cone.hit(&scrape_ray(2.0001), -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn base_cap() {
+        let up_ray = Ray::new(Vec3::new(0.5, -3, 1), Vec3::new(0, 1, 0));
+
+        let hr = test_cone().hit(&up_ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert!((hr.normal - Vec3::new(0, -1, 0)).length() < 1e-12);
+        assert_eq!(Some(true), hr.front_face);
+
+        // Without a cap, the ray hits the inside of the side.
+        let hr = test_cone()
+            .uncapped()
+            .hit(&up_ray, 0.0, f64::INFINITY)
+            .unwrap();
+        assert_eq!(3.0, hr.t);
+        assert_eq!(Some(false), hr.front_face);
+    }
+}
@@ -0,0 +1,279 @@
+//! This module contains the [`ConstantMedium`] type, for volumes such as smoke and
+//! fog that scatter light throughout them rather than at a surface.
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{NameMaterial, ObjectDescription},
+    utils::rand::{self, RTRng},
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+/// A volume of constant density inside a boundary. Rays passing through it are
+/// scattered at a random distance, which is exponentially distributed, so that
+/// denser volumes scatter rays sooner and let less light through. Rays that don't
+/// scatter pass through as if the volume wasn't there.
+///
+/// The boundary must be convex, such as a sphere or a box, so that rays cross it
+/// at most twice. Its material isn't used.
+pub struct ConstantMedium {
+    pub boundary: Arc<dyn Hittable>,
+
+    /// The chance of scattering per unit distance travelled in the volume.
+    pub density: f64,
+
+    /// The material of points where rays scatter, usually a phase function such as
+    /// [`crate::material::Isotropic`].
+    pub phase_function: Arc<dyn Material>,
+}
+
+impl ConstantMedium {
+    /// # Panics
+    /// Panics if `density` isn't positive.
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: f64,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        assert!(density > 0.0, "media must have a positive density");
+        Self {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+}
+
+impl Debug for ConstantMedium {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ConstantMedium")
+            .field("density", &self.density)
+            .field("phase_function", &self.phase_function)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hittable for ConstantMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = inside(self.boundary.as_ref(), ray, t_min, t_max)?;
+
+        // `hit` has no random number generator, so the ray itself is hashed. The
+        // same ray always scatters at the same distance.
+        let random = rand::hash_f64(&ray_hash_values(ray, t_enter, self.density));
+
+        let speed = ray.direction().length();
+        let distance = -(1.0 - random).ln() / self.density;
+        if distance > (t_exit - t_enter) * speed {
+            return None;
+        }
+        Some(scattering_hit(
+            ray,
+            t_enter + distance / speed,
+            &self.phase_function,
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+
+    /// The light passing through a constant density is known exactly, so no random
+    /// estimate is needed.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, _rng: &RTRng) -> f64 {
+        match inside(self.boundary.as_ref(), ray, t_min, t_max) {
+            Some((t_enter, t_exit)) => {
+                let distance = (t_exit - t_enter) * ray.direction().length();
+                (-self.density * distance).exp()
+            }
+            None => 1.0,
+        }
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::ConstantMedium {
+            boundary: Box::new(self.boundary.describe(name_material)?),
+            density: self.density,
+            material: name_material(&self.phase_function)?,
+        })
+    }
+}
+
+/// The range of `t` within `(t_min, t_max)` for which a ray is inside a convex
+/// boundary, or `None` if it doesn't pass through it in that range.
+pub(crate) fn inside(
+    boundary: &dyn Hittable,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64)> {
+    // Where the whole line of the ray is inside the boundary, including any of it
+    // behind the ray's origin, for rays starting inside the volume.
+    let enter = boundary.hit(ray, f64::NEG_INFINITY, f64::INFINITY)?;
+    let exit = boundary.hit(ray, enter.t + 0.0001, f64::INFINITY)?;
+    let (t_enter, t_exit) = (enter.t.max(t_min), exit.t.min(t_max));
+    (t_enter < t_exit).then_some((t_enter, t_exit))
+}
+
+/// The values hashed in place of random numbers for a ray passing through a
+/// medium, which it enters at `t_enter`. Media that the same ray passes through are
+/// entered at different points, so they draw independent numbers. `salt` tells apart
+/// media entered at the same point.
+pub(crate) fn ray_hash_values(ray: &Ray, t_enter: f64, salt: f64) -> [f64; 9] {
+    let (origin, direction) = (ray.origin(), ray.direction());
+    [
+        t_enter,
+        origin.x(),
+        origin.y(),
+        origin.z(),
+        direction.x(),
+        direction.y(),
+        direction.z(),
+        ray.time(),
+        salt,
+    ]
+}
+
+/// The hit where a ray scatters inside a medium at `t`. There is no surface, so the
+/// normal faces against the ray, and phase functions take the ray's direction from
+/// it.
+pub(crate) fn scattering_hit(ray: &Ray, t: f64, material: &Arc<dyn Material>) -> HitRecord {
+    let normal = -ray.direction().normalized();
+    let mut hr = HitRecord::new(ray.at(t), normal, t, Arc::clone(material));
+    hr.front_face = Some(true);
+    hr
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use rstest::rstest;
+
+    use super::super::{cuboid::Cuboid, hittable::Hittable, ray::Ray, sphere::Sphere, vec3::Vec3};
+    use super::ConstantMedium;
+    use crate::{
+        material::{Isotropic, Lambertian},
+        utils::rand::RTRng,
+    };
+
+    fn test_medium(density: f64) -> ConstantMedium {
+        let boundary = Sphere::new(
+            Vec3::new(0, 0, 0),
+            1,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        );
+        ConstantMedium::new(
+            Arc::new(boundary),
+            density,
+            Arc::new(Isotropic::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[rstest]
+    #[should_panic(expected = "media must have a positive density")]
+    #[case(0.0)]
+    #[should_panic(expected = "media must have a positive density")]
+    #[case(-1.0)]
+    #[should_panic(expected = "media must have a positive density")]
+    #[case(f64::NAN)]
+    #[case(1e-9)]
+    fn density_must_be_positive(#[case] density: f64) {
+        test_medium(density);
+    }
+
+    /// Rays along the x axis from `x = -5`, slightly apart so that they scatter at
+    /// different distances.
+    fn rays() -> impl Iterator<Item = Ray> {
+        (0..1000).map(|i| Ray::new(Vec3::new(-5, i as f64 * 1e-6, 0), Vec3::new(1, 0, 0)))
+    }
+
+    #[test]
+    fn ray_misses_medium() {
+        let medium = test_medium(1e6);
+        let miss_ray = Ray::new(Vec3::new(-5, 2, 0), Vec3::new(1, 0, 0));
+
+        assert!(medium.hit(&miss_ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn hits_are_inside_boundary() {
+        let medium = test_medium(1.0);
+        for ray in rays() {
+            if let Some(hr) = medium.hit(&ray, 0.0, f64::INFINITY) {
+                assert!(hr.t > 4.0 && hr.t < 6.0, "{}", hr.t);
+                assert_eq!(ray.at(hr.t), hr.point);
+                assert_eq!(Vec3::new(-1, 0, 0), hr.normal);
+            }
+        }
+    }
+
+    #[test]
+    fn transmittance_follows_density() {
+        // Light passing through a distance of 2 is attenuated by `e^(-2 density)`.
+        for density in [0.1, 0.5, 2.0] {
+            let medium = test_medium(density);
+            let passed = rays()
+                .filter(|ray| medium.hit(ray, 0.0, f64::INFINITY).is_none())
+                .count();
+            let expected = 1000.0 * (-2.0 * density).exp();
+            assert!(
+                (passed as f64 - expected).abs() < 40.0,
+                "{passed} {expected}"
+            );
+
+            let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(2, 0, 0));
+            let transmittance = medium.transmittance(&ray, 0.0, f64::INFINITY, &RTRng::new());
+            assert!((1000.0 * transmittance - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn adjacent_media_scatter_independently() {
+        // Two boxes of the same density, each 1 long along the rays.
+        let media = [-1, 0].map(|x| {
+            let boundary = Cuboid::new(
+                Vec3::new(x, -1, -1),
+                Vec3::new(x + 1, 1, 1),
+                Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+            );
+            ConstantMedium::new(
+                Arc::new(boundary),
+                0.5,
+                Arc::new(Isotropic::new(0.5, 0.5, 0.5)),
+            )
+        });
+        let passed = rays()
+            .filter(|ray| {
+                media
+                    .iter()
+                    .all(|m| m.hit(ray, 0.0, f64::INFINITY).is_none())
+            })
+            .count();
+        let expected = 1000.0 * (-1.0_f64).exp();
+        assert!(
+            (passed as f64 - expected).abs() < 40.0,
+            "{passed} {expected}"
+        );
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        // Dense enough to scatter rays as soon as they enter.
+        let medium = test_medium(1e9);
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(2, 0, 0));
+
+        assert!(medium.hit(&ray, 0.0, 1.999).is_none());
+        let hr = medium.hit(&ray, 0.0, 2.001).unwrap();
+        assert!((hr.t - 2.0).abs() < 1e-6);
+
+        // Rays starting inside the volume scatter there too.
+        let hr = medium.hit(&ray, 2.5, f64::INFINITY).unwrap();
+        assert!((hr.t - 2.5).abs() < 1e-6);
+        assert!(medium.hit(&ray, 3.001, f64::INFINITY).is_none());
+    }
+}
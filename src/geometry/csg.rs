@@ -0,0 +1,258 @@
+//! Constructive solid geometry: solids combined by set operations, such as a sphere
+//! with a bite taken out of it, or the lens where two spheres overlap.
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::scene::description::{NameMaterial, ObjectDescription};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+/// A span of a ray inside a solid.
+pub struct Interval {
+    /// Where the ray enters the solid.
+    pub enter: HitRecord,
+
+    /// Where the ray exits the solid.
+    pub exit: HitRecord,
+}
+
+/// A hittable that encloses a volume, so that it can be combined with other solids
+/// by a [`Csg`].
+pub trait Solid: Hittable {
+    /// All of the spans of a ray inside the solid, in order along the ray and without
+    /// overlaps. Spans at any `t` are included, not just in front of the ray's
+    /// origin. The normals of the hit records point out of the solid, and their
+    /// `front_face` isn't set.
+    fn intervals(&self, ray: &Ray) -> Vec<Interval>;
+}
+
+/// How a [`Csg`] combines its solids.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operation {
+    /// Everything inside either solid.
+    Union,
+    /// Everything inside both solids.
+    Intersection,
+    /// Everything inside the left solid, but not the right solid.
+    Difference,
+}
+
+impl Operation {
+    /// Whether a point is inside the combined solid, given whether it is inside the
+    /// left and right solids.
+    fn contains(self, in_left: bool, in_right: bool) -> bool {
+        match self {
+            Operation::Union => in_left || in_right,
+            Operation::Intersection => in_left && in_right,
+            Operation::Difference => in_left && !in_right,
+        }
+    }
+}
+
+/// Two solids combined by an [`Operation`]. Surfaces keep the material of the solid
+/// they came from, so the inside of a bite has the material of the solid that was
+/// taken away.
+pub struct Csg {
+    pub left: Arc<dyn Solid>,
+    pub right: Arc<dyn Solid>,
+    pub operation: Operation,
+}
+
+impl Csg {
+    pub fn new(operation: Operation, left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self {
+            left,
+            right,
+            operation,
+        }
+    }
+
+    pub fn union(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(Operation::Union, left, right)
+    }
+
+    pub fn intersection(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(Operation::Intersection, left, right)
+    }
+
+    pub fn difference(left: Arc<dyn Solid>, right: Arc<dyn Solid>) -> Self {
+        Self::new(Operation::Difference, left, right)
+    }
+}
+
+impl Debug for Csg {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Csg")
+            .field("operation", &self.operation)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Solid for Csg {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        // The boundaries of both solids, in order along the ray. Each one toggles
+        // whether the ray is inside the solid that it belongs to.
+        let mut boundaries = vec![];
+        for (is_left, solid) in [(true, &self.left), (false, &self.right)] {
+            for Interval { enter, exit } in solid.intervals(ray) {
+                boundaries.push((is_left, enter));
+                boundaries.push((is_left, exit));
+            }
+        }
+        boundaries.sort_by(|(_, a), (_, b)| a.t.total_cmp(&b.t));
+
+        let (mut in_left, mut in_right) = (false, false);
+        let mut enter = None;
+        let mut intervals = vec![];
+        for (is_left, mut hr) in boundaries {
+            if is_left {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+                // The surface of the right solid faces into the left solid where it
+                // is taken away from it.
+                if self.operation == Operation::Difference {
+                    hr.normal = -hr.normal;
+                }
+            }
+
+            let inside = self.operation.contains(in_left, in_right);
+            match (enter.take(), inside) {
+                (None, true) => enter = Some(hr),
+                (Some(enter), false) => intervals.push(Interval { enter, exit: hr }),
+                (unchanged, _) => enter = unchanged,
+            }
+        }
+        intervals
+    }
+}
+
+impl Hittable for Csg {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut hr = self
+            .intervals(ray)
+            .into_iter()
+            .flat_map(|Interval { enter, exit }| [enter, exit])
+            .find(|hr| hr.t > t_min && hr.t < t_max)?;
+        let outward_normal = hr.normal;
+        hr.set_face_normal(ray, outward_normal);
+        Some(hr)
+    }
+
+    /// Intersections and differences are inside the left solid, so they have its
+    /// box.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let left = self.left.bounding_box()?;
+        match self.operation {
+            Operation::Union => Some(left.union(&self.right.bounding_box()?)),
+            Operation::Intersection | Operation::Difference => Some(left),
+        }
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Csg {
+            operation: self.operation.into(),
+            left: Box::new(self.left.describe(name_material)?),
+            right: Box::new(self.right.describe(name_material)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, sphere::Sphere, vec3::Vec3};
+    use super::{Csg, Solid};
+    use crate::material::Lambertian;
+
+    fn sphere(x: f64, radius: f64) -> Arc<Sphere> {
+        Arc::new(Sphere::new(
+            Vec3::new(x, 0, 0),
+            radius,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        ))
+    }
+
+    /// The `x` coordinates where a ray along the x axis enters and exits a solid.
+    fn spans(solid: &dyn Solid) -> Vec<(f64, f64)> {
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(1, 0, 0));
+        solid
+            .intervals(&ray)
+            .iter()
+            .map(|interval| (interval.enter.point.x(), interval.exit.point.x()))
+            .collect()
+    }
+
+    #[test]
+    fn sphere_intervals() {
+        assert_eq!(vec![(-1.0, 1.0)], spans(sphere(0.0, 1.0).as_ref()));
+        assert_eq!(vec![(2.0, 4.0)], spans(sphere(3.0, 1.0).as_ref()));
+
+        let miss_ray = Ray::new(Vec3::new(-5, 2, 0), Vec3::new(1, 0, 0));
+        assert!(sphere(0.0, 1.0).intervals(&miss_ray).is_empty());
+    }
+
+    #[test]
+    fn operations() {
+        let union = Csg::union(sphere(-1.0, 1.0), sphere(3.0, 1.0));
+        assert_eq!(vec![(-2.0, 0.0), (2.0, 4.0)], spans(&union));
+        let union = Csg::union(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        assert_eq!(vec![(-1.5, 1.5)], spans(&union));
+
+        let lens = Csg::intersection(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        assert_eq!(vec![(-0.5, 0.5)], spans(&lens));
+
+        let bite = Csg::difference(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        assert_eq!(vec![(-1.0, 0.0)], spans(&bite));
+        let hole = Csg::difference(sphere(0.0, 1.0), sphere(0.0, 0.5));
+        assert_eq!(vec![(-1.0, -0.5), (0.5, 1.0)], spans(&hole));
+
+        // Combined solids can be combined again.
+        let nested = Csg::difference(Arc::new(hole), sphere(1.0, 0.25));
+        assert_eq!(vec![(-1.0, -0.5), (0.5, 0.75)], spans(&nested));
+    }
+
+    #[test]
+    fn hit_inside_of_bite() {
+        let bite = Csg::difference(sphere(0.0, 1.0), sphere(1.0, 1.0));
+        let ray = Ray::new(Vec3::new(5, 0, 0), Vec3::new(-1, 0, 0));
+
+        // The ray passes through the bite, and hits the surface left by it.
+        let hr = bite.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(5.0, hr.t);
+        assert_eq!(Vec3::new(0, 0, 0), hr.point);
+        assert_eq!(Vec3::new(1, 0, 0), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let lens = Csg::intersection(sphere(-0.5, 1.0), sphere(0.5, 1.0));
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(1, 0, 0));
+
+        assert!(lens.hit(&ray, 0.0, 4.4999).is_none());
+        let hr = lens.hit(&ray, 0.0, 4.5001).unwrap();
+        assert_eq!(Vec3::new(-1, 0, 0), hr.normal);
+
+        // From inside, the ray hits the far side of the lens.
+        let hr = lens.hit(&ray, 5.0, f64::INFINITY).unwrap();
+        assert_eq!(5.5, hr.t);
+        assert_eq!(Some(false), hr.front_face);
+    }
+
+    #[test]
+    fn csg_bounding_box() {
+        let union = Csg::union(sphere(-1.0, 1.0), sphere(3.0, 1.0));
+        let bbox = union.bounding_box().unwrap();
+        assert_eq!(Vec3::new(-2, -1, -1), bbox.min());
+        assert_eq!(Vec3::new(4, 1, 1), bbox.max());
+
+        let bite = Csg::difference(sphere(0.0, 1.0), sphere(3.0, 1.0));
+        let bbox = bite.bounding_box().unwrap();
+        assert_eq!(Vec3::new(1, 1, 1), bbox.max());
+    }
+}
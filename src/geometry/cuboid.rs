@@ -0,0 +1,234 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A rectangular box, which may be rotated to any orientation. Boxes are solid, so
+/// rays starting inside hit their inside faces, as needed by [`Dielectric`].
+///
+/// [`Dielectric`]: crate::material::Dielectric
+#[derive(Debug)]
+pub struct Cuboid {
+    pub center: Vec3,
+
+    /// Half the size of the box along each of its axes.
+    pub half_size: Vec3,
+
+    /// The directions of the box's edges, which are perpendicular unit vectors.
+    pub axes: [Vec3; 3],
+
+    pub material: Arc<dyn Material>,
+}
+
+impl Cuboid {
+    /// Create an axis-aligned box with opposite corners `a` and `b`.
+    pub fn new(a: Vec3, b: Vec3, material: Arc<dyn Material>) -> Self {
+        let bbox = Aabb::new(a, b);
+        Self {
+            center: bbox.centroid(),
+            half_size: 0.5 * (bbox.max() - bbox.min()),
+            axes: [Vec3::new(1, 0, 0), Vec3::new(0, 1, 0), Vec3::new(0, 0, 1)],
+            material,
+        }
+    }
+
+    /// Create a box with the given size along its axes. The box's first axis is
+    /// `x_axis`, its second is the part of `y_axis` perpendicular to the first, and
+    /// its third is perpendicular to both.
+    pub fn oriented(
+        center: Vec3,
+        size: Vec3,
+        x_axis: Vec3,
+        y_axis: Vec3,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        let x = x_axis.normalized();
+        let y = (y_axis - y_axis.dot(x) * x).normalized();
+        Self {
+            center,
+            half_size: 0.5 * size,
+            axes: [x, y, x.cross(y)],
+            material,
+        }
+    }
+}
+
+impl Hittable for Cuboid {
+    /// Texture coordinates run from 0 to 1 across each face.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        // Intersect the ray with the slab between each pair of opposite faces, in
+        // the box's own coordinates.
+        let offset = ray.origin() - self.center;
+        let origin = self.axes.map(|axis| offset.dot(axis));
+        let direction = self.axes.map(|axis| ray.direction().dot(axis));
+
+        let (mut enter, mut exit) = ((f64::NEG_INFINITY, 0), (f64::INFINITY, 0));
+        for i in 0..3 {
+            let h = self.half_size[i];
+            if direction[i] == 0.0 {
+                if origin[i].abs() > h {
+                    return None;
+                }
+                continue;
+            }
+            let t0 = (-h - origin[i]) / direction[i];
+            let t1 = (h - origin[i]) / direction[i];
+            let (near, far) = (t0.min(t1), t0.max(t1));
+            if near > enter.0 {
+                enter = (near, i);
+            }
+            if far < exit.0 {
+                exit = (far, i);
+            }
+        }
+        if enter.0 > exit.0 {
+            return None;
+        }
+
+        // Rays starting inside the box hit the face they leave through.
+        let (t, axis, sign) = if enter.0 > t_min && enter.0 < t_max {
+            (enter.0, enter.1, -direction[enter.1].signum())
+        } else if exit.0 > t_min && exit.0 < t_max {
+            (exit.0, exit.1, direction[exit.1].signum())
+        } else {
+            return None;
+        };
+
+        let point = ray.at(t);
+        let outward_normal = sign * self.axes[axis];
+        let mut hr = HitRecord::new(point, outward_normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, outward_normal);
+
+        let face_coordinate = |i: usize| {
+            let local = (point - self.center).dot(self.axes[i]);
+            (local / self.half_size[i] + 1.0) / 2.0
+        };
+        hr.uv = Some((
+            face_coordinate((axis + 1) % 3),
+            face_coordinate((axis + 2) % 3),
+        ));
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut extent = Vec3::new(0, 0, 0);
+        for (i, axis) in self.axes.iter().enumerate() {
+            for k in 0..3 {
+                extent[k] += axis[k].abs() * self.half_size[i];
+            }
+        }
+        Some(Aabb::new(self.center - extent, self.center + extent).padded())
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Cuboid {
+            center: description::tuple(self.center),
+            size: description::tuple(2.0 * self.half_size),
+            x_axis: description::tuple(self.axes[0]),
+            y_axis: description::tuple(self.axes[1]),
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Cuboid;
+    use crate::material::Lambertian;
+
+    fn test_cuboid() -> Cuboid {
+        Cuboid::new(
+            Vec3::new(1, 2, 3),
+            Vec3::new(-1, 0, -1),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_hits_cuboid() {
+        let cuboid = test_cuboid();
+        assert_eq!(Vec3::new(0, 1, 1), cuboid.center);
+        assert_eq!(Vec3::new(1, 1, 2), cuboid.half_size);
+
+        let ray = Ray::new(Vec3::new(0.5, 1.5, 5), Vec3::new(0, 0, -1));
+        let hr = cuboid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(0, 0, 1), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+        assert_eq!(Some((0.75, 0.75)), hr.uv);
+
+        let ray = Ray::new(Vec3::new(-3, 0.5, 0), Vec3::new(1, 0, 0));
+        let hr = cuboid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(-1, 0, 0), hr.normal);
+    }
+
+    #[test]
+    fn ray_from_inside_hits_back_face() {
+        let cuboid = test_cuboid();
+        let ray = Ray::new(Vec3::new(0, 1, 1), Vec3::new(0, -1, 0));
+
+        let hr = cuboid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(1.0, hr.t);
+        assert_eq!(Vec3::new(0, 0, 1), hr.point);
+        assert_eq!(Some(false), hr.front_face);
+        // The normal faces against the ray, into the box.
+        assert_eq!(Vec3::new(0, 1, 0), hr.normal);
+    }
+
+    #[test]
+    fn ray_misses_cuboid() {
+        let cuboid = test_cuboid();
+        for (origin, direction) in [
+            (Vec3::new(2, 1, 5), Vec3::new(0, 0, -1)),
+            (Vec3::new(0, 3, 5), Vec3::new(0, 0, -1)),
+            (Vec3::new(0, 1, 5), Vec3::new(0, 0, 1)),
+            (Vec3::new(-3, 3, 0), Vec3::new(1, 0.1, 0)),
+        ] {
+            assert!(cuboid
+                .hit(&Ray::new(origin, direction), 0.0, f64::INFINITY)
+                .is_none());
+        }
+        let ray = Ray::new(Vec3::new(0, 1, 5), Vec3::new(0, 0, -1));
+        assert!(cuboid.hit(&ray, 0.0, 1.5).is_none());
+    }
+
+    #[test]
+    fn oriented_cuboid() {
+        // A unit cube rotated 45 degrees about the y axis.
+        let cuboid = Cuboid::oriented(
+            Vec3::new(0, 0, 0),
+            Vec3::new(1, 1, 1),
+            Vec3::new(1, 0, 1),
+            Vec3::new(0.3, 1, 0.3),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        );
+        assert!((cuboid.axes[1] - Vec3::new(0, 1, 0)).length() < 1e-12);
+
+        // An edge of the cube points along +z.
+        let ray = Ray::new(Vec3::new(0, 0, 5), Vec3::new(0, 0, -1));
+        let hr = cuboid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hr.t - (5.0 - 0.5f64.sqrt())).abs() < 1e-12);
+
+        let ray = Ray::new(Vec3::new(0.5, 0, 5), Vec3::new(0, 0, -1));
+        let hr = cuboid.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        let expected = Vec3::new(1, 0, 1).normalized();
+        assert!((hr.normal - expected).length() < 1e-12);
+
+        let bbox = cuboid.bounding_box().unwrap();
+        assert!((bbox.max().x() - 0.5f64.sqrt()).abs() < 1e-6);
+        assert!((bbox.max().y() - 0.5).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,211 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    disk,
+    frame::{self, Frame},
+    hittable::{HitRecord, Hittable},
+    polynomial,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A circular cylinder between the centers of its two ends. Capped cylinders are
+/// solid, and uncapped cylinders are open tubes.
+#[derive(Debug)]
+pub struct Cylinder {
+    pub base: Vec3,
+    pub top: Vec3,
+    pub radius: f64,
+
+    /// Whether the ends of the cylinder are closed with disks.
+    pub capped: bool,
+
+    pub material: Arc<dyn Material>,
+}
+
+impl Cylinder {
+    /// Create a capped cylinder.
+    pub fn new<T: Into<f64>>(
+        base: Vec3,
+        top: Vec3,
+        radius: T,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            base,
+            top,
+            radius: radius.into(),
+            capped: true,
+            material,
+        }
+    }
+
+    /// Leave the ends of the cylinder open.
+    pub fn uncapped(self) -> Self {
+        Self {
+            capped: false,
+            ..self
+        }
+    }
+
+    fn frame(&self) -> Frame {
+        Frame::from_axis(self.base, self.top - self.base)
+    }
+}
+
+impl Hittable for Cylinder {
+    /// On the side, texture coordinates are the angle about the axis as a fraction
+    /// of a turn, and the height as a fraction of the cylinder's height. The caps
+    /// have polar coordinates, like a [`super::disk::Disk`].
+    fn hit(&self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<HitRecord> {
+        let frame = self.frame();
+        let height = (self.top - self.base).length();
+        let local = frame.ray_to_local(ray);
+        let (o, d) = (local.origin(), local.direction());
+
+        // The closest hit, with its local outward normal and texture coordinates.
+        let mut closest = None;
+
+        let a = d.x() * d.x() + d.y() * d.y();
+        let b = 2.0 * (o.x() * d.x() + o.y() * d.y());
+        let c = o.x() * o.x() + o.y() * o.y() - self.radius * self.radius;
+        if a > 0.0 {
+            for &t in polynomial::quadratic_roots(a, b, c).iter() {
+                let p = local.at(t);
+                if t > t_min && t < t_max && (0.0..=height).contains(&p.z()) {
+                    let normal = Vec3::new(p.x(), p.y(), 0) / self.radius;
+                    closest = Some((t, normal, (frame::turns(p.y(), p.x()), p.z() / height)));
+                    t_max = t;
+                    break;
+                }
+            }
+        }
+
+        if self.capped {
+            for (z, normal) in [(0.0, Vec3::new(0, 0, -1)), (height, Vec3::new(0, 0, 1))] {
+                if let Some((t, uv)) = disk::intersect_local(&local, z, self.radius, t_min, t_max) {
+                    closest = Some((t, normal, uv));
+                    t_max = t;
+                }
+            }
+        }
+
+        let (t, normal, uv) = closest?;
+        let outward_normal = frame.vector_to_world(normal);
+        let mut hr = HitRecord::new(ray.at(t), outward_normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, outward_normal);
+        hr.uv = Some(uv);
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (r, height) = (self.radius, (self.top - self.base).length());
+        let local = Aabb::new(Vec3::new(-r, -r, 0), Vec3::new(r, r, height));
+        Some(self.frame().bounding_box(local))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Cylinder {
+            base: description::tuple(self.base),
+            top: description::tuple(self.top),
+            radius: self.radius,
+            capped: self.capped,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Cylinder;
+    use crate::material::Lambertian;
+
+    /// A cylinder of radius 0.5 along the y axis, from y = -1 to y = 1, with its
+    /// side crossing the z axis at z = 0.5.
+    fn test_cylinder() -> Cylinder {
+        Cylinder::new(
+            Vec3::new(0, -1, 1),
+            Vec3::new(0, 1, 1),
+            0.5,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_misses_cylinder() {
+        let cylinder = test_cylinder();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+
+        assert!(cylinder.hit(&miss_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_cylinder_bullseye() {
+        let cylinder = test_cylinder();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        let hr = cylinder
+            .hit(&bullseye_ray, -1.0, 1.0)
+            .expect("Ray should hit cylinder");
+        assert_eq!(0.5, hr.t);
+        assert_eq!(Vec3::new(0, 0, 0.5), hr.point);
+        assert!((hr.normal - Vec3::new(0, 0, -1)).length() < 1e-12);
+        assert_eq!(0.5, hr.uv.unwrap().1);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let cylinder = test_cylinder();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(cylinder.hit(&bullseye_ray, -1.0, 0.4999).is_none());
+        assert!(cylinder.hit(&bullseye_ray, -1.0, 0.5001).is_some());
+    }
+
+    #[test]
+    fn ray_scrapes_cylinder() {
+        let cylinder = test_cylinder();
+        let scrape_ray = |x: f64| Ray::new(Vec3::new(x, 0, -2), Vec3::new(0, 0, 1));
+
+        assert!(cylinder.hit(&scrape_ray(0.4999), -10.0, 10.0).is_some());
+        assert!(cylinder.hit(&scrape_ray(0.5001), -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn caps() {
+        let cylinder = test_cylinder();
+        let down_ray = Ray::new(Vec3::new(0.25, 3, 1), Vec3::new(0, -1, 0));
+
+        let hr = cylinder.hit(&down_ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert!((hr.normal - Vec3::new(0, 1, 0)).length() < 1e-12);
+        assert_eq!(Some(true), hr.front_face);
+        assert_eq!(0.5, hr.uv.unwrap().1);
+
+        // Without caps, the ray passes through the tube.
+        let tube = test_cylinder().uncapped();
+        assert!(tube.hit(&down_ray, 0.0, f64::INFINITY).is_none());
+
+        // From inside the tube, the inside of the side is hit.
+        let ray = Ray::new(Vec3::new(0, 0, 1), Vec3::new(0, 0, 1));
+        let hr = tube.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(0.5, hr.t);
+        assert_eq!(Some(false), hr.front_face);
+    }
+
+    #[test]
+    fn cylinder_has_bounding_box() {
+        let bbox = test_cylinder().bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-0.5, -1, 0.5)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(0.5, 1, 1.5)).length() < 1e-6);
+    }
+}
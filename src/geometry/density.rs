@@ -0,0 +1,306 @@
+//! Densities that vary through a volume, for filling a
+//! [`super::heterogeneous_medium::HeterogeneousMedium`].
+use std::{fmt::Debug, io};
+
+use crate::{
+    scene::description::{self, DensityDescription},
+    utils::rand,
+};
+
+use super::{aabb::Aabb, vec3::Vec3};
+
+/// The density of a medium at each point in space.
+pub trait DensityField: Debug + Send + Sync {
+    /// The chance of scattering per unit distance at `point`. Never negative.
+    fn density(&self, point: Vec3) -> f64;
+
+    /// A density at least as high as the density at every point. Media sample
+    /// distances with it, so fields are rendered faster when it is close to the
+    /// highest density.
+    fn max_density(&self) -> f64;
+
+    /// Describe the field for saving a scene. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self) -> io::Result<DensityDescription> {
+        Err(description::invalid_input(
+            "only grid and noise densities can be saved",
+        ))
+    }
+}
+
+/// Densities sampled on a regular grid spanning a box, and interpolated between the
+/// samples. The density is 0 outside of the box.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DensityGrid {
+    bounds: Aabb,
+    size: [usize; 3],
+    values: Vec<f64>,
+    max_density: f64,
+}
+
+impl DensityGrid {
+    /// Create a grid with `size` samples along each axis, given in `values` with `x`
+    /// varying fastest, then `y`, then `z`. The first and last samples along each
+    /// axis are on the faces of `bounds`.
+    ///
+    /// # Panics
+    /// Panics if there isn't a value for each sample, there are no samples along an
+    /// axis, or any value is negative or not finite.
+    pub fn new(bounds: Aabb, size: [usize; 3], values: Vec<f64>) -> Self {
+        assert!(
+            size.iter().all(|&n| n > 0),
+            "density grids must have samples along each axis"
+        );
+        assert_eq!(
+            size.iter().product::<usize>(),
+            values.len(),
+            "density grids must have a value for each sample"
+        );
+        assert!(
+            values
+                .iter()
+                .all(|&value| value >= 0.0 && value.is_finite()),
+            "densities must be finite and not negative"
+        );
+        let max_density = values.iter().copied().fold(0.0, f64::max);
+        Self {
+            bounds,
+            size,
+            values,
+            max_density,
+        }
+    }
+
+    pub fn bounds(&self) -> Aabb {
+        self.bounds
+    }
+
+    pub fn size(&self) -> [usize; 3] {
+        self.size
+    }
+
+    pub fn values(&self) -> &[f64] {
+        &self.values
+    }
+
+    fn value(&self, [x, y, z]: [usize; 3]) -> f64 {
+        let [nx, ny, _] = self.size;
+        self.values[(z * ny + y) * nx + x]
+    }
+}
+
+impl DensityField for DensityGrid {
+    fn density(&self, point: Vec3) -> f64 {
+        let (min, max) = (self.bounds.min(), self.bounds.max());
+        if (0..3).any(|axis| point[axis] < min[axis] || point[axis] > max[axis]) {
+            return 0.0;
+        }
+
+        // The sample before the point along each axis, and how far the point is
+        // towards the next one.
+        let mut cell = [0; 3];
+        let mut fraction = [0.0; 3];
+        for axis in 0..3 {
+            let last = self.size[axis] - 1;
+            let extent = max[axis] - min[axis];
+            let position = if extent > 0.0 {
+                (point[axis] - min[axis]) / extent * last as f64
+            } else {
+                0.0
+            };
+            cell[axis] = (position.floor() as usize).min(last.saturating_sub(1));
+            fraction[axis] = (position - cell[axis] as f64).min(1.0);
+        }
+
+        // Trilinear interpolation between the 8 samples around the point.
+        let mut density = 0.0;
+        for corner in 0..8 {
+            let mut sample = cell;
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                if corner >> axis & 1 == 1 {
+                    sample[axis] = (sample[axis] + 1).min(self.size[axis] - 1);
+                    weight *= fraction[axis];
+                } else {
+                    weight *= 1.0 - fraction[axis];
+                }
+            }
+            if weight > 0.0 {
+                density += weight * self.value(sample);
+            }
+        }
+        density
+    }
+
+    fn max_density(&self) -> f64 {
+        self.max_density
+    }
+
+    fn describe(&self) -> io::Result<DensityDescription> {
+        Ok(DensityDescription::Grid {
+            min: description::tuple(self.bounds().min()),
+            max: description::tuple(self.bounds().max()),
+            size: self.size(),
+            values: self.values().to_vec(),
+        })
+    }
+}
+
+/// A density varying smoothly at random, like the inside of a cloud. The noise is
+/// the sum of layers of value noise, each with twice the detail and half the
+/// strength of the last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseDensity {
+    /// The highest density of the noise.
+    pub density: f64,
+
+    /// How many times the density varies per unit distance, in the first layer.
+    pub frequency: f64,
+
+    /// The number of layers of noise.
+    pub octaves: u32,
+
+    /// Noise with different seeds varies differently.
+    pub seed: u64,
+}
+
+impl NoiseDensity {
+    /// Noise in `[0, 1)` at a point.
+    fn noise(&self, point: Vec3) -> f64 {
+        let mut total = 0.0;
+        let mut strength = 1.0;
+        let mut total_strength = 0.0;
+        let mut point = point * self.frequency;
+        for octave in 0..self.octaves {
+            total += strength * self.value_noise(point, octave);
+            total_strength += strength;
+            strength *= 0.5;
+            point *= 2.0;
+        }
+        if total_strength > 0.0 {
+            total / total_strength
+        } else {
+            0.0
+        }
+    }
+
+    /// Random values at the corners of a unit lattice, smoothly interpolated between
+    /// them.
+    fn value_noise(&self, point: Vec3, octave: u32) -> f64 {
+        let cell = [point.x().floor(), point.y().floor(), point.z().floor()];
+        let smooth = |axis: usize| {
+            let f = point[axis] - cell[axis];
+            f * f * (3.0 - 2.0 * f)
+        };
+        let weights = [smooth(0), smooth(1), smooth(2)];
+
+        let mut value = 0.0;
+        for corner in 0..8 {
+            let mut lattice = cell;
+            let mut weight = 1.0;
+            for axis in 0..3 {
+                if corner >> axis & 1 == 1 {
+                    lattice[axis] += 1.0;
+                    weight *= weights[axis];
+                } else {
+                    weight *= 1.0 - weights[axis];
+                }
+            }
+            let [x, y, z] = lattice;
+            // The seed's bits are hashed as they are, as an `f64` can't hold every `u64`.
+            let random = rand::hash_f64(&[x, y, z, octave as f64, f64::from_bits(self.seed)]);
+            value += weight * random;
+        }
+        value
+    }
+}
+
+impl DensityField for NoiseDensity {
+    fn density(&self, point: Vec3) -> f64 {
+        self.density * self.noise(point)
+    }
+
+    fn max_density(&self) -> f64 {
+        self.density
+    }
+
+    fn describe(&self) -> io::Result<DensityDescription> {
+        Ok(DensityDescription::Noise {
+            density: self.density,
+            frequency: self.frequency,
+            octaves: self.octaves,
+            seed: self.seed,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{aabb::Aabb, vec3::Vec3};
+    use super::{DensityField, DensityGrid, NoiseDensity};
+
+    #[test]
+    fn grid_interpolates_samples() {
+        // Densities of 0 at x = 0 and 4 at x = 2, constant in y and z.
+        let grid = DensityGrid::new(
+            Aabb::new(Vec3::new(0, 0, 0), Vec3::new(2, 1, 1)),
+            [2, 2, 1],
+            vec![0.0, 4.0, 0.0, 4.0],
+        );
+        assert_eq!(4.0, grid.max_density());
+        assert_eq!(0.0, grid.density(Vec3::new(0, 0.5, 0.5)));
+        assert_eq!(1.0, grid.density(Vec3::new(0.5, 0.5, 0.5)));
+        assert!((grid.density(Vec3::new(1.5, 0.2, 1)) - 3.0).abs() < 1e-12);
+        assert_eq!(4.0, grid.density(Vec3::new(2, 1, 0)));
+
+        // Nothing outside of the box.
+        assert_eq!(0.0, grid.density(Vec3::new(2.1, 0.5, 0.5)));
+        assert_eq!(0.0, grid.density(Vec3::new(1, -0.1, 0.5)));
+    }
+
+    #[test]
+    #[should_panic(expected = "a value for each sample")]
+    fn grid_needs_every_value() {
+        DensityGrid::new(
+            Aabb::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1)),
+            [2, 2, 2],
+            vec![1.0; 7],
+        );
+    }
+
+    #[test]
+    fn noise_is_smooth_and_bounded() {
+        let noise = NoiseDensity {
+            density: 2.0,
+            frequency: 1.5,
+            octaves: 4,
+            seed: 0,
+        };
+        let mut previous = noise.density(Vec3::new(0, 0.3, 0.7));
+        let (mut lowest, mut highest) = (f64::INFINITY, 0.0_f64);
+        for i in 1..1000 {
+            let density = noise.density(Vec3::new(i as f64 * 0.001, 0.3, 0.7));
+            assert!((0.0..2.0).contains(&density), "{density}");
+            assert!((density - previous).abs() < 0.05, "{density} {previous}");
+            (lowest, highest) = (lowest.min(density), highest.max(density));
+            previous = density;
+        }
+        // It varies over a distance of about one.
+        assert!(highest - lowest > 0.1, "{lowest} {highest}");
+
+        let reseeded = NoiseDensity { seed: 1, ..noise };
+        let point = Vec3::new(0.3, 0.3, 0.7);
+        assert_ne!(noise.density(point), reseeded.density(point));
+
+        // Large seeds that round to the same `f64` still differ.
+        let large = NoiseDensity {
+            seed: 1 << 60,
+            ..noise
+        };
+        let next = NoiseDensity {
+            seed: (1 << 60) + 1,
+            ..noise
+        };
+        assert_ne!(large.density(point), next.density(point));
+    }
+}
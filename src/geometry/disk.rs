@@ -0,0 +1,162 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    frame::{self, Frame},
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A flat, circular disk.
+#[derive(Debug)]
+pub struct Disk {
+    pub center: Vec3,
+
+    /// The unit normal of the disk's front face.
+    pub normal: Vec3,
+
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl Disk {
+    pub fn new<T: Into<f64>>(
+        center: Vec3,
+        normal: Vec3,
+        radius: T,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center,
+            normal: normal.normalized(),
+            radius: radius.into(),
+            material,
+        }
+    }
+}
+
+/// Find where a ray, in a shape's local coordinates, hits a disk of `radius`
+/// centered on the third axis at height `z`. Returns the ray parameter of the hit
+/// and polar texture coordinates: the angle about the axis as a fraction of a turn,
+/// and the distance from the axis as a fraction of the radius.
+pub(crate) fn intersect_local(
+    ray: &Ray,
+    z: f64,
+    radius: f64,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, (f64, f64))> {
+    if ray.direction().z() == 0.0 {
+        return None;
+    }
+    let t = (z - ray.origin().z()) / ray.direction().z();
+    if t <= t_min || t >= t_max {
+        return None;
+    }
+    let point = ray.at(t);
+    let distance = (point.x() * point.x() + point.y() * point.y()).sqrt();
+    (distance <= radius).then(|| (t, (frame::turns(point.y(), point.x()), distance / radius)))
+}
+
+impl Hittable for Disk {
+    /// Texture coordinates are polar: the angle about the center as a fraction of a
+    /// turn, and the distance from the center as a fraction of the radius.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let local = Frame::from_axis(self.center, self.normal).ray_to_local(ray);
+        let (t, uv) = intersect_local(&local, 0.0, self.radius, t_min, t_max)?;
+
+        let mut hr = HitRecord::new(ray.at(t), self.normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, self.normal);
+        hr.uv = Some(uv);
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = self.radius;
+        let local = Aabb::new(Vec3::new(-r, -r, 0), Vec3::new(r, r, 0));
+        Some(Frame::from_axis(self.center, self.normal).bounding_box(local))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Disk {
+            center: description::tuple(self.center),
+            normal: description::tuple(self.normal),
+            radius: self.radius,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Disk;
+    use crate::material::Lambertian;
+
+    fn test_disk() -> Disk {
+        Disk::new(
+            Vec3::new(0, 0, 1),
+            Vec3::new(0, 0, -1),
+            0.5,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_misses_disk() {
+        let disk = test_disk();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+
+        assert!(disk.hit(&miss_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_disk_bullseye() {
+        let disk = test_disk();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        let hr = disk
+            .hit(&bullseye_ray, -1.0, 2.0)
+            .expect("Ray should hit disk");
+        assert_eq!(1.0, hr.t);
+        assert_eq!(Vec3::new(0, 0, 1), hr.point);
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+        assert_eq!(0.0, hr.uv.unwrap().1);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let disk = test_disk();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(disk.hit(&bullseye_ray, -1.0, 0.9999).is_none());
+        assert!(disk.hit(&bullseye_ray, -1.0, 1.0001).is_some());
+    }
+
+    #[test]
+    fn ray_scrapes_disk() {
+        let disk = test_disk();
+        let delta = Vec3::new(0.0001, 0, 0);
+        let edge_ray = |offset: Vec3| Ray::new(Vec3::new(0, 0, 0), Vec3::new(0.5, 0, 1) + offset);
+
+        let hr = disk.hit(&edge_ray(-delta), -1.0, 2.0).unwrap();
+        assert!(hr.uv.unwrap().1 > 0.999);
+        assert!(disk.hit(&edge_ray(delta), -1.0, 2.0).is_none());
+    }
+
+    #[test]
+    fn disk_has_bounding_box() {
+        let bbox = test_disk().bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-0.5, -0.5, 1)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(0.5, 0.5, 1)).length() < 1e-6);
+    }
+}
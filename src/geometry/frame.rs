@@ -0,0 +1,135 @@
+//! This module contains the [`Frame`] type, used to intersect rays with shapes in
+//! the shapes' own coordinates.
+use std::f64::consts::PI;
+
+use super::{aabb::Aabb, ray::Ray, vec3::Vec3};
+
+/// An orthonormal coordinate frame. Points are given local coordinates along the
+/// frame's axes, relative to its origin.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Frame {
+    pub origin: Vec3,
+    pub axes: [Vec3; 3],
+}
+
+impl Frame {
+    /// Create a frame whose third axis points along `axis`. The other two axes are
+    /// chosen arbitrarily, but consistently for a given `axis`.
+    pub fn from_axis(origin: Vec3, axis: Vec3) -> Self {
+        // Start from the world axis least aligned with `axis`, so the cross product
+        // is never close to zero.
+        let w = axis.normalized();
+        let helper = if w.x().abs() < w.y().abs() && w.x().abs() < w.z().abs() {
+            Vec3::new(1, 0, 0)
+        } else if w.y().abs() < w.z().abs() {
+            Vec3::new(0, 1, 0)
+        } else {
+            Vec3::new(0, 0, 1)
+        };
+        let u = helper.cross(w).normalized();
+        Self {
+            origin,
+            axes: [u, w.cross(u), w],
+        }
+    }
+
+    /// The local coordinates of a point.
+    pub fn to_local(&self, point: Vec3) -> Vec3 {
+        self.vector_to_local(point - self.origin)
+    }
+
+    /// The local components of a direction.
+    pub fn vector_to_local(&self, v: Vec3) -> Vec3 {
+        let [u, v_axis, w] = self.axes;
+        Vec3::new(v.dot(u), v.dot(v_axis), v.dot(w))
+    }
+
+    /// A ray in local coordinates. Rays have the same parameter `t` at each point in
+    /// both coordinates.
+    pub fn ray_to_local(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.to_local(ray.origin()),
+            self.vector_to_local(ray.direction()),
+        )
+        .with_time(ray.time())
+    }
+
+    /// The world components of a direction given in local components.
+    pub fn vector_to_world(&self, v: Vec3) -> Vec3 {
+        v.x() * self.axes[0] + v.y() * self.axes[1] + v.z() * self.axes[2]
+    }
+
+    /// The world bounding box of a box given in local coordinates.
+    pub fn bounding_box(&self, local: Aabb) -> Aabb {
+        let center = self.origin + self.vector_to_world(local.centroid());
+        let half_size = 0.5 * (local.max() - local.min());
+        let mut extent = Vec3::new(0, 0, 0);
+        for (i, axis) in self.axes.iter().enumerate() {
+            for k in 0..3 {
+                extent[k] += axis[k].abs() * half_size[i];
+            }
+        }
+        Aabb::new(center - extent, center + extent).padded()
+    }
+}
+
+/// The angle of the point `(x, y)` about the origin, anticlockwise from the x axis,
+/// as a fraction of a turn in `[0, 1)`. Shapes use this for texture coordinates.
+pub fn turns(y: f64, x: f64) -> f64 {
+    let angle = y.atan2(x);
+    let angle = if angle < 0.0 { angle + 2.0 * PI } else { angle };
+    (angle / (2.0 * PI)).min(1.0 - f64::EPSILON)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::{aabb::Aabb, ray::Ray, vec3::Vec3};
+    use super::{turns, Frame};
+
+    #[test]
+    fn frame_is_orthonormal() {
+        for axis in [
+            Vec3::new(0, 0, 1),
+            Vec3::new(0, -2, 0),
+            Vec3::new(1, 2, 3),
+            Vec3::new(-1, 0, 1e-9),
+        ] {
+            let frame = Frame::from_axis(Vec3::new(1, 2, 3), axis);
+            let [u, v, w] = frame.axes;
+            assert!((w - axis.normalized()).length() < 1e-12);
+            assert!(u.dot(v).abs() < 1e-12 && v.dot(w).abs() < 1e-12 && w.dot(u).abs() < 1e-12);
+            assert!((u.cross(v) - w).length() < 1e-12, "frames are right-handed");
+        }
+    }
+
+    #[test]
+    fn local_coordinates() {
+        let frame = Frame::from_axis(Vec3::new(1, 2, 3), Vec3::new(0, 0, 1));
+        let world = Vec3::new(3, -1, 7);
+        let local = frame.to_local(world);
+        assert_eq!(4.0, local.z());
+        assert_eq!(world, frame.origin + frame.vector_to_world(local));
+
+        let ray = Ray::new(Vec3::new(1, 2, 0), Vec3::new(0, 0, 2));
+        let local_ray = frame.ray_to_local(&ray);
+        assert_eq!(frame.to_local(ray.at(1.5)), local_ray.at(1.5));
+    }
+
+    #[test]
+    fn angles_in_turns() {
+        assert_eq!(0.0, turns(0.0, 1.0));
+        assert_eq!(0.25, turns(1.0, 0.0));
+        assert_eq!(0.5, turns(0.0, -1.0));
+        assert_eq!(0.75, turns(-1.0, 0.0));
+        assert!(turns(-1e-300, 1.0) < 1.0);
+    }
+
+    #[test]
+    fn rotated_bounding_box() {
+        let frame = Frame::from_axis(Vec3::new(0, 0, 0), Vec3::new(1, 0, 0));
+        let local = Aabb::new(Vec3::new(-1, -1, 0), Vec3::new(1, 1, 4));
+        let bbox = frame.bounding_box(local);
+        assert!((bbox.min() - Vec3::new(0, -1, -1)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(4, 1, 1)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,258 @@
+//! This module contains the [`HeterogeneousMedium`] type, for volumes such as clouds
+//! whose density varies from place to place.
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{NameMaterial, ObjectDescription},
+    utils::rand::{self, RTRng},
+};
+
+use super::{
+    aabb::Aabb,
+    constant_medium,
+    density::DensityField,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+};
+
+/// A volume inside a boundary, with a density given by a [`DensityField`]. Like a
+/// [`super::constant_medium::ConstantMedium`], rays passing through it are scattered
+/// at random distances, but they are more likely to scatter where it is denser.
+///
+/// Scattering distances are found by delta tracking. Rays take random steps as if
+/// the volume had the field's highest density everywhere, and scatter at each step
+/// with the chance that the volume is that dense there. Light passing through the
+/// volume to shadow rays is estimated by ratio tracking instead, in
+/// [`Hittable::transmittance`].
+///
+/// The boundary must be convex, and its material isn't used.
+pub struct HeterogeneousMedium {
+    pub boundary: Arc<dyn Hittable>,
+    pub density: Arc<dyn DensityField>,
+
+    /// The material of points where rays scatter, usually a phase function such as
+    /// [`crate::material::HenyeyGreenstein`].
+    pub phase_function: Arc<dyn Material>,
+}
+
+impl HeterogeneousMedium {
+    pub fn new(
+        boundary: Arc<dyn Hittable>,
+        density: Arc<dyn DensityField>,
+        phase_function: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            boundary,
+            density,
+            phase_function,
+        }
+    }
+}
+
+impl Debug for HeterogeneousMedium {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HeterogeneousMedium")
+            .field("density", &self.density)
+            .field("phase_function", &self.phase_function)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The random steps along a ray taken by delta and ratio tracking, at exponentially
+/// distributed distances for a volume with a constant density.
+struct Steps {
+    t: f64,
+
+    /// The density per unit `t`, rather than per unit distance.
+    rate: f64,
+}
+
+impl Steps {
+    fn new(ray: &Ray, t: f64, density: f64) -> Self {
+        Self {
+            t,
+            rate: density * ray.direction().length(),
+        }
+    }
+
+    fn next(&mut self, rng: &RTRng) -> Option<f64> {
+        if self.rate <= 0.0 {
+            return None;
+        }
+        self.t -= (1.0 - rng.random_f64()).ln() / self.rate;
+        Some(self.t)
+    }
+}
+
+impl Hittable for HeterogeneousMedium {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t_enter, t_exit) = constant_medium::inside(self.boundary.as_ref(), ray, t_min, t_max)?;
+
+        // `hit` has no random number generator, so one is seeded by hashing the ray.
+        let max_density = self.density.max_density();
+        let rng = RTRng::with_seed(rand::hash_u64(&constant_medium::ray_hash_values(
+            ray,
+            t_enter,
+            max_density,
+        )));
+
+        let mut steps = Steps::new(ray, t_enter, max_density);
+        while let Some(t) = steps.next(&rng).filter(|&t| t < t_exit) {
+            if rng.random_f64() * max_density < self.density.density(ray.at(t)) {
+                return Some(constant_medium::scattering_hit(
+                    ray,
+                    t,
+                    &self.phase_function,
+                ));
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.boundary.bounding_box()
+    }
+
+    /// Estimated by ratio tracking, which is unbiased and varies less than testing
+    /// whether the ray hits the volume.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, rng: &RTRng) -> f64 {
+        let Some((t_enter, t_exit)) =
+            constant_medium::inside(self.boundary.as_ref(), ray, t_min, t_max)
+        else {
+            return 1.0;
+        };
+
+        // Each step weighs the light by the chance that the volume isn't as dense as
+        // the highest density there.
+        let max_density = self.density.max_density();
+        let mut transmittance = 1.0;
+        let mut steps = Steps::new(ray, t_enter, max_density);
+        while let Some(t) = steps.next(rng).filter(|&t| t < t_exit) {
+            transmittance *= 1.0 - self.density.density(ray.at(t)) / max_density;
+        }
+        transmittance
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::HeterogeneousMedium {
+            boundary: Box::new(self.boundary.describe(name_material)?),
+            density: self.density.describe()?,
+            material: name_material(&self.phase_function)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{
+        aabb::Aabb,
+        density::{DensityGrid, NoiseDensity},
+        hittable::Hittable,
+        ray::Ray,
+        sphere::Sphere,
+        vec3::Vec3,
+    };
+    use super::HeterogeneousMedium;
+    use crate::{
+        material::{HenyeyGreenstein, Lambertian},
+        utils::rand::RTRng,
+    };
+
+    /// A unit sphere at the origin, with no density for `x < 0`, rising to a density
+    /// of 2 at `x = 1`.
+    fn test_medium() -> HeterogeneousMedium {
+        let boundary = Sphere::new(
+            Vec3::new(0, 0, 0),
+            1,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        );
+        let density = DensityGrid::new(
+            Aabb::new(Vec3::new(-1, -1, -1), Vec3::new(1, 1, 1)),
+            [3, 1, 1],
+            vec![0.0, 0.0, 2.0],
+        );
+        HeterogeneousMedium::new(
+            Arc::new(boundary),
+            Arc::new(density),
+            Arc::new(HenyeyGreenstein::new((0.5, 0.5, 0.5), 0.5)),
+        )
+    }
+
+    /// Rays along the x axis from `x = -5`, slightly apart so that they scatter at
+    /// different distances.
+    fn rays() -> impl Iterator<Item = Ray> {
+        (0..1000).map(|i| Ray::new(Vec3::new(-5, i as f64 * 1e-6, 0), Vec3::new(1, 0, 0)))
+    }
+
+    #[test]
+    fn ray_misses_medium() {
+        let medium = test_medium();
+        let miss_ray = Ray::new(Vec3::new(-5, 2, 0), Vec3::new(1, 0, 0));
+
+        assert!(medium.hit(&miss_ray, 0.0, f64::INFINITY).is_none());
+        let rng = RTRng::new();
+        assert_eq!(
+            1.0,
+            medium.transmittance(&miss_ray, 0.0, f64::INFINITY, &rng)
+        );
+    }
+
+    #[test]
+    fn rays_scatter_where_dense() {
+        let medium = test_medium();
+        for ray in rays() {
+            if let Some(hr) = medium.hit(&ray, 0.0, f64::INFINITY) {
+                assert!(hr.point.x() > 0.0 && hr.point.x() < 1.0, "{:?}", hr.point);
+                assert_eq!(Vec3::new(-1, 0, 0), hr.normal);
+            }
+        }
+
+        // Rays through only the empty half pass through.
+        let ray = Ray::new(Vec3::new(-0.5, 5, 0), Vec3::new(0, -1, 0));
+        assert!(medium.hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn tracking_matches_optical_depth() {
+        // The density integrates to 1 along the x axis, so `e^-1` of the light gets
+        // through.
+        let medium = test_medium();
+        let expected = (-1.0_f64).exp();
+
+        let passed = rays()
+            .filter(|ray| medium.hit(ray, 0.0, f64::INFINITY).is_none())
+            .count();
+        assert!((passed as f64 - 1000.0 * expected).abs() < 40.0, "{passed}");
+
+        let rng = RTRng::new();
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(1, 0, 0));
+        let mean = (0..1000)
+            .map(|_| medium.transmittance(&ray, 0.0, f64::INFINITY, &rng))
+            .sum::<f64>()
+            / 1000.0;
+        assert!((mean - expected).abs() < 0.02, "{mean}");
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let medium = HeterogeneousMedium::new(
+            test_medium().boundary,
+            Arc::new(NoiseDensity {
+                density: 1e9,
+                frequency: 1.0,
+                octaves: 1,
+                seed: 0,
+            }),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        );
+        let ray = Ray::new(Vec3::new(-5, 0, 0), Vec3::new(2, 0, 0));
+
+        assert!(medium.hit(&ray, 0.0, 1.999).is_none());
+        let hr = medium.hit(&ray, 0.0, 2.001).unwrap();
+        assert!((hr.t - 2.0).abs() < 1e-6);
+        assert!(medium.hit(&ray, 3.001, f64::INFINITY).is_none());
+    }
+}
@@ -1,11 +1,16 @@
 //! A *Hittable* refers to something that can be *hit* by a ray in the scene.
 //! This could be something concrete like a [`super::sphere::Sphere`], or
 //! something more general, such as an array of surfaces.
-use std::rc::Rc;
+use std::{io, sync::Arc};
 
-use crate::material::Material;
+use crate::{
+    color::Color,
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+    utils::rand::RTRng,
+};
 
-use super::{ray::Ray, vec3::Vec3};
+use super::{aabb::Aabb, ray::Ray, vec3::Vec3};
 
 pub struct HitRecord {
     /// The point where a ray hits the hittable
@@ -24,7 +29,19 @@ pub struct HitRecord {
     pub front_face: Option<bool>,
 
     /// The material of the hittable that was hit.
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
+
+    /// The texture coordinates of the surface at the hit, if the hittable has them.
+    pub uv: Option<(f64, f64)>,
+
+    /// The barycentric coordinates `(u, v)` of the hit within a triangle. These are
+    /// the weights of the triangle's second and third vertices, and the first vertex
+    /// has weight `1 - u - v`. `None` for hittables that aren't triangles.
+    pub barycentric: Option<(f64, f64)>,
+
+    /// The color of the surface at the hit, interpolated from colors given at the
+    /// vertices of a mesh. Diffuse materials multiply their albedo by it.
+    pub vertex_color: Option<Color>,
     // TODO:
     // - Determine if `normal` needs to be an `Option<Vec3>`.
     // - Given that `t` relates to an incident `Ray`, it looks like
@@ -34,13 +51,16 @@ pub struct HitRecord {
 }
 
 impl HitRecord {
-    pub fn new(point: Vec3, normal: Vec3, t: f64, material: Rc<dyn Material>) -> Self {
+    pub fn new(point: Vec3, normal: Vec3, t: f64, material: Arc<dyn Material>) -> Self {
         Self {
             point,
             normal,
             t,
             front_face: None,
             material,
+            uv: None,
+            barycentric: None,
+            vertex_color: None,
         }
     }
 
@@ -61,15 +81,39 @@ impl HitRecord {
 }
 
 /// Used to determine whether a hittable in the scene has been hit by a ray.
-pub trait Hittable {
+pub trait Hittable: Send + Sync {
     /// Returns a [`HitRecord`] describing where a given [`Ray`] hits the
     /// hittable or `None` if the ray does not hit it.
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
+
+    /// Returns an [`Aabb`] enclosing the hittable, or `None` if the hittable is
+    /// unbounded.
+    fn bounding_box(&self) -> Option<Aabb>;
+
+    /// Returns the fraction of light passing along a [`Ray`] between `t_min` and
+    /// `t_max`, for testing whether a point is in shadow. Surfaces block all of the
+    /// light if the ray hits them, while media such as fog let some of it through.
+    /// Media may return a random estimate, drawn with `rng`.
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, _rng: &RTRng) -> f64 {
+        match self.hit(ray, t_min, t_max) {
+            Some(_) => 0.0,
+            None => 1.0,
+        }
+    }
+
+    /// Describe the hittable for saving a scene, naming its materials with
+    /// `name_material`. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self, _name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Err(description::invalid_input(
+            "the scene contains an object that can't be saved",
+        ))
+    }
 }
 
 impl<T> Hittable for &[T]
 where
-    T: AsRef<dyn Hittable>,
+    T: AsRef<dyn Hittable> + Send + Sync,
 {
     fn hit(&self, ray: &Ray, t_min: f64, mut t_max: f64) -> Option<HitRecord> {
         let mut closest = None;
@@ -82,16 +126,57 @@ where
 
         closest
     }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let mut boxes = self.iter().map(|x| x.as_ref().bounding_box());
+        let first = boxes.next()??;
+        boxes.try_fold(first, |acc, bbox| Some(acc.union(&bbox?)))
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, rng: &RTRng) -> f64 {
+        let mut transmittance = 1.0;
+        for hittable in self.iter().map(|x| x.as_ref()) {
+            transmittance *= hittable.transmittance(ray, t_min, t_max, rng);
+            if transmittance <= 0.0 {
+                return 0.0;
+            }
+        }
+        transmittance
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use super::Hittable;
+    use crate::{
+        geometry::{ray::Ray, sphere::Sphere, vec3::Vec3},
+        material::Lambertian,
+    };
 
     #[test]
     fn hit_on_vec_of_hittable() {
-        todo!(
-            "Write this test later when we have more than one type of \
-            hittable to test in a single slice."
-        )
+        let material = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+        let world: Vec<Arc<dyn Hittable>> = vec![
+            Arc::new(Sphere::new(Vec3::new(0, 0, 5), 0.5, material.clone())),
+            Arc::new(Sphere::new(Vec3::new(0, 0, 2), 0.5, material.clone())),
+            Arc::new(Sphere::new(Vec3::new(0, 0, 8), 0.5, material)),
+        ];
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        let hr = world
+            .as_slice()
+            .hit(&ray, 0.0, f64::INFINITY)
+            .expect("Ray should hit the closest sphere");
+        assert_eq!(1.5, hr.t);
+
+        let hr = world
+            .as_slice()
+            .hit(&ray, 2.0, f64::INFINITY)
+            .expect("Ray should hit the sphere behind");
+        assert_eq!(2.5, hr.t);
+
+        assert!(world.as_slice().hit(&ray, 9.0, f64::INFINITY).is_none());
     }
 }
@@ -0,0 +1,213 @@
+//! This module contains the [`Mat4`] type.
+use std::ops;
+
+use super::vec3::Vec3;
+
+/// A 4x4 matrix of an affine transform, acting on points and vectors as column
+/// vectors in homogeneous coordinates. Points have a fourth coordinate of 1, so
+/// they are translated, and vectors have 0, so they aren't.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mat4 {
+    rows: [[f64; 4]; 4],
+}
+
+impl Mat4 {
+    /// The transform that leaves everything where it is.
+    pub const IDENTITY: Self = Self {
+        rows: [
+            [1.0, 0.0, 0.0, 0.0],
+            [0.0, 1.0, 0.0, 0.0],
+            [0.0, 0.0, 1.0, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ],
+    };
+
+    pub fn from_rows(rows: [[f64; 4]; 4]) -> Self {
+        Self { rows }
+    }
+
+    pub fn rows(&self) -> [[f64; 4]; 4] {
+        self.rows
+    }
+
+    /// A translation by `offset`.
+    pub fn translation(offset: Vec3) -> Self {
+        let mut m = Self::IDENTITY;
+        for i in 0..3 {
+            m.rows[i][3] = offset[i];
+        }
+        m
+    }
+
+    /// A scaling about the origin by a factor along each axis.
+    pub fn scaling(factors: Vec3) -> Self {
+        let mut m = Self::IDENTITY;
+        for i in 0..3 {
+            m.rows[i][i] = factors[i];
+        }
+        m
+    }
+
+    /// A rotation about `axis` through the origin, anticlockwise when looking back
+    /// along the axis.
+    pub fn rotation(axis: Vec3, degrees: f64) -> Self {
+        let (sin, cos) = degrees.to_radians().sin_cos();
+        let k = axis.normalized();
+        let (x, y, z) = (k.x(), k.y(), k.z());
+        let c = 1.0 - cos;
+        Self::from_rows([
+            [
+                cos + x * x * c,
+                x * y * c - z * sin,
+                x * z * c + y * sin,
+                0.0,
+            ],
+            [
+                y * x * c + z * sin,
+                cos + y * y * c,
+                y * z * c - x * sin,
+                0.0,
+            ],
+            [
+                z * x * c - y * sin,
+                z * y * c + x * sin,
+                cos + z * z * c,
+                0.0,
+            ],
+            [0.0, 0.0, 0.0, 1.0],
+        ])
+    }
+
+    pub fn transpose(&self) -> Self {
+        let mut m = *self;
+        for (i, row) in self.rows.iter().enumerate() {
+            for (j, &value) in row.iter().enumerate() {
+                m.rows[j][i] = value;
+            }
+        }
+        m
+    }
+
+    /// The inverse of the matrix, or `None` if it is singular, for example a
+    /// scaling by zero.
+    pub fn inverse(&self) -> Option<Self> {
+        // Gauss-Jordan elimination with partial pivoting, turning `a` into the
+        // identity and the identity into the inverse.
+        let mut a = self.rows;
+        let mut inverse = Self::IDENTITY.rows;
+        for col in 0..4 {
+            let pivot = (col..4)
+                .max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))
+                .unwrap();
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inverse.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inverse[col][j] *= scale;
+            }
+            for i in (0..4).filter(|&i| i != col) {
+                let factor = a[i][col];
+                for j in 0..4 {
+                    a[i][j] -= factor * a[col][j];
+                    inverse[i][j] -= factor * inverse[col][j];
+                }
+            }
+        }
+        Some(Self::from_rows(inverse))
+    }
+
+    pub fn transform_point(&self, point: Vec3) -> Vec3 {
+        self.transform_vector(point) + Vec3::new(self.rows[0][3], self.rows[1][3], self.rows[2][3])
+    }
+
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let row = |i: usize| Vec3::new(self.rows[i][0], self.rows[i][1], self.rows[i][2]).dot(v);
+        Vec3::new(row(0), row(1), row(2))
+    }
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+/// The product `a * b` is the transform that applies `b`, then `a`.
+impl ops::Mul for Mat4 {
+    type Output = Self;
+
+    fn mul(self, rhs: Self) -> Self::Output {
+        let mut m = Self::from_rows([[0.0; 4]; 4]);
+        for i in 0..4 {
+            for j in 0..4 {
+                m.rows[i][j] = (0..4).map(|k| self.rows[i][k] * rhs.rows[k][j]).sum();
+            }
+        }
+        m
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::super::vec3::Vec3;
+    use super::Mat4;
+
+    fn assert_close(expected: Vec3, actual: Vec3) {
+        assert!((expected - actual).length() < 1e-12, "{actual:?}");
+    }
+
+    #[test]
+    fn transform_points_and_vectors() {
+        let translation = Mat4::translation(Vec3::new(1, 2, 3));
+        assert_eq!(
+            Vec3::new(2, 2, 3),
+            translation.transform_point(Vec3::new(1, 0, 0))
+        );
+        assert_eq!(
+            Vec3::new(1, 0, 0),
+            translation.transform_vector(Vec3::new(1, 0, 0))
+        );
+
+        let scaling = Mat4::scaling(Vec3::new(2, 3, 4));
+        assert_eq!(
+            Vec3::new(2, 3, 4),
+            scaling.transform_point(Vec3::new(1, 1, 1))
+        );
+
+        let rotation = Mat4::rotation(Vec3::new(0, 0, 2), 90.0);
+        assert_close(
+            Vec3::new(0, 1, 0),
+            rotation.transform_vector(Vec3::new(1, 0, 0)),
+        );
+        assert_close(
+            Vec3::new(0, 0, 1),
+            rotation.transform_vector(Vec3::new(0, 0, 1)),
+        );
+    }
+
+    #[test]
+    fn products_apply_right_to_left() {
+        let m = Mat4::translation(Vec3::new(1, 0, 0)) * Mat4::scaling(Vec3::new(2, 2, 2));
+        assert_eq!(Vec3::new(3, 2, 2), m.transform_point(Vec3::new(1, 1, 1)));
+        assert_eq!(m, m * Mat4::IDENTITY);
+    }
+
+    #[test]
+    fn inverse() {
+        let m = Mat4::translation(Vec3::new(1, -2, 3))
+            * Mat4::rotation(Vec3::new(1, 1, 0), 30.0)
+            * Mat4::scaling(Vec3::new(2, 0.5, 1));
+        let inverse = m.inverse().unwrap();
+
+        let point = Vec3::new(0.3, -4, 7);
+        assert_close(point, inverse.transform_point(m.transform_point(point)));
+        assert_close(point, m.transform_point(inverse.transform_point(point)));
+
+        assert!(Mat4::scaling(Vec3::new(1, 0, 1)).inverse().is_none());
+    }
+}
@@ -0,0 +1,283 @@
+//! Triangle meshes. A mesh stores its vertices once, in buffers shared by all of its
+//! faces, and accelerates hits with its own [`Bvh`], so a mesh with many thousands
+//! of triangles is a single hittable in the scene.
+use std::{
+    fmt::{self, Debug},
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use crate::{
+    color::Color,
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    bvh::Bvh,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    triangle,
+    vec3::Vec3,
+};
+
+pub mod obj;
+pub mod ply;
+
+/// A triangular face of a [`Mesh`]. Each field holds indices into the mesh's
+/// buffers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Face {
+    pub positions: [usize; 3],
+
+    /// Indices of normals at each vertex. When `None`, the face is flat shaded.
+    pub normals: Option<[usize; 3]>,
+
+    /// Indices of texture coordinates at each vertex. When `None`, the barycentric
+    /// coordinates of hits are used instead.
+    pub uvs: Option<[usize; 3]>,
+
+    pub material: usize,
+}
+
+/// A mesh of triangles sharing vertex buffers.
+pub struct Mesh {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    /// Colors at each position, or empty if the mesh has no vertex colors.
+    colors: Vec<Color>,
+    faces: Vec<Face>,
+    materials: Vec<Arc<dyn Material>>,
+    bvh: Bvh,
+    path: Option<PathBuf>,
+}
+
+impl Mesh {
+    /// Create a mesh from its vertex buffers, faces and the materials they use.
+    ///
+    /// # Panics
+    /// Panics if a face refers to an element that isn't in the buffers.
+    pub fn new(
+        positions: Vec<Vec3>,
+        normals: Vec<Vec3>,
+        uvs: Vec<(f64, f64)>,
+        faces: Vec<Face>,
+        materials: Vec<Arc<dyn Material>>,
+    ) -> Self {
+        for face in &faces {
+            assert!(face.positions.iter().all(|&i| i < positions.len()));
+            assert!(face.normals.iter().flatten().all(|&i| i < normals.len()));
+            assert!(face.uvs.iter().flatten().all(|&i| i < uvs.len()));
+            assert!(face.material < materials.len());
+        }
+
+        let boxes: Vec<_> = faces
+            .iter()
+            .map(|face| triangle::bounding_box(face.positions.map(|i| positions[i])))
+            .collect();
+        Self {
+            bvh: Bvh::new(&boxes),
+            positions,
+            normals,
+            uvs,
+            colors: vec![],
+            faces,
+            materials,
+            path: None,
+        }
+    }
+
+    /// Give the mesh a color at each of its positions, which is interpolated across
+    /// faces and multiplies the albedo of diffuse materials.
+    ///
+    /// # Panics
+    /// Panics if there isn't a color for each position.
+    pub fn with_colors(self, colors: Vec<Color>) -> Self {
+        assert_eq!(self.positions.len(), colors.len());
+        Self { colors, ..self }
+    }
+
+    pub fn positions(&self) -> &[Vec3] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[Vec3] {
+        &self.normals
+    }
+
+    pub fn uvs(&self) -> &[(f64, f64)] {
+        &self.uvs
+    }
+
+    pub fn colors(&self) -> &[Color] {
+        &self.colors
+    }
+
+    pub fn faces(&self) -> &[Face] {
+        &self.faces
+    }
+
+    pub fn materials(&self) -> &[Arc<dyn Material>] {
+        &self.materials
+    }
+
+    /// The path of the file the mesh was loaded from, or `None` if it was built in
+    /// memory.
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+}
+
+/// Load a mesh from a file, choosing the format from its extension. Faces that
+/// don't specify a material use `default_material`, which is always the first of
+/// the mesh's materials.
+///
+/// Supported formats are Wavefront OBJ (`.obj`), with materials from `.mtl` files,
+/// and PLY (`.ply`).
+///
+/// # Errors
+/// Returns an error if the format isn't supported, or the file can't be read.
+pub fn load(path: impl AsRef<Path>, default_material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(str::to_ascii_lowercase);
+    match extension.as_deref() {
+        Some("obj") => obj::load(path, default_material),
+        Some("ply") => ply::load(path, default_material),
+        _ => Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("Unsupported mesh format {}", path.display()),
+        )),
+    }
+}
+
+impl Debug for Mesh {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Mesh")
+            .field("vertices", &self.positions.len())
+            .field("faces", &self.faces.len())
+            .field("path", &self.path)
+            .finish()
+    }
+}
+
+impl Hittable for Mesh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, |index, t_min, t_max| {
+            let face = &self.faces[index];
+            let vertices = face.positions.map(|i| self.positions[i]);
+            let (t, u, v) = triangle::intersect(vertices, ray, t_min, t_max)?;
+            let mut hr = triangle::hit_record(
+                ray,
+                t,
+                (u, v),
+                vertices,
+                face.normals.map(|n| n.map(|i| self.normals[i])),
+                face.uvs.map(|uv| uv.map(|i| self.uvs[i])),
+                Arc::clone(&self.materials[face.material]),
+            );
+            if !self.colors.is_empty() {
+                let colors = face.positions.map(|i| self.colors[i]);
+                hr.vertex_color = Some(triangle::interpolate(colors, (u, v)));
+            }
+            Some(hr)
+        })
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bvh.bounding_box()
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        let path = self.path().ok_or_else(|| {
+            description::invalid_input("only meshes loaded from a file can be saved")
+        })?;
+        Ok(ObjectDescription::Mesh {
+            path: description::absolute_path(path)?,
+            material: name_material(&self.materials()[0])?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::{Face, Mesh};
+    use crate::material::{Lambertian, Material, Metal};
+
+    /// A grid of `n` by `n` unit squares in the plane `z = 0`, each made of two
+    /// triangles. Triangles in the left half use the second material.
+    fn grid(n: usize) -> Mesh {
+        let positions = (0..=n)
+            .flat_map(|y| (0..=n).map(move |x| Vec3::new(x as f64, y as f64, 0.0)))
+            .collect();
+        let index = |x: usize, y: usize| y * (n + 1) + x;
+        let mut faces = vec![];
+        for y in 0..n {
+            for x in 0..n {
+                let material = usize::from(x < n / 2);
+                let face = |positions| Face {
+                    positions,
+                    normals: None,
+                    uvs: None,
+                    material,
+                };
+                faces.push(face([index(x, y), index(x + 1, y), index(x + 1, y + 1)]));
+                faces.push(face([index(x, y), index(x + 1, y + 1), index(x, y + 1)]));
+            }
+        }
+        let materials: Vec<Arc<dyn Material>> = vec![
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+            Arc::new(Metal::new((0.5, 0.5, 0.5), 0.0)),
+        ];
+        Mesh::new(positions, vec![], vec![], faces, materials)
+    }
+
+    #[test]
+    fn ray_hits_mesh() {
+        let mesh = grid(10);
+        assert_eq!(200, mesh.faces().len());
+
+        let ray = Ray::new(Vec3::new(7.25, 3.5, 2), Vec3::new(0, 0, -1));
+        let hr = mesh.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(7.25, 3.5, 0), hr.point);
+        assert_eq!(Vec3::new(0, 0, 1), hr.normal);
+        assert!(Arc::ptr_eq(&mesh.materials()[0], &hr.material));
+
+        let ray = Ray::new(Vec3::new(2.5, 3.5, 2), Vec3::new(0, 0, -1));
+        let hr = mesh.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!(Arc::ptr_eq(&mesh.materials()[1], &hr.material));
+
+        let ray = Ray::new(Vec3::new(10.5, 3.5, 2), Vec3::new(0, 0, -1));
+        assert!(mesh.hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn mesh_has_bounding_box() {
+        let bbox = grid(4).bounding_box().unwrap();
+        assert!(bbox.min().x() <= 0.0 && bbox.max().x() >= 4.0);
+        assert!(bbox.min().z() < 0.0 && bbox.max().z() > 0.0);
+    }
+
+    #[test]
+    #[should_panic]
+    fn faces_must_refer_to_vertices() {
+        let face = Face {
+            positions: [0, 1, 3],
+            normals: None,
+            uvs: None,
+            material: 0,
+        };
+        let positions = vec![Vec3::new(0, 0, 0); 3];
+        let material: Arc<dyn Material> = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+        Mesh::new(positions, vec![], vec![], vec![face], vec![material]);
+    }
+}
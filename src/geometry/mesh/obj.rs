@@ -0,0 +1,460 @@
+//! Reader for Wavefront OBJ meshes and their MTL material libraries.
+//!
+//! Polygonal faces are triangulated as fans around their first vertex, and vertex
+//! normals and texture coordinates are kept. Statements other than vertex data,
+//! faces and materials, such as groups, smoothing groups, lines and curves, are
+//! ignored.
+//!
+//! MTL materials are mapped onto the renderer's materials:
+//! - Transparent materials (`d` below 1, or an `illum` model with refraction)
+//!   become [`Dielectric`]s with the `Ni` refractive index.
+//! - Reflective materials (an `illum` model with reflection, or a black `Kd`) with
+//!   a non-black `Ks` become [`Metal`]s with albedo `Ks`. Smaller `Ns` exponents
+//!   give fuzzier reflections.
+//! - Anything else becomes a [`Lambertian`] with albedo `Kd`.
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use super::{Face, Mesh};
+use crate::{
+    geometry::vec3::Vec3,
+    material::{Dielectric, Lambertian, Material, Metal},
+};
+
+/// The albedo of materials without a `Kd` statement.
+const DEFAULT_DIFFUSE: (f64, f64, f64) = (0.8, 0.8, 0.8);
+
+/// The refractive index of transparent materials without an `Ni` statement.
+const DEFAULT_REFRACTIVE_INDEX: f64 = 1.5;
+
+/// Load an OBJ mesh. Material libraries are found relative to the mesh's
+/// directory. Faces that don't use a material from a library use
+/// `default_material`, which is always the first of the mesh's materials.
+///
+/// # Errors
+/// Returns an error if the mesh or its material libraries can't be read or are
+/// invalid. The error message gives the line of the invalid input.
+pub fn load(path: impl AsRef<Path>, default_material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    let mesh = File::open(path)
+        .and_then(|file| read(BufReader::new(file), base_dir, default_material))
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    Ok(Mesh {
+        path: Some(path.to_path_buf()),
+        ..mesh
+    })
+}
+
+/// Read an OBJ mesh. Material libraries are found relative to `base_dir`.
+///
+/// # Errors
+/// Returns an error if reading fails, or the mesh or its material libraries are
+/// invalid.
+pub fn read(
+    reader: impl BufRead,
+    base_dir: &Path,
+    default_material: Arc<dyn Material>,
+) -> io::Result<Mesh> {
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    let mut faces = vec![];
+    let mut materials = vec![default_material];
+
+    let mut library = HashMap::new();
+    // Indices in `materials` of the library materials used so far.
+    let mut used = HashMap::new();
+    let mut material = 0;
+
+    for_each_statement(reader, |keyword, args| {
+        match keyword {
+            "v" => {
+                // An optional weight is only meaningful for rational curves.
+                let [x, y, z] = floats(args, 3, 4)?;
+                positions.push(Vec3::new(x, y, z));
+            }
+            "vn" => {
+                let [x, y, z] = floats(args, 3, 3)?;
+                normals.push(Vec3::new(x, y, z));
+            }
+            "vt" => {
+                let [u, v] = floats(args, 1, 3)?;
+                uvs.push((u, v));
+            }
+            "f" => {
+                let counts = (positions.len(), uvs.len(), normals.len());
+                let vertices = args
+                    .iter()
+                    .map(|vertex| face_vertex(vertex, counts))
+                    .collect::<Result<Vec<_>, _>>()?;
+                if vertices.len() < 3 {
+                    return Err("faces must have at least 3 vertices".into());
+                }
+                let has_uvs = vertices[0].1.is_some();
+                let has_normals = vertices[0].2.is_some();
+                if vertices
+                    .iter()
+                    .any(|v| v.1.is_some() != has_uvs || v.2.is_some() != has_normals)
+                {
+                    return Err("all vertices of a face must have the same attributes".into());
+                }
+
+                for i in 1..vertices.len() - 1 {
+                    let corners = [vertices[0], vertices[i], vertices[i + 1]];
+                    faces.push(Face {
+                        positions: corners.map(|v| v.0),
+                        uvs: has_uvs.then(|| corners.map(|v| v.1.unwrap_or_default())),
+                        normals: has_normals.then(|| corners.map(|v| v.2.unwrap_or_default())),
+                        material,
+                    });
+                }
+            }
+            "mtllib" => {
+                for name in args {
+                    let path = base_dir.join(name);
+                    let file = File::open(&path)
+                        .and_then(|file| read_materials(BufReader::new(file)))
+                        .map_err(|e| format!("{}: {e}", path.display()))?;
+                    library.extend(file);
+                }
+            }
+            "usemtl" => {
+                let name = args.join(" ");
+                material = match used.get(&name) {
+                    Some(&index) => index,
+                    None => {
+                        let built = library
+                            .get(&name)
+                            .ok_or_else(|| format!("unknown material {name:?}"))?;
+                        materials.push(Arc::clone(built));
+                        used.insert(name, materials.len() - 1);
+                        materials.len() - 1
+                    }
+                };
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    Ok(Mesh::new(positions, normals, uvs, faces, materials))
+}
+
+/// Read the materials of an MTL library, by name.
+///
+/// # Errors
+/// Returns an error if reading fails, or the library is invalid.
+pub fn read_materials(reader: impl BufRead) -> io::Result<HashMap<String, Arc<dyn Material>>> {
+    let mut descriptions: Vec<(String, MtlMaterial)> = vec![];
+    for_each_statement(reader, |keyword, args| {
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err("materials must have a name".into());
+            }
+            descriptions.push((args.join(" "), MtlMaterial::default()));
+            return Ok(());
+        }
+
+        let Some((_, description)) = descriptions.last_mut() else {
+            return match keyword {
+                "Kd" | "Ks" | "Ns" | "Ni" | "d" | "Tr" | "illum" => {
+                    Err(format!("{keyword} must follow a newmtl statement"))
+                }
+                _ => Ok(()),
+            };
+        };
+        match keyword {
+            "Kd" => description.diffuse = Some(color(args)?),
+            "Ks" => description.specular = Some(color(args)?),
+            "Ns" => description.shininess = Some(floats::<1>(args, 1, 1)?[0]),
+            "Ni" => description.refractive_index = Some(floats::<1>(args, 1, 1)?[0]),
+            "d" => description.dissolve = Some(floats::<1>(args, 1, 1)?[0]),
+            "Tr" => description.dissolve = Some(1.0 - floats::<1>(args, 1, 1)?[0]),
+            "illum" => {
+                let [illum] = args else {
+                    return Err("illum takes one value".into());
+                };
+                description.illum = Some(illum.parse().map_err(|_| "invalid illum model")?);
+            }
+            _ => {}
+        }
+        Ok(())
+    })?;
+
+    Ok(descriptions
+        .into_iter()
+        .map(|(name, description)| (name, description.build()))
+        .collect())
+}
+
+/// The properties of an MTL material that are mapped onto the renderer's materials.
+#[derive(Debug, Default)]
+struct MtlMaterial {
+    diffuse: Option<(f64, f64, f64)>,
+    specular: Option<(f64, f64, f64)>,
+    shininess: Option<f64>,
+    refractive_index: Option<f64>,
+    dissolve: Option<f64>,
+    illum: Option<u32>,
+}
+
+impl MtlMaterial {
+    fn build(&self) -> Arc<dyn Material> {
+        let is_black = |color: (f64, f64, f64)| color.0 <= 0.0 && color.1 <= 0.0 && color.2 <= 0.0;
+        let diffuse = self.diffuse.unwrap_or(DEFAULT_DIFFUSE);
+
+        let refracts = matches!(self.illum, Some(4 | 6 | 7 | 9));
+        if refracts || self.dissolve.is_some_and(|d| d < 1.0) {
+            return Arc::new(Dielectric::new(
+                self.refractive_index.unwrap_or(DEFAULT_REFRACTIVE_INDEX),
+            ));
+        }
+
+        let reflects = matches!(self.illum, Some(3 | 5 | 8));
+        match self.specular {
+            Some(specular) if !is_black(specular) && (reflects || is_black(diffuse)) => {
+                // The usual mapping of a Phong exponent to a roughness.
+                let fuzz = self
+                    .shininess
+                    .map_or(0.0, |ns| (2.0 / (ns.max(0.0) + 2.0)).sqrt());
+                Arc::new(Metal::new(specular, fuzz))
+            }
+            _ => Arc::new(Lambertian::new(diffuse.0, diffuse.1, diffuse.2)),
+        }
+    }
+}
+
+/// Call `f` with the keyword and arguments of each statement, skipping comments
+/// and blank lines and joining lines continued with a backslash. Errors returned by
+/// `f` are given the line of the statement.
+fn for_each_statement(
+    reader: impl BufRead,
+    mut f: impl FnMut(&str, &[&str]) -> Result<(), String>,
+) -> io::Result<()> {
+    let mut statement = String::new();
+    let mut start = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        if statement.is_empty() {
+            start = i + 1;
+        }
+        let line = line.split('#').next().unwrap_or_default().trim_end();
+        if let Some(continued) = line.strip_suffix('\\') {
+            statement.push_str(continued);
+            statement.push(' ');
+            continue;
+        }
+        statement.push_str(line);
+
+        let mut words = statement.split_whitespace();
+        if let Some(keyword) = words.next() {
+            let args: Vec<_> = words.collect();
+            f(keyword, &args)
+                .map_err(|message| invalid_data(format!("line {start}: {message}")))?;
+        }
+        statement.clear();
+    }
+    Ok(())
+}
+
+/// Parse between `min` and `max` numbers. Missing numbers up to `N` are zero.
+fn floats<const N: usize>(args: &[&str], min: usize, max: usize) -> Result<[f64; N], String> {
+    if args.len() < min || args.len() > max {
+        return Err(if min == max {
+            format!("expected {min} numbers")
+        } else {
+            format!("expected {min} to {max} numbers")
+        });
+    }
+    let mut values = [0.0; N];
+    for (value, arg) in values.iter_mut().zip(args) {
+        *value = arg.parse().map_err(|_| format!("invalid number {arg:?}"))?;
+    }
+    Ok(values)
+}
+
+/// Parse an RGB color. A single value is used for all channels.
+fn color(args: &[&str]) -> Result<(f64, f64, f64), String> {
+    match args.len() {
+        1 => {
+            let [v] = floats(args, 1, 1)?;
+            Ok((v, v, v))
+        }
+        _ => floats(args, 3, 3).map(|[r, g, b]| (r, g, b)),
+    }
+}
+
+/// Parse a face vertex of the form `v`, `v/vt`, `v//vn` or `v/vt/vn`, given the
+/// number of positions, texture coordinates and normals defined so far. Returns
+/// zero-based indices.
+fn face_vertex(
+    vertex: &str,
+    (positions, uvs, normals): (usize, usize, usize),
+) -> Result<(usize, Option<usize>, Option<usize>), String> {
+    let mut parts = vertex.split('/');
+    let position = index(parts.next().unwrap_or_default(), positions)?;
+    let uv = match parts.next() {
+        None | Some("") => None,
+        Some(uv) => Some(index(uv, uvs)?),
+    };
+    let normal = parts
+        .next()
+        .map(|normal| index(normal, normals))
+        .transpose()?;
+    if parts.next().is_some() {
+        return Err(format!("invalid face vertex {vertex:?}"));
+    }
+    Ok((position, uv, normal))
+}
+
+/// Resolve a one-based index, or a negative index relative to the end of the
+/// `count` elements defined so far.
+fn index(index: &str, count: usize) -> Result<usize, String> {
+    let resolved = match index.parse::<isize>() {
+        Ok(i) if i > 0 => Some(i as usize - 1),
+        Ok(i) if i < 0 => count.checked_sub(i.unsigned_abs()),
+        _ => return Err(format!("invalid index {index:?}")),
+    };
+    resolved
+        .filter(|&i| i < count)
+        .ok_or_else(|| format!("index {index} refers to an undefined element"))
+}
+
+fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::Path, sync::Arc};
+
+    use super::{read, read_materials};
+    use crate::{
+        geometry::{hittable::Hittable, ray::Ray, vec3::Vec3},
+        material::{Lambertian, Material},
+        scene::description::{MaterialDescription, TextureDescription},
+    };
+
+    fn gray() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(0.5, 0.5, 0.5))
+    }
+
+    fn read_str(text: &str) -> std::io::Result<super::Mesh> {
+        read(text.as_bytes(), Path::new(""), gray())
+    }
+
+    #[test]
+    fn read_triangulated_polygons() {
+        let mesh = read_str(
+            "# A unit square and a triangle\n\
+             v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n\
+             vt 0 0\nvt 1 0\nvt 1 1\nvt 0 1\n\
+             vn 0 0 1\n\
+             o square\n\
+             f 1/1/1 2/2/1 3/3/1 4/4/1\n\
+             f -4 -3 \\\n  -1\n",
+        )
+        .unwrap();
+
+        assert_eq!(4, mesh.positions().len());
+        assert_eq!(3, mesh.faces().len());
+        let faces = mesh.faces();
+        assert_eq!([0, 1, 2], faces[0].positions);
+        assert_eq!([0, 2, 3], faces[1].positions);
+        assert_eq!(Some([0, 2, 3]), faces[1].uvs);
+        assert_eq!(Some([0, 0, 0]), faces[1].normals);
+        assert_eq!([0, 1, 3], faces[2].positions);
+        assert_eq!(None, faces[2].uvs);
+
+        let ray = Ray::new(Vec3::new(0.25, 0.75, 1), Vec3::new(0, 0, -1));
+        let hr = mesh.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(1.0, hr.t);
+        assert_eq!(Some((0.25, 0.75)), hr.uv);
+        assert_eq!(Vec3::new(0, 0, 1), hr.normal);
+    }
+
+    #[test]
+    fn invalid_meshes_give_line() {
+        for (text, line) in [
+            ("v 0 0 0\nv 1 0 0\nf 1 2 3\n", 3),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2\n", 4),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1/1 2 3\n", 4),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 -4\n", 4),
+            ("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 0 1 2\n", 4),
+            ("v 0 0\n", 1),
+            ("# comment\nvn 0 0 x\n", 2),
+            ("usemtl missing\n", 1),
+        ] {
+            let message = read_str(text).err().unwrap().to_string();
+            assert!(message.starts_with(&format!("line {line}:")), "{message}");
+        }
+    }
+
+    #[test]
+    fn mtl_materials() {
+        let materials = read_materials(
+            "newmtl matte\nKd 0.1 0.2 0.3\nKs 0.5 0.5 0.5\n\
+             newmtl mirror\nKd 0 0 0\nKs 0.9 0.8 0.7\n\
+             newmtl brushed steel\nillum 3\nKd 0.2\nKs 0.6\nNs 198\n\
+             newmtl glass\nd 0.1\nNi 1.33\n"
+                .as_bytes(),
+        )
+        .unwrap();
+
+        let material = |name: &str| materials[name].describe().unwrap();
+        let MaterialDescription::Lambertian {
+            albedo: TextureDescription::Color(matte),
+        } = material("matte")
+        else {
+            panic!("matte isn't lambertian");
+        };
+        assert_eq!((0.1, 0.2, 0.3), matte);
+
+        let MaterialDescription::Metal {
+            albedo: TextureDescription::Color(mirror),
+            fuzz,
+        } = material("mirror")
+        else {
+            panic!("mirror isn't metal");
+        };
+        assert_eq!((0.9, 0.8, 0.7), mirror);
+        assert_eq!(0.0, fuzz);
+
+        let MaterialDescription::Metal {
+            albedo: TextureDescription::Color(steel),
+            fuzz,
+        } = material("brushed steel")
+        else {
+            panic!("brushed steel isn't metal");
+        };
+        assert_eq!((0.6, 0.6, 0.6), steel);
+        assert!((fuzz - 0.1).abs() < 1e-12);
+
+        let MaterialDescription::Dielectric { refractive_index } = material("glass") else {
+            panic!("glass isn't dielectric");
+        };
+        assert_eq!(1.33, refractive_index);
+
+        assert!(read_materials("Kd 1 1 1\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn load_example_mesh() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/pyramid.obj");
+        let default_material = gray();
+        let mesh = super::load(path, Arc::clone(&default_material)).unwrap();
+
+        assert_eq!(Some(Path::new(path)), mesh.path());
+        assert_eq!(6, mesh.faces().len());
+        assert_eq!(3, mesh.materials().len());
+        assert!(Arc::ptr_eq(&default_material, &mesh.materials()[0]));
+        let base = mesh.materials()[1].describe().unwrap();
+        assert_eq!("metal", base.kind());
+    }
+}
@@ -0,0 +1,580 @@
+//! Reader for PLY (Stanford polygon file format) meshes, stored as ASCII or
+//! binary.
+//!
+//! Vertices are read with their positions, and their normals, colors and texture
+//! coordinates when present. Colors are decoded from the display encoding, like
+//! 8-bit images. Faces are read from a list of vertex indices, and polygons are
+//! triangulated as fans around their first vertex. Other elements and properties
+//! are skipped.
+use std::{
+    fs::File,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    sync::Arc,
+};
+
+use super::{Face, Mesh};
+use crate::{color::Color, geometry::vec3::Vec3, material::Material};
+
+/// Load a PLY mesh, with `material` used by all of its faces.
+///
+/// # Errors
+/// Returns an error if the file can't be read, or isn't a valid PLY mesh.
+pub fn load(path: impl AsRef<Path>, material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let path = path.as_ref();
+    let mesh = File::open(path)
+        .and_then(|file| read(BufReader::new(file), material))
+        .map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))?;
+    Ok(Mesh {
+        path: Some(path.to_path_buf()),
+        ..mesh
+    })
+}
+
+/// Read a PLY mesh, with `material` used by all of its faces.
+///
+/// # Errors
+/// Returns an error if reading fails, or the data isn't a valid PLY mesh.
+pub fn read(mut reader: impl BufRead, material: Arc<dyn Material>) -> io::Result<Mesh> {
+    let header = read_header(&mut reader)?;
+    let mut source = match header.format {
+        Format::Ascii => Source::Ascii {
+            reader,
+            tokens: vec![],
+        },
+        Format::BinaryLittleEndian => Source::Binary {
+            reader,
+            big_endian: false,
+        },
+        Format::BinaryBigEndian => Source::Binary {
+            reader,
+            big_endian: true,
+        },
+    };
+
+    let mut vertices = Vertices::default();
+    let mut polygons: Vec<Vec<usize>> = vec![];
+    for element in &header.elements {
+        match element.name.as_str() {
+            "vertex" => vertices = read_vertices(&mut source, element)?,
+            "face" => polygons = read_faces(&mut source, element)?,
+            _ => {
+                for _ in 0..element.count {
+                    for property in &element.properties {
+                        source.skip(property)?;
+                    }
+                    source.end_row()?;
+                }
+            }
+        }
+    }
+
+    let vertex_count = vertices.positions.len();
+    let mut faces = vec![];
+    for polygon in polygons {
+        if polygon.len() < 3 {
+            return Err(invalid_data("Faces must have at least 3 vertices"));
+        }
+        if polygon.iter().any(|&i| i >= vertex_count) {
+            return Err(invalid_data("Face refers to an undefined vertex"));
+        }
+        for i in 1..polygon.len() - 1 {
+            let positions = [polygon[0], polygon[i], polygon[i + 1]];
+            faces.push(Face {
+                positions,
+                normals: (!vertices.normals.is_empty()).then_some(positions),
+                uvs: (!vertices.uvs.is_empty()).then_some(positions),
+                material: 0,
+            });
+        }
+    }
+
+    let mesh = Mesh::new(
+        vertices.positions,
+        vertices.normals,
+        vertices.uvs,
+        faces,
+        vec![material],
+    );
+    Ok(if vertices.colors.is_empty() {
+        mesh
+    } else {
+        mesh.with_colors(vertices.colors)
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+/// The type of a property value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scalar {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl Scalar {
+    fn parse(name: &str) -> io::Result<Self> {
+        Ok(match name {
+            "char" | "int8" => Scalar::Int8,
+            "uchar" | "uint8" => Scalar::UInt8,
+            "short" | "int16" => Scalar::Int16,
+            "ushort" | "uint16" => Scalar::UInt16,
+            "int" | "int32" => Scalar::Int32,
+            "uint" | "uint32" => Scalar::UInt32,
+            "float" | "float32" => Scalar::Float32,
+            "double" | "float64" => Scalar::Float64,
+            _ => return Err(invalid_data(format!("Unknown PLY type {name:?}"))),
+        })
+    }
+
+    /// The value that represents full intensity, when the type is used for colors.
+    fn color_scale(&self) -> f64 {
+        match self {
+            Scalar::Int8 => i8::MAX as f64,
+            Scalar::UInt8 => u8::MAX as f64,
+            Scalar::Int16 => i16::MAX as f64,
+            Scalar::UInt16 => u16::MAX as f64,
+            Scalar::Int32 => i32::MAX as f64,
+            Scalar::UInt32 => u32::MAX as f64,
+            Scalar::Float32 | Scalar::Float64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Property {
+    Scalar {
+        name: String,
+        ty: Scalar,
+    },
+    List {
+        name: String,
+        count: Scalar,
+        item: Scalar,
+    },
+}
+
+impl Property {
+    fn name(&self) -> &str {
+        match self {
+            Property::Scalar { name, .. } | Property::List { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    /// The index of the scalar property with one of the given names.
+    fn scalar(&self, names: &[&str]) -> Option<(usize, Scalar)> {
+        self.properties
+            .iter()
+            .enumerate()
+            .find_map(|(i, p)| match p {
+                Property::Scalar { name, ty } if names.contains(&name.as_str()) => Some((i, *ty)),
+                _ => None,
+            })
+    }
+}
+
+struct Header {
+    format: Format,
+    elements: Vec<Element>,
+}
+
+fn read_header(reader: &mut impl BufRead) -> io::Result<Header> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim_end() != "ply" {
+        return Err(invalid_data("Missing PLY header"));
+    }
+
+    let mut format = None;
+    let mut elements: Vec<Element> = vec![];
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("Unexpected end of PLY header"));
+        }
+        let words: Vec<_> = line.split_whitespace().collect();
+        match words[..] {
+            ["end_header"] => break,
+            [] | ["comment" | "obj_info", ..] => {}
+            ["format", name, "1.0"] => {
+                format = Some(match name {
+                    "ascii" => Format::Ascii,
+                    "binary_little_endian" => Format::BinaryLittleEndian,
+                    "binary_big_endian" => Format::BinaryBigEndian,
+                    _ => return Err(invalid_data(format!("Unsupported PLY format {name}"))),
+                });
+            }
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(invalid_data)?,
+                properties: vec![],
+            }),
+            ["property", ..] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data("PLY property before any element"))?;
+                element.properties.push(match words[1..] {
+                    ["list", count, item, name] => Property::List {
+                        name: name.to_string(),
+                        count: Scalar::parse(count)?,
+                        item: Scalar::parse(item)?,
+                    },
+                    [ty, name] => Property::Scalar {
+                        name: name.to_string(),
+                        ty: Scalar::parse(ty)?,
+                    },
+                    _ => {
+                        return Err(invalid_data(format!(
+                            "Invalid PLY property {:?}",
+                            line.trim()
+                        )))
+                    }
+                });
+            }
+            _ => {
+                return Err(invalid_data(format!(
+                    "Invalid PLY header line {:?}",
+                    line.trim()
+                )))
+            }
+        }
+    }
+
+    let format = format.ok_or_else(|| invalid_data("Missing PLY format"))?;
+    Ok(Header { format, elements })
+}
+
+/// Where property values are read from.
+enum Source<R> {
+    /// Values separated by whitespace, one element per line. `tokens` holds the
+    /// values of the current line that haven't been read yet, in reverse.
+    Ascii {
+        reader: R,
+        tokens: Vec<String>,
+    },
+    Binary {
+        reader: R,
+        big_endian: bool,
+    },
+}
+
+impl<R: BufRead> Source<R> {
+    fn scalar(&mut self, ty: Scalar) -> io::Result<f64> {
+        match self {
+            Source::Ascii { reader, tokens } => {
+                while tokens.is_empty() {
+                    let mut line = String::new();
+                    if reader.read_line(&mut line)? == 0 {
+                        return Err(io::ErrorKind::UnexpectedEof.into());
+                    }
+                    tokens.extend(line.split_whitespace().rev().map(str::to_string));
+                }
+                let token = tokens.pop().unwrap_or_default();
+                token
+                    .parse()
+                    .map_err(|_| invalid_data(format!("Invalid PLY value {token:?}")))
+            }
+            Source::Binary { reader, big_endian } => {
+                macro_rules! read {
+                    ($t:ty) => {{
+                        let mut bytes = [0; std::mem::size_of::<$t>()];
+                        reader.read_exact(&mut bytes)?;
+                        if *big_endian {
+                            <$t>::from_be_bytes(bytes) as f64
+                        } else {
+                            <$t>::from_le_bytes(bytes) as f64
+                        }
+                    }};
+                }
+                Ok(match ty {
+                    Scalar::Int8 => read!(i8),
+                    Scalar::UInt8 => read!(u8),
+                    Scalar::Int16 => read!(i16),
+                    Scalar::UInt16 => read!(u16),
+                    Scalar::Int32 => read!(i32),
+                    Scalar::UInt32 => read!(u32),
+                    Scalar::Float32 => read!(f32),
+                    Scalar::Float64 => read!(f64),
+                })
+            }
+        }
+    }
+
+    /// Read a non-negative integer, such as a list length or an index.
+    fn index(&mut self, ty: Scalar) -> io::Result<usize> {
+        let value = self.scalar(ty)?;
+        if value < 0.0 || value.fract() != 0.0 {
+            return Err(invalid_data(format!("Invalid PLY index {value}")));
+        }
+        Ok(value as usize)
+    }
+
+    fn skip(&mut self, property: &Property) -> io::Result<()> {
+        match *property {
+            Property::Scalar { ty, .. } => {
+                self.scalar(ty)?;
+            }
+            Property::List { count, item, .. } => {
+                for _ in 0..self.index(count)? {
+                    self.scalar(item)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Finish reading an element, checking that ASCII elements have no extra values.
+    fn end_row(&mut self) -> io::Result<()> {
+        match self {
+            Source::Ascii { tokens, .. } if !tokens.is_empty() => {
+                Err(invalid_data("PLY element has too many values"))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct Vertices {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<(f64, f64)>,
+    colors: Vec<Color>,
+}
+
+fn read_vertices(source: &mut Source<impl BufRead>, element: &Element) -> io::Result<Vertices> {
+    // The indices of the properties holding each attribute, and their types.
+    let attribute = |names: [&[&str]; 3]| {
+        let found = names.map(|names| element.scalar(names));
+        match found {
+            [Some(a), Some(b), Some(c)] => Some([a, b, c]),
+            _ => None,
+        }
+    };
+    let position = attribute([&["x"], &["y"], &["z"]])
+        .ok_or_else(|| invalid_data("PLY vertices must have x, y and z properties"))?;
+    let normal = attribute([&["nx"], &["ny"], &["nz"]]);
+    let color = attribute([
+        &["red", "diffuse_red", "r"],
+        &["green", "diffuse_green", "g"],
+        &["blue", "diffuse_blue", "b"],
+    ]);
+    let uv = element
+        .scalar(&["u", "s", "texture_u", "texture_s"])
+        .zip(element.scalar(&["v", "t", "texture_v", "texture_t"]));
+
+    let mut vertices = Vertices::default();
+    let mut values = vec![0.0; element.properties.len()];
+    for _ in 0..element.count {
+        for (value, property) in values.iter_mut().zip(&element.properties) {
+            match *property {
+                Property::Scalar { ty, .. } => *value = source.scalar(ty)?,
+                Property::List { .. } => source.skip(property)?,
+            }
+        }
+        source.end_row()?;
+
+        let vec3 =
+            |[x, y, z]: [(usize, Scalar); 3]| Vec3::new(values[x.0], values[y.0], values[z.0]);
+        vertices.positions.push(vec3(position));
+        if let Some(normal) = normal {
+            vertices.normals.push(vec3(normal));
+        }
+        if let Some((u, v)) = uv {
+            vertices.uvs.push((values[u.0], values[v.0]));
+        }
+        if let Some(color) = color {
+            let [r, g, b] = color.map(|(i, ty)| (values[i] / ty.color_scale()).clamp(0.0, 1.0));
+            vertices.colors.push(Color::from_encoded(r, g, b));
+        }
+    }
+    Ok(vertices)
+}
+
+fn read_faces(source: &mut Source<impl BufRead>, element: &Element) -> io::Result<Vec<Vec<usize>>> {
+    let indices = element
+        .properties
+        .iter()
+        .position(|p| {
+            matches!(p, Property::List { .. })
+                && matches!(p.name(), "vertex_indices" | "vertex_index")
+        })
+        .ok_or_else(|| invalid_data("PLY faces must have a vertex_indices list"))?;
+
+    // The count comes from the header, so it isn't trusted to preallocate.
+    let mut faces = vec![];
+    for _ in 0..element.count {
+        let mut face = vec![];
+        for (i, property) in element.properties.iter().enumerate() {
+            match *property {
+                Property::List { count, item, .. } if i == indices => {
+                    for _ in 0..source.index(count)? {
+                        face.push(source.index(item)?);
+                    }
+                }
+                _ => source.skip(property)?,
+            }
+        }
+        source.end_row()?;
+        faces.push(face);
+    }
+    Ok(faces)
+}
+
+fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::read;
+    use crate::{
+        color::Color,
+        geometry::{hittable::Hittable, ray::Ray, vec3::Vec3},
+        material::{Lambertian, Material},
+    };
+
+    const ASCII: &str = "ply
+format ascii 1.0
+comment A unit square with colored corners
+element vertex 4
+property float x
+property float y
+property float z
+property float nx
+property float ny
+property float nz
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+element edge 1
+property int vertex1
+property int vertex2
+end_header
+0 0 0 0 0 1 255 0 0
+1 0 0 0 0 1 0 255 0
+1 1 0 0 0 1 0 0 255
+0 1 0 0 0 1 255 255 255
+4 0 1 2 3
+0 2
+";
+
+    fn white() -> Arc<dyn Material> {
+        Arc::new(Lambertian::new(1.0, 1.0, 1.0))
+    }
+
+    /// Encode the ASCII test mesh as binary.
+    fn binary(big_endian: bool) -> Vec<u8> {
+        let format = if big_endian {
+            "binary_big_endian"
+        } else {
+            "binary_little_endian"
+        };
+        let header_end = ASCII.find("end_header\n").unwrap() + "end_header\n".len();
+        let mut data = ASCII[..header_end]
+            .replace("format ascii", &format!("format {format}"))
+            .into_bytes();
+
+        let lines: Vec<Vec<f64>> = ASCII[header_end..]
+            .lines()
+            .map(|line| line.split(' ').map(|v| v.parse().unwrap()).collect())
+            .collect();
+        let f32_bytes = |v: f64| {
+            if big_endian {
+                (v as f32).to_be_bytes()
+            } else {
+                (v as f32).to_le_bytes()
+            }
+        };
+        let i32_bytes = |v: f64| {
+            if big_endian {
+                (v as i32).to_be_bytes()
+            } else {
+                (v as i32).to_le_bytes()
+            }
+        };
+        for vertex in &lines[..4] {
+            for &v in &vertex[..6] {
+                data.extend(f32_bytes(v));
+            }
+            data.extend(vertex[6..].iter().map(|&v| v as u8));
+        }
+        data.push(4);
+        for &i in &lines[4][1..] {
+            data.extend(i32_bytes(i));
+        }
+        for &i in &lines[5] {
+            data.extend(i32_bytes(i));
+        }
+        data
+    }
+
+    #[test]
+    fn read_colored_square() {
+        for data in [ASCII.as_bytes().to_vec(), binary(false), binary(true)] {
+            let mesh = read(&data[..], white()).unwrap();
+            assert_eq!(4, mesh.positions().len());
+            assert_eq!(4, mesh.normals().len());
+            assert_eq!(2, mesh.faces().len());
+            assert_eq!(
+                &[
+                    Color::new(1.0, 0.0, 0.0),
+                    Color::new(0.0, 1.0, 0.0),
+                    Color::new(0.0, 0.0, 1.0),
+                    Color::new(1.0, 1.0, 1.0),
+                ],
+                mesh.colors()
+            );
+
+            let ray = Ray::new(Vec3::new(1, 0, 1), Vec3::new(0, 0, -1));
+            let hr = mesh.hit(&ray, 0.0, f64::INFINITY).unwrap();
+            assert_eq!(Some(Color::new(0.0, 1.0, 0.0)), hr.vertex_color);
+            assert_eq!(
+                Color::new(0.0, 0.5, 0.0),
+                hr.material.attenuate(&hr, Color::new(0.5, 0.5, 0.5))
+            );
+        }
+    }
+
+    #[test]
+    fn invalid_meshes() {
+        for (from, to) in [
+            ("ply\n", "plx\n"),
+            ("format ascii 1.0", "format ebcdic 1.0"),
+            ("property float x\n", ""),
+            ("property float nz", "property quad nz"),
+            ("4 0 1 2 3", "4 0 1 2 4"),
+            ("4 0 1 2 3", "2 0 1"),
+            ("0 2\n", "0 2 3\n"),
+            ("0 2\n", ""),
+            ("element face 1", "element face 99999999999999999"),
+        ] {
+            let text = ASCII.replacen(from, to, 1);
+            assert!(read(text.as_bytes(), white()).is_err(), "{to:?}");
+        }
+    }
+}
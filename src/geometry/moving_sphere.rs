@@ -0,0 +1,162 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    csg::{Interval, Solid},
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    sphere,
+    vec3::Vec3,
+};
+
+/// A sphere moving in a straight line, from `center0` at `time0` to `center1` at
+/// `time1`. It rests at `center0` before `time0`, and at `center1` after `time1`.
+#[derive(Debug)]
+pub struct MovingSphere {
+    pub center0: Vec3,
+    pub center1: Vec3,
+    pub time0: f64,
+    pub time1: f64,
+    pub radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl MovingSphere {
+    pub fn new<T: Into<f64>>(
+        center0: Vec3,
+        center1: Vec3,
+        time0: f64,
+        time1: f64,
+        radius: T,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center0,
+            center1,
+            time0,
+            time1,
+            radius: radius.into(),
+            material,
+        }
+    }
+
+    /// The center of the sphere at a given time.
+    pub fn center(&self, time: f64) -> Vec3 {
+        if self.time1 <= self.time0 {
+            return self.center1;
+        }
+        let fraction = ((time - self.time0) / (self.time1 - self.time0)).clamp(0.0, 1.0);
+        self.center0 + fraction * (self.center1 - self.center0)
+    }
+}
+
+impl Hittable for MovingSphere {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let center = self.center(ray.time());
+        sphere::hit(center, self.radius, &self.material, ray, t_min, t_max)
+    }
+
+    /// The box encloses the sphere at every time.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox0 = sphere::bounding_box(self.center0, self.radius);
+        let bbox1 = sphere::bounding_box(self.center1, self.radius);
+        Some(bbox0.union(&bbox1))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::MovingSphere {
+            centers: [
+                description::tuple(self.center0),
+                description::tuple(self.center1),
+            ],
+            times: (self.time0, self.time1),
+            radius: self.radius,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+impl Solid for MovingSphere {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        sphere::intervals(self.center(ray.time()), self.radius, &self.material, ray)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::MovingSphere;
+    use crate::material::Lambertian;
+
+    /// A sphere moving up from (0, 0, 1) at time 0 to (0, 1, 1) at time 1.
+    fn test_sphere() -> MovingSphere {
+        MovingSphere::new(
+            Vec3::new(0, 0, 1),
+            Vec3::new(0, 1, 1),
+            0.0,
+            1.0,
+            0.5,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn center_moves_over_time() {
+        let sphere = test_sphere();
+        assert_eq!(Vec3::new(0, 0, 1), sphere.center(0.0));
+        assert_eq!(Vec3::new(0, 0.25, 1), sphere.center(0.25));
+        assert_eq!(Vec3::new(0, 1, 1), sphere.center(1.0));
+
+        // At rest outside of its interval.
+        assert_eq!(Vec3::new(0, 0, 1), sphere.center(-1.0));
+        assert_eq!(Vec3::new(0, 1, 1), sphere.center(2.0));
+    }
+
+    #[test]
+    fn ray_at_moving_sphere_bullseye() {
+        let sphere = test_sphere();
+        let bullseye_ray =
+            |time| Ray::new(Vec3::new(0, 0.5, 0), Vec3::new(0, 0, 1)).with_time(time);
+
+        // The ray passes above the sphere at the start, and through its middle halfway.
+        assert!(sphere.hit(&bullseye_ray(0.0), -1.0, 1.0).is_none());
+        let hr = sphere
+            .hit(&bullseye_ray(0.5), -1.0, 1.0)
+            .expect("Ray should hit sphere");
+        assert_eq!(0.5, hr.t);
+        assert_eq!(Vec3::new(0, 0.5, 0.5), hr.point);
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let sphere = test_sphere();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0.5, 0), Vec3::new(0, 0, 1)).with_time(0.5);
+
+        assert!(sphere.hit(&bullseye_ray, -1.0, 0.4999).is_none());
+        assert!(sphere.hit(&bullseye_ray, -1.0, 0.5001).is_some());
+    }
+
+    #[test]
+    fn ray_scrapes_moving_sphere() {
+        let sphere = test_sphere();
+        let scrape_ray = |y: f64| Ray::new(Vec3::new(0, y, 0), Vec3::new(0, 0, 1)).with_time(1.0);
+
+        assert!(sphere.hit(&scrape_ray(1.4999), -10.0, 10.0).is_some());
+        assert!(sphere.hit(&scrape_ray(1.5001), -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn bounding_box_covers_path() {
+        let bbox = test_sphere().bounding_box().unwrap();
+        assert_eq!(Vec3::new(-0.5, -0.5, 0.5), bbox.min());
+        assert_eq!(Vec3::new(0.5, 1.5, 1.5), bbox.max());
+    }
+}
@@ -0,0 +1,155 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    frame::Frame,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// Values of `normal · direction` smaller than this are treated as rays parallel to
+/// the plane.
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+/// An infinite plane. Planes have no bounding box, so they are tested against every
+/// ray rather than placed in the scene's hierarchy.
+#[derive(Debug)]
+pub struct Plane {
+    /// A point on the plane, which is the origin of its texture coordinates.
+    pub point: Vec3,
+
+    /// The unit normal of the plane's front face.
+    pub normal: Vec3,
+
+    pub material: Arc<dyn Material>,
+}
+
+impl Plane {
+    /// Create a plane through `point`, facing the direction of `normal`.
+    pub fn new(point: Vec3, normal: Vec3, material: Arc<dyn Material>) -> Self {
+        Self {
+            point,
+            normal: normal.normalized(),
+            material,
+        }
+    }
+
+    /// Two unit vectors in the plane, perpendicular to each other, along which
+    /// texture coordinates are measured.
+    pub fn tangents(&self) -> (Vec3, Vec3) {
+        let [u, v, _] = Frame::from_axis(self.point, self.normal).axes;
+        (u, v)
+    }
+}
+
+/// Find where a ray hits the plane through `point` with normal `normal`, returning
+/// the ray parameter of the hit.
+pub(crate) fn intersect(
+    point: Vec3,
+    normal: Vec3,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<f64> {
+    let denominator = normal.dot(ray.direction());
+    if denominator.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+    let t = normal.dot(point - ray.origin()) / denominator;
+    (t > t_min && t < t_max).then_some(t)
+}
+
+impl Hittable for Plane {
+    /// Texture coordinates are distances from the plane's point along its
+    /// [`Plane::tangents`], so they repeat every unit.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let t = intersect(self.point, self.normal, ray, t_min, t_max)?;
+        let point = ray.at(t);
+        let mut hr = HitRecord::new(point, self.normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, self.normal);
+
+        let (u, v) = self.tangents();
+        let offset = point - self.point;
+        hr.uv = Some((offset.dot(u), offset.dot(v)));
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Plane {
+            point: description::tuple(self.point),
+            normal: description::tuple(self.normal),
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Plane;
+    use crate::material::Lambertian;
+
+    fn floor() -> Plane {
+        Plane::new(
+            Vec3::new(0, -1, 0),
+            Vec3::new(0, 2, 0),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_hits_plane() {
+        let plane = floor();
+        let ray = Ray::new(Vec3::new(100, 1, -50), Vec3::new(0, -1, 0));
+
+        let hr = plane.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(100, -1, -50), hr.point);
+        assert_eq!(Vec3::new(0, 1, 0), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+
+        let ray = Ray::new(Vec3::new(0, -3, 0), Vec3::new(1, 1, 0));
+        let hr = plane.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Some(false), hr.front_face);
+        assert_eq!(Vec3::new(0, -1, 0), hr.normal);
+    }
+
+    #[test]
+    fn ray_misses_plane() {
+        let plane = floor();
+        for direction in [Vec3::new(0, 1, 0), Vec3::new(1, 0, 1)] {
+            let ray = Ray::new(Vec3::new(0, 1, 0), direction);
+            assert!(plane.hit(&ray, 0.0, f64::INFINITY).is_none());
+        }
+        let ray = Ray::new(Vec3::new(0, 1, 0), Vec3::new(0, -1, 0));
+        assert!(plane.hit(&ray, 0.0, 1.5).is_none());
+        assert!(plane.bounding_box().is_none());
+    }
+
+    #[test]
+    fn plane_uvs_measure_distance() {
+        let plane = floor();
+        let (u, v) = plane.tangents();
+        assert!(u.dot(v).abs() < 1e-12 && u.dot(plane.normal).abs() < 1e-12);
+
+        let hit_uv = |point: Vec3| {
+            let ray = Ray::new(point + Vec3::new(0, 1, 0), Vec3::new(0, -1, 0));
+            plane.hit(&ray, 0.0, f64::INFINITY).unwrap().uv.unwrap()
+        };
+        assert_eq!((0.0, 0.0), hit_uv(plane.point));
+        let (u1, v1) = hit_uv(plane.point + 2.0 * u + 3.0 * v);
+        assert!((u1 - 2.0).abs() < 1e-12 && (v1 - 3.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,174 @@
+//! Real roots of polynomials, used to find where rays hit curved surfaces.
+use std::ops::Deref;
+
+/// Up to four real roots of a polynomial, in increasing order.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Roots {
+    values: [f64; 4],
+    len: usize,
+}
+
+impl Roots {
+    fn new(roots: &[f64]) -> Self {
+        let mut values = [0.0; 4];
+        values[..roots.len()].copy_from_slice(roots);
+        values[..roots.len()].sort_by(f64::total_cmp);
+        Self {
+            values,
+            len: roots.len(),
+        }
+    }
+}
+
+impl Deref for Roots {
+    type Target = [f64];
+
+    fn deref(&self) -> &[f64] {
+        &self.values[..self.len]
+    }
+}
+
+/// The real roots of `a x² + b x + c`. A repeated root is given twice. When `a` is
+/// zero, this is the root of the linear equation.
+pub fn quadratic_roots(a: f64, b: f64, c: f64) -> Roots {
+    if a == 0.0 {
+        return match b {
+            0.0 => Roots::new(&[]),
+            _ => Roots::new(&[-c / b]),
+        };
+    }
+
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return Roots::new(&[]);
+    }
+    // Avoid subtracting nearly equal numbers, which loses precision.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    if q == 0.0 {
+        return Roots::new(&[0.0, 0.0]);
+    }
+    Roots::new(&[q / a, c / q])
+}
+
+/// The largest real root of the monic cubic `x³ + a x² + b x + c`.
+fn largest_cubic_root(a: f64, b: f64, c: f64) -> f64 {
+    // Substitute `x = t - a / 3` to get `t³ + p t + q`.
+    let p = b - a * a / 3.0;
+    let q = 2.0 * a * a * a / 27.0 - a * b / 3.0 + c;
+    let shift = -a / 3.0;
+
+    let discriminant = q * q / 4.0 + p * p * p / 27.0;
+    if discriminant >= 0.0 {
+        // One real root, by Cardano's formula.
+        let sqrt_d = discriminant.sqrt();
+        return (-q / 2.0 + sqrt_d).cbrt() + (-q / 2.0 - sqrt_d).cbrt() + shift;
+    }
+
+    // Three real roots, by the trigonometric method. `p` is negative here.
+    let m = 2.0 * (-p / 3.0).sqrt();
+    let theta = ((3.0 * q / (p * m)).clamp(-1.0, 1.0)).acos() / 3.0;
+    m * theta.cos() + shift
+}
+
+/// The real roots of `a x⁴ + b x³ + c x² + d x + e`, found with Ferrari's method
+/// and refined with Newton's method.
+pub fn quartic_roots(a: f64, b: f64, c: f64, d: f64, e: f64) -> Roots {
+    if a == 0.0 {
+        // Lower degrees aren't needed by any surface.
+        return Roots::new(&[]);
+    }
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // Substitute `x = y - b / 4` to get `y⁴ + p y² + q y + r`.
+    let shift = -b / 4.0;
+    let b2 = b * b;
+    let p = c - 3.0 * b2 / 8.0;
+    let q = d - b * c / 2.0 + b2 * b / 8.0;
+    let r = e - b * d / 4.0 + b2 * c / 16.0 - 3.0 * b2 * b2 / 256.0;
+
+    let mut roots = [0.0; 4];
+    let mut len = 0;
+    let mut push_quadratic = |a: f64, b: f64, c: f64, square: bool| {
+        for &root in quadratic_roots(a, b, c).iter() {
+            if square {
+                // Roots of the quadratic are squares of the quartic's roots.
+                if root >= 0.0 {
+                    roots[len] = root.sqrt();
+                    roots[len + 1] = -root.sqrt();
+                    len += 2;
+                }
+            } else {
+                roots[len] = root;
+                len += 1;
+            }
+        }
+    };
+
+    if q.abs() < 1e-12 {
+        // A quadratic in `y²`.
+        push_quadratic(1.0, p, r, true);
+    } else {
+        // Complete the square with the largest root of the resolvent cubic, which is
+        // positive, to factor the quartic into two quadratics.
+        let m = largest_cubic_root(p, p * p / 4.0 - r, -q * q / 8.0).max(f64::MIN_POSITIVE);
+        let s = (2.0 * m).sqrt();
+        push_quadratic(1.0, -s, p / 2.0 + m + q / (2.0 * s), false);
+        push_quadratic(1.0, s, p / 2.0 + m - q / (2.0 * s), false);
+    }
+
+    let polish = |mut x: f64| {
+        for _ in 0..2 {
+            let f = (((x + b) * x + c) * x + d) * x + e;
+            let df = ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+            if df == 0.0 {
+                break;
+            }
+            x -= f / df;
+        }
+        x
+    };
+    for root in &mut roots[..len] {
+        *root = polish(*root + shift);
+    }
+    Roots::new(&roots[..len])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{quadratic_roots, quartic_roots};
+
+    fn assert_roots(expected: &[f64], roots: &[f64]) {
+        assert_eq!(expected.len(), roots.len(), "{roots:?}");
+        for (e, r) in expected.iter().zip(roots) {
+            assert!((e - r).abs() < 1e-9, "expected {expected:?}, got {roots:?}");
+        }
+    }
+
+    #[test]
+    fn quadratics() {
+        assert_roots(&[-3.0, 2.0], &quadratic_roots(1.0, 1.0, -6.0));
+        assert_roots(&[1.0, 1.0], &quadratic_roots(2.0, -4.0, 2.0));
+        assert_roots(&[], &quadratic_roots(1.0, 0.0, 1.0));
+        assert_roots(&[-2.0], &quadratic_roots(0.0, 3.0, 6.0));
+        // Roots of very different sizes keep their precision.
+        assert_roots(&[1e-8, 1e8], &quadratic_roots(1.0, -1e8 - 1e-8, 1.0));
+    }
+
+    #[test]
+    fn quartics() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        assert_roots(
+            &[1.0, 2.0, 3.0, 4.0],
+            &quartic_roots(1.0, -10.0, 35.0, -50.0, 24.0),
+        );
+        // 2 (x² - 1)(x² - 4), which is quadratic in x².
+        assert_roots(
+            &[-2.0, -1.0, 1.0, 2.0],
+            &quartic_roots(2.0, 0.0, -10.0, 0.0, 8.0),
+        );
+        // (x + 1)(x - 5)(x² + 1)
+        assert_roots(&[-1.0, 5.0], &quartic_roots(1.0, -4.0, -4.0, -4.0, -5.0));
+        // x⁴ + 1
+        assert_roots(&[], &quartic_roots(1.0, 0.0, 0.0, 0.0, 1.0));
+    }
+}
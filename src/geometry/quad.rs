@@ -0,0 +1,139 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    plane,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A parallelogram, with corners at `origin`, `origin + u`, `origin + v` and
+/// `origin + u + v`. Rectangles, such as walls and area lights, are quads with
+/// perpendicular edges.
+#[derive(Debug)]
+pub struct Quad {
+    pub origin: Vec3,
+    pub u: Vec3,
+    pub v: Vec3,
+    pub material: Arc<dyn Material>,
+}
+
+impl Quad {
+    /// Create a quad from a corner and its two edges from that corner. The front
+    /// face is the side from which `u` turns counter-clockwise to `v`.
+    pub fn new(origin: Vec3, u: Vec3, v: Vec3, material: Arc<dyn Material>) -> Self {
+        Self {
+            origin,
+            u,
+            v,
+            material,
+        }
+    }
+
+    /// The unit normal of the quad's front face.
+    pub fn normal(&self) -> Vec3 {
+        self.u.cross(self.v).normalized()
+    }
+}
+
+impl Hittable for Quad {
+    /// Texture coordinates are the fractions of the edges `u` and `v` to the hit, so
+    /// they run from 0 to 1 across the quad.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let n = self.u.cross(self.v);
+        let normal = n.normalized();
+        let t = plane::intersect(self.origin, normal, ray, t_min, t_max)?;
+        let point = ray.at(t);
+
+        // Solve `point = origin + alpha * u + beta * v` in the plane of the quad.
+        let w = n / n.length_squared();
+        let offset = point - self.origin;
+        let alpha = w.dot(offset.cross(self.v));
+        let beta = w.dot(self.u.cross(offset));
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
+            return None;
+        }
+
+        let mut hr = HitRecord::new(point, normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, normal);
+        hr.uv = Some((alpha, beta));
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = Aabb::new(self.origin, self.origin + self.u + self.v)
+            .union(&Aabb::new(self.origin + self.u, self.origin + self.v));
+        Some(bbox.padded())
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Quad {
+            origin: description::tuple(self.origin),
+            u: description::tuple(self.u),
+            v: description::tuple(self.v),
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Quad;
+    use crate::material::Lambertian;
+
+    /// A parallelogram in the plane `z = -1`, facing +z.
+    fn test_quad() -> Quad {
+        Quad::new(
+            Vec3::new(0, 0, -1),
+            Vec3::new(2, 0, 0),
+            Vec3::new(1, 1, 0),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_hits_quad() {
+        let quad = test_quad();
+        let ray = Ray::new(Vec3::new(2, 0.5, 1), Vec3::new(0, 0, -1));
+
+        let hr = quad.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(2, 0.5, -1), hr.point);
+        assert_eq!(Vec3::new(0, 0, 1), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+        assert_eq!(Some((0.75, 0.5)), hr.uv);
+
+        let ray = Ray::new(Vec3::new(2, 0.5, -3), Vec3::new(0, 0, 1));
+        let hr = quad.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Some(false), hr.front_face);
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+    }
+
+    #[test]
+    fn ray_misses_quad() {
+        let quad = test_quad();
+        for (x, y) in [(0.25, 0.5), (3.25, 0.5), (1.0, -0.1), (2.0, 1.1)] {
+            let ray = Ray::new(Vec3::new(x, y, 1), Vec3::new(0, 0, -1));
+            assert!(quad.hit(&ray, 0.0, f64::INFINITY).is_none(), "{x}, {y}");
+        }
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 0, 0));
+        assert!(quad.hit(&ray, 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn flat_quad_has_bounding_box() {
+        let bbox = test_quad().bounding_box().unwrap();
+        assert!(bbox.min().x() <= 0.0 && bbox.max().x() >= 3.0);
+        assert!(bbox.min().y() <= 0.0 && bbox.max().y() >= 1.0);
+        assert!(bbox.min().z() < -1.0 && bbox.max().z() > -1.0);
+    }
+}
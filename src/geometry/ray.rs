@@ -7,11 +7,23 @@ use super::vec3::Vec3;
 pub struct Ray {
     origin: Vec3,
     direction: Vec3,
+    time: f64,
 }
 
 impl Ray {
+    /// Create a ray at time 0.
     pub fn new(origin: Vec3, direction: Vec3) -> Self {
-        Ray { origin, direction }
+        Ray {
+            origin,
+            direction,
+            time: 0.0,
+        }
+    }
+
+    /// Set the time that the ray is cast at, which moving hittables use to find
+    /// where they are.
+    pub fn with_time(self, time: f64) -> Self {
+        Ray { time, ..self }
     }
 
     /// The point in space that the `Ray` starts from.
@@ -24,6 +36,12 @@ impl Ray {
         self.direction
     }
 
+    /// The time that the `Ray` is cast at. Rays scattered from a hit keep the time
+    /// of the ray that hit.
+    pub fn time(&self) -> f64 {
+        self.time
+    }
+
     /// Compute the position along the `Ray`, starting from its origin,
     /// at time `t`. Positive values of `t` yield positions in front
     /// of the origin and negative values yield positions behind the
@@ -49,4 +67,14 @@ mod tests {
         assert_eq!(Vec3::new(2.0, 3.0, 4.0), ray.at(1.0));
         assert_eq!(Vec3::new(4.0, 7.0, 10.0), ray.at(3.0));
     }
+
+    #[test]
+    fn time() {
+        let ray = Ray::new(Vec3::new(1.0, 1.0, 1.0), Vec3::new(1.0, 2.0, 3.0));
+        assert_eq!(0.0, ray.time());
+
+        let ray = ray.with_time(0.5);
+        assert_eq!(0.5, ray.time());
+        assert_eq!(Vec3::new(2.0, 3.0, 4.0), ray.at(1.0));
+    }
 }
@@ -0,0 +1,273 @@
+//! Surfaces described by signed distance functions, rendered by sphere tracing.
+//!
+//! A signed distance function gives the distance from a point to the nearest point
+//! on a surface, which is positive outside the surface and negative inside it. Rays
+//! step towards the surface by that distance, which can't pass through it, until
+//! they are close enough to count as hitting it. Distance functions can describe
+//! surfaces with no simple intersection formula, such as fractals, and blends of
+//! shapes. Built-in shapes and operators are in [`shapes`].
+pub mod shapes;
+
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription, ShapeDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A signed distance function. Closures of points are distance functions.
+pub trait DistanceFunction: Send + Sync {
+    /// The signed distance from `point` to the surface. It may be less than the
+    /// true distance, which makes rays take more steps, but must never be more,
+    /// or rays can step through the surface.
+    fn distance(&self, point: Vec3) -> f64;
+
+    /// Returns an [`Aabb`] enclosing the surface, or `None` if it is unbounded or
+    /// unknown.
+    fn bounding_box(&self) -> Option<Aabb> {
+        None
+    }
+
+    /// Describe the shape for saving a scene. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Err(description::invalid_input(
+            "only SDFs made of built-in shapes can be saved",
+        ))
+    }
+}
+
+impl<F> DistanceFunction for F
+where
+    F: Fn(Vec3) -> f64 + Send + Sync,
+{
+    fn distance(&self, point: Vec3) -> f64 {
+        self(point)
+    }
+}
+
+/// The default [`Sdf::epsilon`].
+pub const DEFAULT_EPSILON: f64 = 1e-4;
+
+/// The default [`Sdf::max_steps`].
+pub const DEFAULT_MAX_STEPS: u32 = 256;
+
+/// A surface where a signed distance function is zero.
+pub struct Sdf {
+    pub distance: Arc<dyn DistanceFunction>,
+    pub material: Arc<dyn Material>,
+
+    /// Rays hit the surface when they come within this distance of it. Also the
+    /// step used to find normals.
+    pub epsilon: f64,
+
+    /// The most steps a ray takes towards the surface before it is treated as
+    /// missing. Rays that pass close to the surface without hitting it take the
+    /// most steps.
+    pub max_steps: u32,
+
+    /// The region that rays are stepped through, from where they enter it. `None`
+    /// if rays start stepping from their origin.
+    pub bounds: Option<Aabb>,
+}
+
+impl Sdf {
+    /// Create a surface bounded by the distance function's bounding box, with the
+    /// default epsilon and step limit.
+    pub fn new(distance: Arc<dyn DistanceFunction>, material: Arc<dyn Material>) -> Self {
+        Self {
+            bounds: distance.bounding_box(),
+            distance,
+            material,
+            epsilon: DEFAULT_EPSILON,
+            max_steps: DEFAULT_MAX_STEPS,
+        }
+    }
+
+    pub fn with_epsilon(self, epsilon: f64) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    pub fn with_max_steps(self, max_steps: u32) -> Self {
+        Self { max_steps, ..self }
+    }
+
+    /// Only step rays through `bounds`. Needed for distance functions without
+    /// bounding boxes of their own, such as closures, so that they can be put in a
+    /// [`super::bvh::HittableBvh`] and rays that miss them stop early.
+    pub fn with_bounds(self, bounds: Aabb) -> Self {
+        Self {
+            bounds: Some(bounds),
+            ..self
+        }
+    }
+
+    /// The outward unit normal of the surface near `point`, from the gradient of the
+    /// distance function found by central differences.
+    pub fn normal(&self, point: Vec3) -> Vec3 {
+        let difference = |offset: Vec3| {
+            self.distance.distance(point + offset) - self.distance.distance(point - offset)
+        };
+        let h = self.epsilon;
+        Vec3::new(
+            difference(Vec3::new(h, 0, 0)),
+            difference(Vec3::new(0, h, 0)),
+            difference(Vec3::new(0, 0, h)),
+        )
+        .normalized()
+    }
+}
+
+impl Debug for Sdf {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Sdf")
+            .field("material", &self.material)
+            .field("epsilon", &self.epsilon)
+            .field("max_steps", &self.max_steps)
+            .field("bounds", &self.bounds)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Hittable for Sdf {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (mut t, t_max) = match &self.bounds {
+            Some(bounds) => bounds.interval(ray, t_min, t_max)?,
+            None => (t_min, t_max),
+        };
+
+        // Distances are along the surface, so they are divided by the speed of the
+        // ray to step in `t`. Rays from inside the surface step by the distance to it
+        // too, so they can hit it from behind.
+        let speed = ray.direction().length();
+        for _ in 0..self.max_steps {
+            let point = ray.at(t);
+            let distance = self.distance.distance(point).abs();
+            if distance < self.epsilon && t > t_min {
+                let outward_normal = self.normal(point);
+                let mut hr = HitRecord::new(point, outward_normal, t, Arc::clone(&self.material));
+                hr.set_face_normal(ray, outward_normal);
+                return Some(hr);
+            }
+
+            // A ray starting on the surface, such as one scattered from it, steps
+            // off it before it can hit it again.
+            t += distance.max(self.epsilon) / speed;
+            if t > t_max {
+                return None;
+            }
+        }
+        None
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        self.bounds
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Sdf {
+            shape: self.distance.describe()?,
+            epsilon: self.epsilon,
+            max_steps: self.max_steps,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{aabb::Aabb, hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::{shapes, Sdf};
+    use crate::material::Lambertian;
+
+    fn test_sdf() -> Sdf {
+        Sdf::new(
+            Arc::new(shapes::Sphere {
+                center: Vec3::new(0, 0, 1),
+                radius: 0.5,
+            }),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_misses_sdf() {
+        let sdf = test_sdf();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 1));
+
+        assert!(sdf.hit(&miss_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_sdf_bullseye() {
+        let sdf = test_sdf();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 2));
+
+        let hr = sdf
+            .hit(&bullseye_ray, 0.0, 1.0)
+            .expect("Ray should hit surface");
+        assert!((hr.t - 0.25).abs() < 1e-4);
+        assert!((hr.point - Vec3::new(0, 0, 0.5)).length() < 1e-4);
+        assert!((hr.normal - Vec3::new(0, 0, -1)).length() < 1e-6);
+        assert_eq!(Some(true), hr.front_face);
+
+        // From inside, the far side is hit from behind.
+        let inside_ray = Ray::new(Vec3::new(0, 0, 1), Vec3::new(0, 0, 1));
+        let hr = sdf.hit(&inside_ray, 0.0, 10.0).unwrap();
+        assert!((hr.t - 0.5).abs() < 1e-4);
+        assert_eq!(Some(false), hr.front_face);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let sdf = test_sdf();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+
+        assert!(sdf.hit(&bullseye_ray, 0.0, 0.499).is_none());
+        assert!(sdf.hit(&bullseye_ray, 0.0, 0.501).is_some());
+        // Starting beyond the near side.
+        let hr = sdf.hit(&bullseye_ray, 0.6, 10.0).unwrap();
+        assert!((hr.t - 1.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn ray_scrapes_sdf() {
+        let sdf = test_sdf();
+        let scrape_ray = |x: f64| Ray::new(Vec3::new(x, 0, -2), Vec3::new(0, 0, 1));
+
+        assert!(sdf.hit(&scrape_ray(0.499), 0.0, 10.0).is_some());
+        assert!(sdf.hit(&scrape_ray(0.501), 0.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn closures_are_distance_functions() {
+        // A plane at z = 1, which needs bounds to be put in a BVH.
+        let sdf = Sdf::new(
+            Arc::new(|p: Vec3| p.z() - 1.0),
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+        .with_epsilon(1e-6)
+        .with_max_steps(32);
+        assert!(sdf.bounding_box().is_none());
+
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 1, 1));
+        let hr = sdf.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hr.t - 1.0).abs() < 1e-6);
+        assert!((hr.normal - Vec3::new(0, 0, -1)).length() < 1e-6);
+
+        let bounds = Aabb::new(Vec3::new(-1, -1, 0), Vec3::new(1, 1, 2));
+        let sdf = sdf.with_bounds(bounds);
+        assert_eq!(Some(bounds), sdf.bounding_box());
+        let miss_ray = Ray::new(Vec3::new(0, 5, 0), Vec3::new(0, 0, 1));
+        assert!(sdf.hit(&miss_ray, 0.0, f64::INFINITY).is_none());
+    }
+}
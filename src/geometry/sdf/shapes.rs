@@ -0,0 +1,330 @@
+//! Built-in [`DistanceFunction`]s, and operators that combine them.
+use std::{io, sync::Arc};
+
+use crate::scene::description::{self, ShapeDescription};
+
+use super::{
+    super::{aabb::Aabb, vec3::Vec3},
+    DistanceFunction,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Sphere {
+    pub center: Vec3,
+    pub radius: f64,
+}
+
+impl DistanceFunction for Sphere {
+    fn distance(&self, point: Vec3) -> f64 {
+        (point - self.center).length() - self.radius
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let r = Vec3::new(self.radius, self.radius, self.radius);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::Sphere {
+            center: description::tuple(self.center),
+            radius: self.radius,
+        })
+    }
+}
+
+/// An axis-aligned box.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Cuboid {
+    pub center: Vec3,
+    pub half_size: Vec3,
+}
+
+impl DistanceFunction for Cuboid {
+    fn distance(&self, point: Vec3) -> f64 {
+        // The distance past each face, which is negative inside the box.
+        let p = point - self.center;
+        let q = Vec3::new(p.x().abs(), p.y().abs(), p.z().abs()) - self.half_size;
+        let outside = Vec3::new(q.x().max(0.0), q.y().max(0.0), q.z().max(0.0)).length();
+        let inside = q.x().max(q.y()).max(q.z()).min(0.0);
+        outside + inside
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(Aabb::new(
+            self.center - self.half_size,
+            self.center + self.half_size,
+        ))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::Cuboid {
+            center: description::tuple(self.center),
+            size: description::tuple(2.0 * self.half_size),
+        })
+    }
+}
+
+/// A torus swept about `axis`, like a [`crate::geometry::torus::Torus`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Torus {
+    pub center: Vec3,
+
+    /// The unit axis that the torus is swept about.
+    pub axis: Vec3,
+
+    pub major_radius: f64,
+    pub minor_radius: f64,
+}
+
+impl DistanceFunction for Torus {
+    fn distance(&self, point: Vec3) -> f64 {
+        let p = point - self.center;
+        let height = p.dot(self.axis);
+        let rho = (p - height * self.axis).length();
+        ((rho - self.major_radius).powi(2) + height * height).sqrt() - self.minor_radius
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // The extent of the swept circle along each world axis, plus the tube.
+        let extent = |i: usize| {
+            self.major_radius * (1.0 - self.axis[i] * self.axis[i]).max(0.0).sqrt()
+                + self.minor_radius
+        };
+        let extent = Vec3::new(extent(0), extent(1), extent(2));
+        Some(Aabb::new(self.center - extent, self.center + extent))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::Torus {
+            center: description::tuple(self.center),
+            axis: description::tuple(self.axis),
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+        })
+    }
+}
+
+/// The Mandelbulb fractal: a three-dimensional Mandelbrot set, raised to `power`
+/// in spherical coordinates. Its distance is estimated from the growth of the
+/// iterated point, and is more detailed with more `iterations`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Mandelbulb {
+    pub center: Vec3,
+
+    /// The size of the fractal. It fits in a sphere of twice this radius.
+    pub scale: f64,
+
+    pub power: f64,
+    pub iterations: u32,
+}
+
+impl DistanceFunction for Mandelbulb {
+    fn distance(&self, point: Vec3) -> f64 {
+        let c = (point - self.center) / self.scale;
+        if c.length() > 4.0 {
+            // Far away, the estimate is more than the distance, so the distance to a
+            // sphere around the fractal is used instead. Nearer, this would be 0 on
+            // the sphere, which rays would hit.
+            return (c.length() - 2.0) * self.scale;
+        }
+
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.length();
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+            // Raise `z` to the power, and track the derivative of its length. The
+            // direction of `z` is arbitrary at the center.
+            let theta = (z.z() / r.max(f64::MIN_POSITIVE)).acos() * self.power;
+            let phi = z.y().atan2(z.x()) * self.power;
+            dr = r.powf(self.power - 1.0) * self.power * dr + 1.0;
+            let (sin_theta, cos_theta) = theta.sin_cos();
+            let (sin_phi, cos_phi) = phi.sin_cos();
+            z = r.powf(self.power) * Vec3::new(sin_theta * cos_phi, sin_theta * sin_phi, cos_theta)
+                + c;
+            r = z.length();
+        }
+        let r = r.max(f64::MIN_POSITIVE);
+        0.5 * r.ln() * r / dr * self.scale
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        // Points further than 2 from the center escape, and aren't in the set.
+        let r = 2.0 * self.scale;
+        let r = Vec3::new(r, r, r);
+        Some(Aabb::new(self.center - r, self.center + r))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::Mandelbulb {
+            center: description::tuple(self.center),
+            scale: self.scale,
+            power: self.power,
+            iterations: self.iterations,
+        })
+    }
+}
+
+/// The union of two surfaces, with a smooth fillet where they meet. The fillet is
+/// wider with more `smoothness`, and is a sharp union at 0.
+pub struct SmoothUnion {
+    pub a: Arc<dyn DistanceFunction>,
+    pub b: Arc<dyn DistanceFunction>,
+    pub smoothness: f64,
+}
+
+impl DistanceFunction for SmoothUnion {
+    fn distance(&self, point: Vec3) -> f64 {
+        let (a, b) = (self.a.distance(point), self.b.distance(point));
+        let k = self.smoothness;
+        if k <= 0.0 {
+            return a.min(b);
+        }
+        // The polynomial smooth minimum.
+        let h = (0.5 + 0.5 * (b - a) / k).clamp(0.0, 1.0);
+        b + (a - b) * h - k * h * (1.0 - h)
+    }
+
+    /// The fillet reaches at most a quarter of the smoothness outside of either
+    /// surface.
+    fn bounding_box(&self) -> Option<Aabb> {
+        let bbox = self.a.bounding_box()?.union(&self.b.bounding_box()?);
+        let padding = 0.25 * self.smoothness.max(0.0);
+        let padding = Vec3::new(padding, padding, padding);
+        Some(Aabb::new(bbox.min() - padding, bbox.max() + padding))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::SmoothUnion {
+            a: Box::new(self.a.describe()?),
+            b: Box::new(self.b.describe()?),
+            smoothness: self.smoothness,
+        })
+    }
+}
+
+/// A surface part way between two others, morphing from `a` at a `weight` of 0 to
+/// `b` at 1.
+pub struct Blend {
+    pub a: Arc<dyn DistanceFunction>,
+    pub b: Arc<dyn DistanceFunction>,
+    pub weight: f64,
+}
+
+impl DistanceFunction for Blend {
+    fn distance(&self, point: Vec3) -> f64 {
+        (1.0 - self.weight) * self.a.distance(point) + self.weight * self.b.distance(point)
+    }
+
+    /// Outside of both surfaces' boxes, both distances are positive, so the blend
+    /// is too.
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(self.a.bounding_box()?.union(&self.b.bounding_box()?))
+    }
+
+    fn describe(&self) -> io::Result<ShapeDescription> {
+        Ok(ShapeDescription::Blend {
+            a: Box::new(self.a.describe()?),
+            b: Box::new(self.b.describe()?),
+            weight: self.weight,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{super::vec3::Vec3, DistanceFunction};
+    use super::{Blend, Cuboid, Mandelbulb, SmoothUnion, Sphere, Torus};
+
+    fn assert_close(expected: f64, actual: f64) {
+        assert!((expected - actual).abs() < 1e-12, "{actual}");
+    }
+
+    #[test]
+    fn shape_distances() {
+        let sphere = Sphere {
+            center: Vec3::new(1, 0, 0),
+            radius: 0.5,
+        };
+        assert_close(1.5, sphere.distance(Vec3::new(-1, 0, 0)));
+        assert_close(-0.5, sphere.distance(Vec3::new(1, 0, 0)));
+
+        let cuboid = Cuboid {
+            center: Vec3::new(0, 0, 0),
+            half_size: Vec3::new(1, 2, 3),
+        };
+        assert_close(1.0, cuboid.distance(Vec3::new(0, 3, 0)));
+        assert_close(5.0, cuboid.distance(Vec3::new(4, 6, 0)));
+        assert_close(-0.5, cuboid.distance(Vec3::new(0.5, 0, 0)));
+
+        let torus = Torus {
+            center: Vec3::new(0, 0, 0),
+            axis: Vec3::new(0, 1, 0),
+            major_radius: 1.0,
+            minor_radius: 0.25,
+        };
+        assert_close(-0.25, torus.distance(Vec3::new(0, 0, 1)));
+        assert_close(0.75, torus.distance(Vec3::new(0, 0, 0)));
+        assert_close(0.75, torus.distance(Vec3::new(-1, 1, 0)));
+        let bbox = torus.bounding_box().unwrap();
+        assert_eq!(Vec3::new(1.25, 0.25, 1.25), bbox.max());
+    }
+
+    #[test]
+    fn mandelbulb_distance() {
+        let bulb = Mandelbulb {
+            center: Vec3::new(0, 0, 0),
+            scale: 1.0,
+            power: 8.0,
+            iterations: 10,
+        };
+        // The center is inside, and far away points are a little closer than their
+        // distance from the fractal, which reaches out to around 1.1.
+        assert!(bulb.distance(Vec3::new(0, 0, 0)) <= 0.0);
+        assert_close(8.0, bulb.distance(Vec3::new(0, 0, 10)));
+        let near = bulb.distance(Vec3::new(0, 0, 1.5));
+        assert!(near > 0.0 && near < 0.5, "{near}");
+    }
+
+    #[test]
+    fn operators() {
+        let a = Arc::new(Sphere {
+            center: Vec3::new(-1, 0, 0),
+            radius: 1.0,
+        });
+        let b = Arc::new(Sphere {
+            center: Vec3::new(1, 0, 0),
+            radius: 1.0,
+        });
+        let point = Vec3::new(0, 0.5, 0);
+
+        let sharp = SmoothUnion {
+            a: a.clone(),
+            b: b.clone(),
+            smoothness: 0.0,
+        };
+        assert_close(a.distance(point), sharp.distance(point));
+
+        // Where the spheres meet, the fillet fills in the gap between them.
+        let smooth = SmoothUnion {
+            a: a.clone(),
+            b: b.clone(),
+            smoothness: 0.5,
+        };
+        assert_close(a.distance(point) - 0.125, smooth.distance(point));
+        let far = Vec3::new(-3, 0, 0);
+        assert_close(a.distance(far), smooth.distance(far));
+
+        let blend = Blend {
+            a: a.clone(),
+            b,
+            weight: 0.25,
+        };
+        assert_close(-0.5, blend.distance(Vec3::new(-1, 0, 0)));
+        assert_eq!(Vec3::new(-2, -1, -1), blend.bounding_box().unwrap().min());
+    }
+}
@@ -1,9 +1,16 @@
-use std::{fmt::Debug, rc::Rc};
+use std::{f64::consts::PI, fmt::Debug, io, sync::Arc};
 
-use crate::material::Material;
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
 
 use super::{
+    aabb::Aabb,
+    csg::{Interval, Solid},
+    frame,
     hittable::{HitRecord, Hittable},
+    polynomial,
     ray::Ray,
     vec3::Vec3,
 };
@@ -12,11 +19,11 @@ use super::{
 pub struct Sphere {
     pub center: Vec3,
     pub radius: f64,
-    pub material: Rc<dyn Material>,
+    pub material: Arc<dyn Material>,
 }
 
 impl Sphere {
-    pub fn new<T: Into<f64>>(center: Vec3, radius: T, material: Rc<dyn Material>) -> Self {
+    pub fn new<T: Into<f64>>(center: Vec3, radius: T, material: Arc<dyn Material>) -> Self {
         Self {
             center,
             radius: radius.into(),
@@ -27,38 +34,125 @@ impl Sphere {
 
 impl Hittable for Sphere {
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
-        let co = ray.origin() - self.center;
-        let a = ray.direction().length_squared();
-        let half_b = ray.direction().dot(co);
-        let c = co.length_squared() - self.radius * self.radius;
-        let discrim = half_b * half_b - a * c;
-
-        // solve the quadratic equation
-        let sqrt_d = (discrim >= 0.0).then_some(discrim.sqrt())?;
-        let root = Some((-half_b - sqrt_d) / a)
-            .filter(|&t| t > t_min && t < t_max)
-            .or_else(|| Some((-half_b + sqrt_d) / a))
-            .filter(|&t| t > t_min && t < t_max)?;
-
-        let t = root;
-        let point = ray.at(t);
-        let outward_normal = (point - self.center) / self.radius;
+        hit(self.center, self.radius, &self.material, ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounding_box(self.center, self.radius))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Sphere {
+            center: description::tuple(self.center),
+            radius: self.radius,
+            material: name_material(&self.material)?,
+        })
+    }
+}
 
-        // FIXME: Do we have to calculate `set_face_normal` as a separate step?
-        let mut hr = HitRecord::new(point, outward_normal, t, Rc::clone(&self.material));
-        hr.set_face_normal(ray, outward_normal);
-        Some(hr)
+impl Solid for Sphere {
+    fn intervals(&self, ray: &Ray) -> Vec<Interval> {
+        intervals(self.center, self.radius, &self.material, ray)
     }
 }
 
+/// Find where a ray hits a sphere. Shared by hittables that are spheres at any
+/// moment, such as [`super::moving_sphere::MovingSphere`].
+pub(crate) fn hit(
+    center: Vec3,
+    radius: f64,
+    material: &Arc<dyn Material>,
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<HitRecord> {
+    let co = ray.origin() - center;
+    let a = ray.direction().length_squared();
+    let half_b = ray.direction().dot(co);
+    let c = co.length_squared() - radius * radius;
+    let discrim = half_b * half_b - a * c;
+
+    // solve the quadratic equation
+    let sqrt_d = (discrim >= 0.0).then_some(discrim.sqrt())?;
+    let root = Some((-half_b - sqrt_d) / a)
+        .filter(|&t| t > t_min && t < t_max)
+        .or_else(|| Some((-half_b + sqrt_d) / a))
+        .filter(|&t| t > t_min && t < t_max)?;
+
+    let t = root;
+    let point = ray.at(t);
+    let outward_normal = (point - center) / radius;
+
+    // FIXME: Do we have to calculate `set_face_normal` as a separate step?
+    let mut hr = HitRecord::new(point, outward_normal, t, Arc::clone(material));
+    hr.set_face_normal(ray, outward_normal);
+    hr.uv = Some(uv(center, radius, point));
+    Some(hr)
+}
+
+/// Find the span of a ray inside a sphere, if the ray passes through it.
+pub(crate) fn intervals(
+    center: Vec3,
+    radius: f64,
+    material: &Arc<dyn Material>,
+    ray: &Ray,
+) -> Vec<Interval> {
+    let co = ray.origin() - center;
+    let a = ray.direction().length_squared();
+    let b = 2.0 * ray.direction().dot(co);
+    let c = co.length_squared() - radius * radius;
+
+    // Rays that only touch the sphere don't pass through it.
+    let &[enter, exit] = &*polynomial::quadratic_roots(a, b, c) else {
+        return vec![];
+    };
+    if enter == exit {
+        return vec![];
+    }
+    let record = |t: f64| {
+        let point = ray.at(t);
+        let mut hr = HitRecord::new(point, (point - center) / radius, t, Arc::clone(material));
+        hr.uv = Some(uv(center, radius, point));
+        hr
+    };
+    vec![Interval {
+        enter: record(enter),
+        exit: record(exit),
+    }]
+}
+
+/// The texture coordinates of a point on a sphere. `u` goes once around the `y`
+/// axis, starting from `+x`, and `v` goes from the bottom of the sphere to the top.
+pub(crate) fn uv(center: Vec3, radius: f64, point: Vec3) -> (f64, f64) {
+    let d = (point - center) / radius.abs();
+    let v = (-d.y()).clamp(-1.0, 1.0).acos() / PI;
+    (frame::turns(-d.z(), d.x()), v)
+}
+
+pub(crate) fn bounding_box(center: Vec3, radius: f64) -> Aabb {
+    let r = Vec3::new(radius, radius, radius);
+    Aabb::new(center - r, center + r)
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
     use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
     use super::Sphere;
+    use crate::material::Lambertian;
+
+    fn test_sphere() -> Sphere {
+        Sphere::new(
+            Vec3::new(0.0, 0.0, 1.0),
+            0.5,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
 
     #[test]
     fn ray_misses_sphere() {
-        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 1.0), 0.5);
+        let sphere = test_sphere();
         let miss_ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 1.0, 1.0));
 
         assert!(sphere.hit(&miss_ray, -10.0, 10.0).is_none());
@@ -66,7 +160,7 @@ mod tests {
 
     #[test]
     fn ray_at_sphere_bullseye() {
-        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 1.0), 0.5);
+        let sphere = test_sphere();
         let bullseye_ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
 
         let hr = sphere
@@ -78,7 +172,7 @@ mod tests {
 
     #[test]
     fn ray_t_bounds() {
-        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 1.0), 0.5);
+        let sphere = test_sphere();
         let bullseye_ray = Ray::new(Vec3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 1.0));
 
         assert!(sphere.hit(&bullseye_ray, -1.0, 0.4999).is_none());
@@ -87,7 +181,7 @@ mod tests {
 
     #[test]
     fn ray_scrapes_sphere() {
-        let sphere = Sphere::new(Vec3::new(0.0, 0.0, 1.0), 0.5);
+        let sphere = test_sphere();
         let scrape_dir = Vec3::new(0.0, 1.0, 3.0_f64.sqrt());
 
         // The scrape direction is where the ray is tangent to the sphere,
@@ -101,4 +195,23 @@ mod tests {
         assert!(sphere.hit(&scrape_ray_hit, -1.0, 1.0).is_some());
         assert!(sphere.hit(&scrape_ray_miss, -1.0, 1.0).is_none());
     }
+
+    #[test]
+    fn sphere_uv() {
+        let sphere = test_sphere();
+        let uv = |origin: Vec3| {
+            let ray = Ray::new(origin, sphere.center - origin);
+            sphere.hit(&ray, 0.0, f64::INFINITY).unwrap().uv.unwrap()
+        };
+
+        // Around the equator, a quarter turn at a time.
+        assert_eq!((0.0, 0.5), uv(Vec3::new(5.0, 0.0, 1.0)));
+        assert_eq!((0.25, 0.5), uv(Vec3::new(0.0, 0.0, -4.0)));
+        assert_eq!((0.5, 0.5), uv(Vec3::new(-5.0, 0.0, 1.0)));
+        assert_eq!((0.75, 0.5), uv(Vec3::new(0.0, 0.0, 6.0)));
+
+        // From the bottom to the top.
+        assert_eq!(0.0, uv(Vec3::new(0.0, -5.0, 1.0)).1);
+        assert_eq!(1.0, uv(Vec3::new(0.0, 5.0, 1.0)).1);
+    }
 }
@@ -0,0 +1,195 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    frame::{self, Frame},
+    hittable::{HitRecord, Hittable},
+    polynomial,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A ring-shaped torus: the surface swept by a circle of `minor_radius` whose
+/// center moves around a circle of `major_radius` about the torus's axis.
+#[derive(Debug)]
+pub struct Torus {
+    pub center: Vec3,
+
+    /// The unit axis that the torus is swept about.
+    pub axis: Vec3,
+
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub material: Arc<dyn Material>,
+}
+
+impl Torus {
+    pub fn new<T: Into<f64>, U: Into<f64>>(
+        center: Vec3,
+        axis: Vec3,
+        major_radius: T,
+        minor_radius: U,
+        material: Arc<dyn Material>,
+    ) -> Self {
+        Self {
+            center,
+            axis: axis.normalized(),
+            major_radius: major_radius.into(),
+            minor_radius: minor_radius.into(),
+            material,
+        }
+    }
+}
+
+impl Hittable for Torus {
+    /// Texture coordinates are the angle about the axis, and the angle about the
+    /// swept circle starting from its outside edge, both as fractions of a turn.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let frame = Frame::from_axis(self.center, self.axis);
+        let local = frame.ray_to_local(ray);
+        let (big_r, small_r) = (self.major_radius, self.minor_radius);
+
+        // The coefficients of the quartic are better conditioned for a unit direction
+        // and an origin near the torus, so solve from the point on the ray nearest the
+        // center, with `t = (s + shift) / length`.
+        let length = local.direction().length();
+        let d = local.direction() / length;
+        let shift = -local.origin().dot(d);
+        let o = local.origin() + shift * d;
+        if o.length_squared() > (big_r + small_r).powi(2) {
+            return None;
+        }
+
+        // Substitute the ray into `(|p|² + R² - r²)² = 4 R² (x² + y²)`.
+        let alpha = o.dot(d);
+        let beta = o.length_squared() + big_r * big_r - small_r * small_r;
+        let four_r2 = 4.0 * big_r * big_r;
+        let roots = polynomial::quartic_roots(
+            1.0,
+            4.0 * alpha,
+            4.0 * alpha * alpha + 2.0 * beta - four_r2 * (d.x() * d.x() + d.y() * d.y()),
+            4.0 * alpha * beta - 2.0 * four_r2 * (o.x() * d.x() + o.y() * d.y()),
+            beta * beta - four_r2 * (o.x() * o.x() + o.y() * o.y()),
+        );
+        let (s, t) = roots
+            .iter()
+            .map(|&s| (s, (s + shift) / length))
+            .find(|&(_, t)| t > t_min && t < t_max)?;
+
+        // The normal points away from the nearest point on the swept circle's path.
+        let p = o + s * d;
+        let rho = (p.x() * p.x() + p.y() * p.y()).sqrt();
+        let nearest = match rho {
+            0.0 => Vec3::new(big_r, 0, 0),
+            _ => Vec3::new(p.x(), p.y(), 0) * (big_r / rho),
+        };
+        let outward_normal = frame.vector_to_world((p - nearest).normalized());
+
+        let mut hr = HitRecord::new(ray.at(t), outward_normal, t, Arc::clone(&self.material));
+        hr.set_face_normal(ray, outward_normal);
+        hr.uv = Some((frame::turns(p.y(), p.x()), frame::turns(p.z(), rho - big_r)));
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let (outer, r) = (self.major_radius + self.minor_radius, self.minor_radius);
+        let local = Aabb::new(Vec3::new(-outer, -outer, -r), Vec3::new(outer, outer, r));
+        Some(Frame::from_axis(self.center, self.axis).bounding_box(local))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Torus {
+            center: description::tuple(self.center),
+            axis: description::tuple(self.axis),
+            major_radius: self.major_radius,
+            minor_radius: self.minor_radius,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Torus;
+    use crate::material::Lambertian;
+
+    /// A torus lying in the plane z = 1, with its tube crossing the y axis from
+    /// y = 0.75 to y = 1.25.
+    fn test_torus() -> Torus {
+        Torus::new(
+            Vec3::new(0, 0, 1),
+            Vec3::new(0, 0, 1),
+            1,
+            0.25,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_misses_torus() {
+        let torus = test_torus();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 1, 0));
+        assert!(torus.hit(&miss_ray, -10.0, 10.0).is_none());
+
+        // Through the hole in the middle.
+        let hole_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+        assert!(torus.hit(&hole_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_torus_bullseye() {
+        let torus = test_torus();
+        let bullseye_ray = Ray::new(Vec3::new(0, 1, 0), Vec3::new(0, 0, 1));
+
+        let hr = torus
+            .hit(&bullseye_ray, -1.0, 1.0)
+            .expect("Ray should hit torus");
+        assert!((hr.t - 0.75).abs() < 1e-12);
+        assert!((hr.point - Vec3::new(0, 1, 0.75)).length() < 1e-12);
+        assert!((hr.normal - Vec3::new(0, 0, -1)).length() < 1e-12);
+        let (u, v) = hr.uv.unwrap();
+        assert!((u - 0.25).abs() < 1e-12 && (v - 0.75).abs() < 1e-12);
+
+        // Along the plane of the torus, the ray crosses the tube twice.
+        let side_ray = Ray::new(Vec3::new(0, -5, 1), Vec3::new(0, 1, 0));
+        let hr = torus.hit(&side_ray, 0.0, f64::INFINITY).unwrap();
+        assert!((hr.t - 3.75).abs() < 1e-9);
+        let hr = torus.hit(&side_ray, 3.8, f64::INFINITY).unwrap();
+        assert!((hr.t - 4.25).abs() < 1e-9);
+        assert_eq!(Some(false), hr.front_face);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let torus = test_torus();
+        let bullseye_ray = Ray::new(Vec3::new(0, 1, 0), Vec3::new(0, 0, 1));
+
+        assert!(torus.hit(&bullseye_ray, -1.0, 0.7499).is_none());
+        assert!(torus.hit(&bullseye_ray, -1.0, 0.7501).is_some());
+    }
+
+    #[test]
+    fn ray_scrapes_torus() {
+        let torus = test_torus();
+        // Rays along the top of the tube, from far away.
+        let scrape_ray = |z: f64| Ray::new(Vec3::new(-100, 1, z), Vec3::new(1, 0, 0));
+
+        assert!(torus.hit(&scrape_ray(1.2499), 0.0, f64::INFINITY).is_some());
+        assert!(torus.hit(&scrape_ray(1.2501), 0.0, f64::INFINITY).is_none());
+    }
+
+    #[test]
+    fn torus_has_bounding_box() {
+        let bbox = test_torus().bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-1.25, -1.25, 0.75)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(1.25, 1.25, 1.25)).length() < 1e-6);
+    }
+}
@@ -0,0 +1,195 @@
+//! This module contains the [`Transformed`] type, for moving, rotating and scaling
+//! hittables without changing them.
+use std::{io, sync::Arc};
+
+use crate::{
+    scene::description::{NameMaterial, ObjectDescription, TransformDescription},
+    utils::rand::RTRng,
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    mat4::Mat4,
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// A hittable placed in the scene by an affine transform. The same hittable can be
+/// shared by many instances, each with their own transform.
+pub struct Transformed {
+    object: Arc<dyn Hittable>,
+    matrix: Mat4,
+    inverse: Mat4,
+    /// The inverse transpose of `matrix`, which transforms normals.
+    normal_matrix: Mat4,
+}
+
+impl Transformed {
+    /// Transform `object` from its own coordinates into the scene by `matrix`.
+    ///
+    /// # Panics
+    /// Panics if `matrix` isn't invertible.
+    pub fn new(object: Arc<dyn Hittable>, matrix: Mat4) -> Self {
+        let inverse = matrix.inverse().expect("transforms must be invertible");
+        Self {
+            object,
+            matrix,
+            inverse,
+            normal_matrix: inverse.transpose(),
+        }
+    }
+
+    pub fn object(&self) -> &Arc<dyn Hittable> {
+        &self.object
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.matrix
+    }
+}
+
+impl std::fmt::Debug for Transformed {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transformed")
+            .field("matrix", &self.matrix)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Transformed {
+    /// The ray in the object's own coordinates. It isn't normalized, so that it has
+    /// the same parameter `t` at each point as the ray in the scene.
+    fn local_ray(&self, ray: &Ray) -> Ray {
+        Ray::new(
+            self.inverse.transform_point(ray.origin()),
+            self.inverse.transform_vector(ray.direction()),
+        )
+        .with_time(ray.time())
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut hr = self.object.hit(&self.local_ray(ray), t_min, t_max)?;
+
+        // Normals are transformed by the inverse transpose, so they stay
+        // perpendicular to the surface. This keeps the side of the surface that they
+        // are on, so they still point against the ray.
+        hr.point = self.matrix.transform_point(hr.point);
+        hr.normal = self.normal_matrix.transform_vector(hr.normal).normalized();
+        Some(hr)
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        let local = self.object.bounding_box()?;
+        let (min, max) = (local.min(), local.max());
+        let corner = |i: usize| {
+            let pick = |axis: usize| match i >> axis & 1 {
+                0 => min[axis],
+                _ => max[axis],
+            };
+            self.matrix
+                .transform_point(Vec3::new(pick(0), pick(1), pick(2)))
+        };
+        Some(
+            (1..8)
+                .map(corner)
+                .fold(Aabb::new(corner(0), corner(0)), |bbox, point| {
+                    bbox.union_point(point)
+                }),
+        )
+    }
+
+    fn transmittance(&self, ray: &Ray, t_min: f64, t_max: f64, rng: &RTRng) -> f64 {
+        self.object
+            .transmittance(&self.local_ray(ray), t_min, t_max, rng)
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Transformed {
+            transform: vec![TransformDescription::describe(self.matrix)],
+            object: Box::new(self.object.describe(name_material)?),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, mat4::Mat4, ray::Ray, sphere::Sphere, vec3::Vec3};
+    use super::Transformed;
+    use crate::material::Lambertian;
+
+    /// A unit sphere at the origin, stretched to twice its size along x and moved to
+    /// (0, 0, 5).
+    fn test_ellipsoid() -> Transformed {
+        let sphere = Sphere::new(
+            Vec3::new(0, 0, 0),
+            1,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        );
+        let matrix = Mat4::translation(Vec3::new(0, 0, 5)) * Mat4::scaling(Vec3::new(2, 1, 1));
+        Transformed::new(Arc::new(sphere), matrix)
+    }
+
+    #[test]
+    fn ray_misses_transformed() {
+        let ellipsoid = test_ellipsoid();
+        let miss_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 1.1, 5));
+
+        assert!(ellipsoid.hit(&miss_ray, -10.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn ray_at_transformed_bullseye() {
+        let ellipsoid = test_ellipsoid();
+        let bullseye_ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 2));
+
+        let hr = ellipsoid
+            .hit(&bullseye_ray, 0.0, 10.0)
+            .expect("Ray should hit transformed sphere");
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(0, 0, 4), hr.point);
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+    }
+
+    #[test]
+    fn ray_t_bounds() {
+        let ellipsoid = test_ellipsoid();
+        let side_ray = Ray::new(Vec3::new(-5, 0, 5), Vec3::new(1, 0, 0));
+
+        assert!(ellipsoid.hit(&side_ray, 0.0, 2.9999).is_none());
+        let hr = ellipsoid.hit(&side_ray, 0.0, 3.0001).unwrap();
+        assert!((hr.point - Vec3::new(-2, 0, 5)).length() < 1e-12);
+    }
+
+    #[test]
+    fn normals_stay_perpendicular() {
+        let ellipsoid = test_ellipsoid();
+        // The surface is `(x / 2)² + y² + (z - 5)² = 1`, with a gradient of
+        // `(x / 2, 2 y, 2 (z - 5))` along the normal.
+        let ray = Ray::new(Vec3::new(1, 0, 0), Vec3::new(0, 0, 1));
+        let hr = ellipsoid.hit(&ray, 0.0, 10.0).unwrap();
+        let (x, z) = (hr.point.x(), hr.point.z());
+        let expected = Vec3::new(x / 2.0, 0, 2.0 * (z - 5.0)).normalized();
+        assert!((hr.normal - expected).length() < 1e-12);
+    }
+
+    #[test]
+    fn transformed_bounding_box() {
+        let bbox = test_ellipsoid().bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(-2, -1, 4)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(2, 1, 6)).length() < 1e-6);
+
+        let rotated = Transformed::new(
+            Arc::new(test_ellipsoid()),
+            Mat4::rotation(Vec3::new(0, 1, 0), 90.0),
+        );
+        let bbox = rotated.bounding_box().unwrap();
+        assert!((bbox.min() - Vec3::new(4, -1, -2)).length() < 1e-6);
+        assert!((bbox.max() - Vec3::new(6, 1, 2)).length() < 1e-6);
+    }
+}
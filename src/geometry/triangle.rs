@@ -0,0 +1,269 @@
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    material::Material,
+    scene::description::{self, NameMaterial, ObjectDescription},
+};
+
+use super::{
+    aabb::Aabb,
+    hittable::{HitRecord, Hittable},
+    ray::Ray,
+    vec3::Vec3,
+};
+
+/// Determinants smaller than this are treated as rays parallel to the triangle.
+const PARALLEL_EPSILON: f64 = 1e-12;
+
+/// A triangle, optionally with per-vertex normals for smooth shading and per-vertex
+/// texture coordinates.
+#[derive(Debug)]
+pub struct Triangle {
+    pub vertices: [Vec3; 3],
+
+    /// Normals at each vertex, which are interpolated across the triangle. When
+    /// `None`, the triangle is flat shaded with its geometric normal.
+    pub normals: Option<[Vec3; 3]>,
+
+    /// Texture coordinates at each vertex, which are interpolated across the
+    /// triangle. When `None`, the barycentric coordinates of hits are used instead.
+    pub uvs: Option<[(f64, f64); 3]>,
+
+    pub material: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Create a flat shaded triangle. The front face is the side from which the
+    /// vertices appear counter-clockwise.
+    pub fn new(vertices: [Vec3; 3], material: Arc<dyn Material>) -> Self {
+        Self {
+            vertices,
+            normals: None,
+            uvs: None,
+            material,
+        }
+    }
+
+    /// Smoothly shade the triangle by interpolating normals given at each vertex.
+    pub fn with_normals(self, normals: [Vec3; 3]) -> Self {
+        Self {
+            normals: Some(normals),
+            ..self
+        }
+    }
+
+    /// Give the triangle texture coordinates at each vertex.
+    pub fn with_uvs(self, uvs: [(f64, f64); 3]) -> Self {
+        Self {
+            uvs: Some(uvs),
+            ..self
+        }
+    }
+}
+
+/// Find where a ray hits a triangle with the Möller–Trumbore algorithm, returning
+/// the ray parameter and barycentric coordinates `(u, v)` of the hit.
+pub(crate) fn intersect(
+    [v0, v1, v2]: [Vec3; 3],
+    ray: &Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let edge1 = v1 - v0;
+    let edge2 = v2 - v0;
+    let p = ray.direction().cross(edge2);
+    let det = edge1.dot(p);
+    if det.abs() < PARALLEL_EPSILON {
+        return None;
+    }
+
+    let inv_det = 1.0 / det;
+    let s = ray.origin() - v0;
+    let u = s.dot(p) * inv_det;
+    if !(0.0..=1.0).contains(&u) {
+        return None;
+    }
+
+    let q = s.cross(edge1);
+    let v = ray.direction().dot(q) * inv_det;
+    if v < 0.0 || u + v > 1.0 {
+        return None;
+    }
+
+    let t = edge2.dot(q) * inv_det;
+    (t > t_min && t < t_max).then_some((t, u, v))
+}
+
+/// Interpolate values at the vertices of a triangle with barycentric coordinates.
+pub(crate) fn interpolate<T>([a, b, c]: [T; 3], (u, v): (f64, f64)) -> T
+where
+    T: std::ops::Mul<f64, Output = T> + std::ops::Add<Output = T>,
+{
+    a * (1.0 - u - v) + b * u + c * v
+}
+
+/// Build the hit record of a ray hitting a triangle at `t` with barycentric
+/// coordinates `barycentric`.
+pub(crate) fn hit_record(
+    ray: &Ray,
+    t: f64,
+    barycentric: (f64, f64),
+    [v0, v1, v2]: [Vec3; 3],
+    normals: Option<[Vec3; 3]>,
+    uvs: Option<[(f64, f64); 3]>,
+    material: Arc<dyn Material>,
+) -> HitRecord {
+    let outward_normal = (v1 - v0).cross(v2 - v0).normalized();
+    let mut hr = HitRecord::new(ray.at(t), outward_normal, t, material);
+    hr.set_face_normal(ray, outward_normal);
+
+    // Which face was hit is decided by the geometry, but shading uses the
+    // interpolated normal, facing the same side.
+    if let Some(normals) = normals {
+        let shading_normal = interpolate(normals, barycentric).normalized();
+        hr.normal = if hr.front_face == Some(true) {
+            shading_normal
+        } else {
+            -shading_normal
+        };
+    }
+
+    hr.uv = Some(match uvs {
+        Some(uvs) => {
+            let [(u0, v0), (u1, v1), (u2, v2)] = uvs;
+            (
+                interpolate([u0, u1, u2], barycentric),
+                interpolate([v0, v1, v2], barycentric),
+            )
+        }
+        None => barycentric,
+    });
+    hr.barycentric = Some(barycentric);
+    hr
+}
+
+/// The bounding box of a triangle.
+pub(crate) fn bounding_box([v0, v1, v2]: [Vec3; 3]) -> Aabb {
+    Aabb::new(v0, v1).union_point(v2).padded()
+}
+
+impl Hittable for Triangle {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t, u, v) = intersect(self.vertices, ray, t_min, t_max)?;
+        Some(hit_record(
+            ray,
+            t,
+            (u, v),
+            self.vertices,
+            self.normals,
+            self.uvs,
+            Arc::clone(&self.material),
+        ))
+    }
+
+    fn bounding_box(&self) -> Option<Aabb> {
+        Some(bounding_box(self.vertices))
+    }
+
+    fn describe(&self, name_material: &mut NameMaterial) -> io::Result<ObjectDescription> {
+        Ok(ObjectDescription::Triangle {
+            vertices: self.vertices.map(description::tuple),
+            normals: self.normals.map(|normals| normals.map(description::tuple)),
+            uvs: self.uvs,
+            material: name_material(&self.material)?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::super::{hittable::Hittable, ray::Ray, vec3::Vec3};
+    use super::Triangle;
+    use crate::material::Lambertian;
+
+    fn test_triangle() -> Triangle {
+        Triangle::new(
+            [
+                Vec3::new(0, 0, -1),
+                Vec3::new(1, 0, -1),
+                Vec3::new(0, 1, -1),
+            ],
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        )
+    }
+
+    #[test]
+    fn ray_hits_triangle() {
+        let triangle = test_triangle();
+        let ray = Ray::new(Vec3::new(0.25, 0.5, 1), Vec3::new(0, 0, -1));
+
+        let hr = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.0, hr.t);
+        assert_eq!(Vec3::new(0.25, 0.5, -1), hr.point);
+        assert_eq!(Vec3::new(0, 0, 1), hr.normal);
+        assert_eq!(Some(true), hr.front_face);
+        assert_eq!(Some((0.25, 0.5)), hr.barycentric);
+        assert_eq!(Some((0.25, 0.5)), hr.uv);
+    }
+
+    #[test]
+    fn ray_hits_back_face() {
+        let triangle = test_triangle();
+        let ray = Ray::new(Vec3::new(0.25, 0.25, -3), Vec3::new(0, 0, 1));
+
+        let hr = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Some(false), hr.front_face);
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+    }
+
+    #[test]
+    fn ray_misses_triangle() {
+        let triangle = test_triangle();
+        let origin = Vec3::new(0, 0, 1);
+
+        for direction in [
+            Vec3::new(0.6, 0.6, -2),
+            Vec3::new(-0.1, 0.5, -2),
+            Vec3::new(0.5, -0.1, -2),
+            Vec3::new(1, 1, 0),
+            Vec3::new(0.1, 0.1, 1),
+        ] {
+            assert!(triangle
+                .hit(&Ray::new(origin, direction), 0.0, f64::INFINITY)
+                .is_none());
+        }
+
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 1), Vec3::new(0, 0, -1));
+        assert!(triangle.hit(&ray, 0.0, 1.5).is_none());
+    }
+
+    #[test]
+    fn interpolated_normals_and_uvs() {
+        let triangle = test_triangle()
+            .with_normals([Vec3::new(0, 0, 1), Vec3::new(1, 0, 0), Vec3::new(0, 1, 0)])
+            .with_uvs([(0.0, 0.0), (2.0, 0.0), (0.0, 4.0)]);
+        let ray = Ray::new(Vec3::new(0.5, 0.5, 1), Vec3::new(0, 0, -1));
+
+        let hr = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Some((0.5, 0.5)), hr.barycentric);
+        assert_eq!(Some((1.0, 2.0)), hr.uv);
+        assert_eq!(Vec3::new(1, 1, 0).normalized(), hr.normal);
+
+        // Interpolated normals face the side that was hit.
+        let ray = Ray::new(Vec3::new(0, 0, -2), Vec3::new(0, 0, 1));
+        let hr = triangle.hit(&ray, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(Vec3::new(0, 0, -1), hr.normal);
+    }
+
+    #[test]
+    fn flat_triangle_has_bounding_box() {
+        let triangle = test_triangle();
+        let bbox = triangle.bounding_box().unwrap();
+        let ray = Ray::new(Vec3::new(0.25, 0.25, 1), Vec3::new(0, 0, -1));
+
+        assert!(bbox.hit(&ray, 0.0, f64::INFINITY));
+        assert!(bbox.min().z() < -1.0 && bbox.max().z() > -1.0);
+    }
+}
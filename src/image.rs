@@ -0,0 +1,171 @@
+//! Reading and writing images.
+use std::{
+    error::Error,
+    fs::File,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::tracer::ImagePixels;
+
+pub mod exr;
+pub mod hdr;
+pub mod pfm;
+pub mod png;
+pub mod ppm;
+mod zlib;
+
+/// Image formats that can be written.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Exr(exr::Compression),
+    /// PNG with 8 or 16 bits per channel.
+    Png(png::BitDepth),
+    Ppm(ppm::Encoding),
+    Pfm,
+}
+
+impl Format {
+    /// Choose a format from the extension of a path. EXR images are written with zip
+    /// compression, PNG images are written with 8 bits per channel and PPM images are
+    /// written in binary. Returns `None` if the extension isn't recognised.
+    pub fn from_path(path: impl AsRef<Path>) -> Option<Self> {
+        match extension(path.as_ref())?.as_str() {
+            "exr" => Some(Format::Exr(exr::Compression::Zip)),
+            "png" => Some(Format::Png(png::BitDepth::Eight)),
+            "ppm" => Some(Format::Ppm(ppm::Encoding::Binary)),
+            "pfm" => Some(Format::Pfm),
+            _ => None,
+        }
+    }
+
+    /// Write an image in this format.
+    ///
+    /// # Errors
+    /// Returns an error if the image can't be written.
+    pub fn write(&self, writer: impl Write, pixels: &ImagePixels) -> io::Result<()> {
+        match *self {
+            Format::Exr(compression) => exr::write(writer, pixels, compression),
+            Format::Png(bit_depth) => png::write(writer, pixels, bit_depth),
+            Format::Ppm(encoding) => ppm::write(writer, pixels, encoding),
+            Format::Pfm => pfm::write(writer, pixels),
+        }
+    }
+}
+
+/// Write an image to a file, choosing the format from the file extension. Use
+/// [`write_as`] to choose options that the extension can't, such as
+/// `Format::Png(png::BitDepth::Sixteen)`.
+///
+/// # Errors
+/// Returns an error if the format is not supported, or the image can't be written.
+pub fn write(path: impl AsRef<Path>, pixels: &ImagePixels) -> io::Result<()> {
+    let path = path.as_ref();
+    let format = Format::from_path(path).ok_or_else(|| unsupported_format(path))?;
+    write_as(path, pixels, format)
+}
+
+/// Write an image to a file in the given format.
+///
+/// # Errors
+/// Returns an error if the image can't be written.
+pub fn write_as(path: impl AsRef<Path>, pixels: &ImagePixels, format: Format) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    format.write(&mut writer, pixels)?;
+    writer.flush()
+}
+
+/// Read an image from a file, choosing the format from the file extension.
+///
+/// # Errors
+/// Returns an error if the file can't be read, its format is not supported, or the
+/// file does not contain a valid image.
+pub fn read(path: impl AsRef<Path>) -> io::Result<ImagePixels> {
+    let path = path.as_ref();
+    let reader = BufReader::new(File::open(path)?);
+    match extension(path).as_deref() {
+        Some("hdr") => hdr::read(reader),
+        Some("pfm") => pfm::read(reader),
+        Some("ppm") => ppm::read(reader),
+        _ => Err(unsupported_format(path)),
+    }
+}
+
+/// The width and height of an image.
+///
+/// # Errors
+/// Returns an error if the image is empty or its scanlines differ in length.
+fn dimensions(pixels: &ImagePixels) -> io::Result<(usize, usize)> {
+    let width = pixels.first().map_or(0, Vec::len);
+    if width == 0 || pixels.iter().any(|row| row.len() != width) {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Image must be a non-empty rectangle of pixels",
+        ));
+    }
+    Ok((width, pixels.len()))
+}
+
+/// The lowercase extension of a path.
+fn extension(path: &Path) -> Option<String> {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(str::to_ascii_lowercase)
+}
+
+fn unsupported_format(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Unsupported,
+        format!("Unsupported image format: {}", path.display()),
+    )
+}
+
+/// Read a whitespace delimited token from the header of a Netpbm-style image,
+/// skipping `#` comments. The single whitespace character ending the token is
+/// consumed.
+fn read_header_token(reader: &mut impl BufRead) -> io::Result<String> {
+    let mut token = String::new();
+    let mut in_comment = false;
+    loop {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        match byte[0] {
+            b'\n' if in_comment => in_comment = false,
+            _ if in_comment => {}
+            b'#' if token.is_empty() => in_comment = true,
+            b if b.is_ascii_whitespace() => {
+                if !token.is_empty() {
+                    return Ok(token);
+                }
+            }
+            b => token.push(b as char),
+        }
+    }
+}
+
+/// The number of samples in an image with the given dimensions and number of
+/// channels.
+///
+/// # Errors
+/// Returns an error if the number doesn't fit in a `usize`.
+fn sample_count(width: usize, height: usize, channels: usize) -> io::Result<usize> {
+    width
+        .checked_mul(height)
+        .and_then(|pixels| pixels.checked_mul(channels))
+        .ok_or_else(|| invalid_data(format!("Image of size {width}x{height} is too large")))
+}
+
+/// Read exactly `len` bytes. Unlike [`Read::read_exact`], the buffer grows as the
+/// data arrives, so a length read from a corrupt header can't exhaust memory.
+fn read_bytes(reader: &mut impl Read, len: usize) -> io::Result<Vec<u8>> {
+    let mut data = vec![];
+    reader.take(len as u64).read_to_end(&mut data)?;
+    if data.len() != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(data)
+}
+
+fn invalid_data(error: impl Into<Box<dyn Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
@@ -0,0 +1,549 @@
+//! Writer for OpenEXR (`.exr`) images.
+//!
+//! Images are written as single-part scanline images, with 32-bit float or unsigned
+//! integer channels, either uncompressed or compressed with zlib.
+use std::io::{self, Write};
+
+use super::zlib;
+use crate::{
+    color::Color,
+    tracer::{Aovs, ImagePixels},
+};
+
+const MAGIC: [u8; 4] = [0x76, 0x2F, 0x31, 0x01];
+
+/// How the pixel data of an image is compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// zlib compression of one scanline at a time.
+    Zips,
+    /// zlib compression of blocks of 16 scanlines.
+    Zip,
+}
+
+impl Compression {
+    /// The value identifying the compression in the image header.
+    fn id(self) -> u8 {
+        match self {
+            Compression::None => 0,
+            Compression::Zips => 2,
+            Compression::Zip => 3,
+        }
+    }
+
+    /// The number of scanlines stored in each chunk of pixel data.
+    fn scanlines_per_chunk(self) -> usize {
+        match self {
+            Compression::None | Compression::Zips => 1,
+            Compression::Zip => 16,
+        }
+    }
+}
+
+/// The samples of a channel, ordered top to bottom and left to right.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Samples {
+    Uint(Vec<u32>),
+    Float(Vec<f32>),
+}
+
+impl Samples {
+    fn len(&self) -> usize {
+        match self {
+            Samples::Uint(samples) => samples.len(),
+            Samples::Float(samples) => samples.len(),
+        }
+    }
+
+    /// The value identifying the sample type in the image header.
+    fn pixel_type(&self) -> i32 {
+        match self {
+            Samples::Uint(_) => 0,
+            Samples::Float(_) => 2,
+        }
+    }
+
+    fn write_scanline(&self, out: &mut Vec<u8>, y: usize, width: usize) {
+        let range = y * width..(y + 1) * width;
+        match self {
+            Samples::Uint(samples) => {
+                out.extend(samples[range].iter().flat_map(|s| s.to_le_bytes()));
+            }
+            Samples::Float(samples) => {
+                out.extend(samples[range].iter().flat_map(|s| s.to_le_bytes()));
+            }
+        }
+    }
+}
+
+/// A named channel of an image.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Channel {
+    pub name: String,
+    pub samples: Samples,
+}
+
+impl Channel {
+    pub fn new(name: impl Into<String>, samples: Samples) -> Self {
+        Self {
+            name: name.into(),
+            samples,
+        }
+    }
+}
+
+/// Write an image of linear colors as `R`, `G` and `B` float channels.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty or its scanlines
+/// differ in length.
+pub fn write(writer: impl Write, pixels: &ImagePixels, compression: Compression) -> io::Result<()> {
+    let (width, height) = super::dimensions(pixels)?;
+    write_channels(
+        writer,
+        width,
+        height,
+        color_channels(pixels, ""),
+        compression,
+    )
+}
+
+/// Write an image of linear colors along with its AOVs. The AOVs are written as
+/// the channels:
+/// - `Z`: depth.
+/// - `normal.X`, `normal.Y` and `normal.Z`: surface normal.
+/// - `albedo.R`, `albedo.G` and `albedo.B`: albedo.
+/// - `id`: object ID, as an unsigned integer.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty, its scanlines differ
+/// in length, or the AOVs have different dimensions to the image.
+pub fn write_with_aovs(
+    writer: impl Write,
+    pixels: &ImagePixels,
+    aovs: &Aovs,
+    compression: Compression,
+) -> io::Result<()> {
+    let (width, height) = super::dimensions(pixels)?;
+    let mut channels = color_channels(pixels, "");
+    channels.extend(color_channels(&aovs.albedo, "albedo."));
+    channels.push(Channel::new(
+        "Z",
+        Samples::Float(aovs.depth.iter().flatten().map(|&d| d as f32).collect()),
+    ));
+    for (axis, name) in ["normal.X", "normal.Y", "normal.Z"].iter().enumerate() {
+        let samples = aovs.normal.iter().flatten().map(|n| n[axis] as f32);
+        channels.push(Channel::new(*name, Samples::Float(samples.collect())));
+    }
+    let ids = aovs.object_id.iter().flatten().copied().collect();
+    channels.push(Channel::new("id", Samples::Uint(ids)));
+
+    write_channels(writer, width, height, channels, compression)
+}
+
+/// Write an image made of arbitrary channels. Channels are stored in the file
+/// sorted by name, as required by the format.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty, a channel does not
+/// have `width * height` samples, a channel name is empty or longer than 31 bytes,
+/// or two channels share a name.
+pub fn write_channels(
+    mut writer: impl Write,
+    width: usize,
+    height: usize,
+    mut channels: Vec<Channel>,
+    compression: Compression,
+) -> io::Result<()> {
+    if width == 0 || height == 0 || width > i32::MAX as usize || height > i32::MAX as usize {
+        return Err(invalid_input("Image must have a positive width and height"));
+    }
+    if channels.iter().any(|c| c.samples.len() != width * height) {
+        return Err(invalid_input("Channels must have a sample for every pixel"));
+    }
+    // Longer names need the long names flag, which not every reader supports.
+    if channels
+        .iter()
+        .any(|c| c.name.is_empty() || c.name.len() > 31)
+    {
+        return Err(invalid_input("Channel names must be 1 to 31 bytes long"));
+    }
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    if channels.windows(2).any(|pair| pair[0].name == pair[1].name) {
+        return Err(invalid_input("Channel names must be unique"));
+    }
+
+    let header = header(width, height, &channels, compression);
+
+    let lines_per_chunk = compression.scanlines_per_chunk();
+    let chunks: Vec<Vec<u8>> = (0..height)
+        .step_by(lines_per_chunk)
+        .map(|y| {
+            let mut data = vec![];
+            for line in y..(y + lines_per_chunk).min(height) {
+                for channel in &channels {
+                    channel.samples.write_scanline(&mut data, line, width);
+                }
+            }
+            let data = compress(data, compression);
+
+            let mut chunk = (y as i32).to_le_bytes().to_vec();
+            chunk.extend((data.len() as i32).to_le_bytes());
+            chunk.extend(data);
+            chunk
+        })
+        .collect();
+
+    // The offset table gives the position of each chunk in the file.
+    let mut offset = (header.len() + 8 * chunks.len()) as u64;
+    let mut offsets = Vec::with_capacity(8 * chunks.len());
+    for chunk in &chunks {
+        offsets.extend(offset.to_le_bytes());
+        offset += chunk.len() as u64;
+    }
+
+    writer.write_all(&header)?;
+    writer.write_all(&offsets)?;
+    for chunk in &chunks {
+        writer.write_all(chunk)?;
+    }
+    Ok(())
+}
+
+/// Split an image into red, green and blue float channels.
+fn color_channels(pixels: &ImagePixels, prefix: &str) -> Vec<Channel> {
+    let samples = |component: fn(&Color) -> f64| {
+        let samples = pixels.iter().flatten().map(|c| component(c) as f32);
+        Samples::Float(samples.collect())
+    };
+    vec![
+        Channel::new(format!("{prefix}R"), samples(|c| c.red)),
+        Channel::new(format!("{prefix}G"), samples(|c| c.green)),
+        Channel::new(format!("{prefix}B"), samples(|c| c.blue)),
+    ]
+}
+
+/// The magic number, version and header attributes of an image.
+fn header(width: usize, height: usize, channels: &[Channel], compression: Compression) -> Vec<u8> {
+    let mut header = MAGIC.to_vec();
+    // Version 2, single-part scanline image.
+    header.extend(2u32.to_le_bytes());
+
+    let mut channel_list = vec![];
+    for channel in channels {
+        channel_list.extend(channel.name.as_bytes());
+        channel_list.push(0);
+        channel_list.extend(channel.samples.pixel_type().to_le_bytes());
+        // Not perceptually linear, followed by three reserved bytes.
+        channel_list.extend([0; 4]);
+        // No subsampling in x or y.
+        channel_list.extend(1i32.to_le_bytes());
+        channel_list.extend(1i32.to_le_bytes());
+    }
+    channel_list.push(0);
+
+    let window: Vec<u8> = [0, 0, width as i32 - 1, height as i32 - 1]
+        .iter()
+        .flat_map(|v| v.to_le_bytes())
+        .collect();
+
+    let mut attribute = |name: &str, kind: &str, value: &[u8]| {
+        for s in [name, kind] {
+            header.extend(s.as_bytes());
+            header.push(0);
+        }
+        header.extend((value.len() as i32).to_le_bytes());
+        header.extend(value);
+    };
+    attribute("channels", "chlist", &channel_list);
+    attribute("compression", "compression", &[compression.id()]);
+    attribute("dataWindow", "box2i", &window);
+    attribute("displayWindow", "box2i", &window);
+    // Increasing y, so scanlines are stored top to bottom.
+    attribute("lineOrder", "lineOrder", &[0]);
+    attribute("pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute("screenWindowCenter", "v2f", &[0; 8]);
+    attribute("screenWindowWidth", "float", &1f32.to_le_bytes());
+
+    header.push(0);
+    header
+}
+
+/// Compress the pixel data of a chunk. Data that doesn't get smaller is stored
+/// uncompressed, which readers detect from its size.
+fn compress(data: Vec<u8>, compression: Compression) -> Vec<u8> {
+    if compression == Compression::None {
+        return data;
+    }
+
+    // Separate the low and high bytes of samples, then store the differences
+    // between consecutive bytes, to make the data more compressible.
+    let (even, odd): (Vec<_>, Vec<_>) = data.iter().enumerate().partition(|(i, _)| i % 2 == 0);
+    let mut predicted: Vec<u8> = even.into_iter().chain(odd).map(|(_, &b)| b).collect();
+    for i in (1..predicted.len()).rev() {
+        predicted[i] = predicted[i]
+            .wrapping_sub(predicted[i - 1])
+            .wrapping_add(128);
+    }
+
+    let compressed = zlib::compress(&predicted);
+    if compressed.len() < data.len() {
+        compressed
+    } else {
+        data
+    }
+}
+
+fn invalid_input(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::{write, write_channels, write_with_aovs, Channel, Compression, Samples, MAGIC};
+    use crate::{
+        color::Color,
+        geometry::vec3::Vec3,
+        image::zlib::tests::decompress,
+        tracer::{Aovs, ImagePixels},
+    };
+
+    fn i32_at(data: &[u8], pos: usize) -> i32 {
+        i32::from_le_bytes(data[pos..pos + 4].try_into().unwrap())
+    }
+
+    fn read_string(data: &[u8], pos: &mut usize) -> String {
+        let end = *pos + data[*pos..].iter().position(|&b| b == 0).unwrap();
+        let s = String::from_utf8(data[*pos..end].to_vec()).unwrap();
+        *pos = end + 1;
+        s
+    }
+
+    /// A decoded image: its attributes, channel names and types, and the
+    /// uncompressed pixel data of each chunk.
+    struct Decoded {
+        attributes: HashMap<String, Vec<u8>>,
+        channels: Vec<(String, i32)>,
+        chunks: Vec<(i32, Vec<u8>)>,
+    }
+
+    fn decode(data: &[u8]) -> Decoded {
+        assert_eq!(MAGIC, data[..4]);
+        assert_eq!(2, i32_at(data, 4));
+
+        let mut pos = 8;
+        let mut attributes = HashMap::new();
+        while data[pos] != 0 {
+            let name = read_string(data, &mut pos);
+            read_string(data, &mut pos);
+            let size = i32_at(data, pos) as usize;
+            attributes.insert(name, data[pos + 4..pos + 4 + size].to_vec());
+            pos += 4 + size;
+        }
+        pos += 1;
+
+        let channel_list = &attributes["channels"];
+        let mut channels = vec![];
+        let mut channel_pos = 0;
+        while channel_list[channel_pos] != 0 {
+            let name = read_string(channel_list, &mut channel_pos);
+            channels.push((name, i32_at(channel_list, channel_pos)));
+            channel_pos += 16;
+        }
+
+        let window = &attributes["dataWindow"];
+        let height = i32_at(window, 12) as usize + 1;
+        let lines_per_chunk = match attributes["compression"][0] {
+            0 | 2 => 1,
+            3 => 16,
+            c => panic!("Unexpected compression {c}"),
+        };
+        let chunk_count = height.div_ceil(lines_per_chunk);
+
+        let chunks = (0..chunk_count)
+            .map(|i| {
+                let offset = u64::from_le_bytes(data[pos + 8 * i..][..8].try_into().unwrap());
+                let offset = offset as usize;
+                let y = i32_at(data, offset);
+                let size = i32_at(data, offset + 4) as usize;
+                (y, data[offset + 8..offset + 8 + size].to_vec())
+            })
+            .collect();
+
+        Decoded {
+            attributes,
+            channels,
+            chunks,
+        }
+    }
+
+    /// Undo the compression of a chunk, given its uncompressed size.
+    fn uncompress(data: &[u8], size: usize) -> Vec<u8> {
+        if data.len() == size {
+            return data.to_vec();
+        }
+
+        let mut predicted = decompress(data);
+        for i in 1..predicted.len() {
+            predicted[i] = predicted[i]
+                .wrapping_add(predicted[i - 1])
+                .wrapping_sub(128);
+        }
+        let (even, odd) = predicted.split_at(size.div_ceil(2));
+        (0..size)
+            .map(|i| if i % 2 == 0 { even[i / 2] } else { odd[i / 2] })
+            .collect()
+    }
+
+    fn floats(data: &[u8]) -> Vec<f32> {
+        data.chunks_exact(4)
+            .map(|b| f32::from_le_bytes(b.try_into().unwrap()))
+            .collect()
+    }
+
+    fn test_image() -> ImagePixels {
+        (0..20)
+            .map(|y| {
+                (0..3)
+                    .map(|x| Color::new(x as f64, y as f64, 0.5))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn header() {
+        let mut data = vec![];
+        write(&mut data, &test_image(), Compression::None).unwrap();
+        let decoded = decode(&data);
+
+        let channels: Vec<_> = decoded.channels.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(vec!["B", "G", "R"], channels);
+        assert!(decoded.channels.iter().all(|&(_, kind)| kind == 2));
+
+        let window: Vec<i32> = (0..4)
+            .map(|i| i32_at(&decoded.attributes["dataWindow"], 4 * i))
+            .collect();
+        assert_eq!(vec![0, 0, 2, 19], window);
+        assert_eq!(
+            decoded.attributes["dataWindow"],
+            decoded.attributes["displayWindow"]
+        );
+        for name in [
+            "lineOrder",
+            "pixelAspectRatio",
+            "screenWindowCenter",
+            "screenWindowWidth",
+        ] {
+            assert!(decoded.attributes.contains_key(name), "Missing {name}");
+        }
+    }
+
+    #[test]
+    fn uncompressed_scanlines() {
+        let mut data = vec![];
+        write(&mut data, &test_image(), Compression::None).unwrap();
+        let decoded = decode(&data);
+
+        assert_eq!(20, decoded.chunks.len());
+        for (i, (y, chunk)) in decoded.chunks.iter().enumerate() {
+            assert_eq!(i as i32, *y);
+            // Blue, green then red samples for the whole scanline.
+            let expected = [[0.5; 3], [i as f32; 3], [0.0, 1.0, 2.0]].concat();
+            assert_eq!(expected, floats(chunk));
+        }
+    }
+
+    #[test]
+    fn compressed_scanlines() {
+        for compression in [Compression::Zips, Compression::Zip] {
+            let mut data = vec![];
+            write(&mut data, &test_image(), compression).unwrap();
+            let decoded = decode(&data);
+
+            let lines_per_chunk = if compression == Compression::Zip {
+                16
+            } else {
+                1
+            };
+            let mut scanlines = vec![];
+            for (i, (y, chunk)) in decoded.chunks.iter().enumerate() {
+                assert_eq!((i * lines_per_chunk) as i32, *y);
+                let lines = lines_per_chunk.min(20 - i * lines_per_chunk);
+                let samples = floats(&uncompress(chunk, lines * 9 * 4));
+                scanlines.extend(samples.chunks(9).map(<[f32]>::to_vec));
+            }
+
+            assert_eq!(20, scanlines.len());
+            for (y, scanline) in scanlines.iter().enumerate() {
+                assert_eq!(
+                    [[0.5; 3], [y as f32; 3], [0.0, 1.0, 2.0]].concat(),
+                    *scanline
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn aov_channels() {
+        let aovs = Aovs {
+            depth: vec![vec![1.5, f64::INFINITY]],
+            normal: vec![vec![Vec3::new(0, 0, 1), Vec3::default()]],
+            albedo: vec![vec![Color::new(0.25, 0.5, 0.75), Color::default()]],
+            object_id: vec![vec![7, 0]],
+        };
+        let pixels = vec![vec![Color::new(1.0, 2.0, 3.0), Color::default()]];
+        let mut data = vec![];
+        write_with_aovs(&mut data, &pixels, &aovs, Compression::None).unwrap();
+        let decoded = decode(&data);
+
+        let channels: Vec<_> = decoded.channels.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(
+            vec![
+                "B", "G", "R", "Z", "albedo.B", "albedo.G", "albedo.R", "id", "normal.X",
+                "normal.Y", "normal.Z"
+            ],
+            channels
+        );
+        assert_eq!(("id".to_string(), 0), decoded.channels[7]);
+
+        let samples = &decoded.chunks[0].1;
+        let float_samples = floats(samples);
+        assert_eq!([3.0, 0.0], float_samples[0..2]);
+        assert_eq!([1.5, f32::INFINITY], float_samples[6..8]);
+        assert_eq!([0.25, 0.0], float_samples[12..14]);
+        assert_eq!(7, i32_at(samples, 14 * 4));
+        assert_eq!([1.0, 0.0], float_samples[20..22]);
+    }
+
+    #[test]
+    fn invalid_channels() {
+        let channel = |name: &str, len| Channel::new(name, Samples::Float(vec![0.0; len]));
+        let mut data = vec![];
+
+        assert!(write_channels(&mut data, 2, 2, vec![channel("Y", 3)], Compression::None).is_err());
+        assert!(write_channels(
+            &mut data,
+            2,
+            2,
+            vec![channel("Y", 4), channel("Y", 4)],
+            Compression::None
+        )
+        .is_err());
+        assert!(write_channels(&mut data, 2, 2, vec![channel("", 4)], Compression::None).is_err());
+        let long_name = "Y".repeat(32);
+        assert!(write_channels(
+            &mut data,
+            2,
+            2,
+            vec![channel(&long_name, 4)],
+            Compression::None
+        )
+        .is_err());
+        assert!(write_channels(&mut data, 0, 2, vec![], Compression::None).is_err());
+        assert!(data.is_empty());
+    }
+}
@@ -0,0 +1,205 @@
+//! Reader for Radiance RGBE (`.hdr`) images.
+//!
+//! Each pixel is stored as an 8-bit mantissa per channel sharing a single 8-bit
+//! exponent. Scanlines may be stored flat or with the "new" run-length encoding.
+use std::io::{self, BufRead};
+
+use super::{invalid_data, read_bytes, sample_count};
+use crate::{color::Color, tracer::ImagePixels};
+
+/// Read a Radiance RGBE image.
+///
+/// # Errors
+/// Returns an error if reading fails, or if the data is not a valid RGBE image.
+pub fn read(mut reader: impl BufRead) -> io::Result<ImagePixels> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if !line.starts_with("#?") {
+        return Err(invalid_data("Missing Radiance header"));
+    }
+
+    // Header variables end at an empty line.
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("Unexpected end of Radiance header"));
+        }
+        let line = line.trim();
+        if line.is_empty() {
+            break;
+        }
+        if let Some(format) = line.strip_prefix("FORMAT=") {
+            if format != "32-bit_rle_rgbe" {
+                return Err(invalid_data(format!(
+                    "Unsupported Radiance format {format}"
+                )));
+            }
+        }
+    }
+
+    line.clear();
+    reader.read_line(&mut line)?;
+    let (flip_vertical, height, width) = match line.split_whitespace().collect::<Vec<_>>()[..] {
+        [y_axis @ ("-Y" | "+Y"), height, "+X", width] => (
+            y_axis == "+Y",
+            height.parse::<usize>().map_err(invalid_data)?,
+            width.parse::<usize>().map_err(invalid_data)?,
+        ),
+        _ => {
+            return Err(invalid_data(format!(
+                "Unsupported resolution string {:?}",
+                line.trim()
+            )))
+        }
+    };
+
+    if width == 0 || height == 0 {
+        return Err(invalid_data("Image has no pixels"));
+    }
+
+    // The header isn't trusted to preallocate the image.
+    sample_count(width, height, 4)?;
+    let mut pixels: ImagePixels = vec![];
+    for _ in 0..height {
+        let rgbe = read_scanline(&mut reader, width)?;
+        pixels.push(rgbe.into_iter().map(rgbe_to_color).collect());
+    }
+
+    if flip_vertical {
+        pixels.reverse();
+    }
+    Ok(pixels)
+}
+
+fn read_scanline(reader: &mut impl BufRead, width: usize) -> io::Result<Vec<[u8; 4]>> {
+    let mut first = [0u8; 4];
+    reader.read_exact(&mut first)?;
+
+    let is_rle = (8..0x8000).contains(&width) && first[0] == 2 && first[1] == 2 && first[2] < 128;
+    if !is_rle {
+        let rest = read_bytes(reader, (width - 1) * 4)?;
+        let rest = rest.chunks_exact(4).map(|p| [p[0], p[1], p[2], p[3]]);
+        return Ok([first].into_iter().chain(rest).collect());
+    }
+
+    if (first[2] as usize) << 8 | first[3] as usize != width {
+        return Err(invalid_data("Run-length encoded scanline has wrong width"));
+    }
+
+    // Each channel is run-length encoded separately.
+    let mut scanline = vec![[0u8; 4]; width];
+    for channel in 0..4 {
+        let mut x = 0;
+        while x < width {
+            let mut count = [0u8; 1];
+            reader.read_exact(&mut count)?;
+            let (count, is_run) = match count[0] {
+                c if c > 128 => (c as usize - 128, true),
+                c => (c as usize, false),
+            };
+            if count == 0 || x + count > width {
+                return Err(invalid_data("Bad run-length encoded scanline"));
+            }
+
+            if is_run {
+                let mut value = [0u8; 1];
+                reader.read_exact(&mut value)?;
+                for pixel in &mut scanline[x..x + count] {
+                    pixel[channel] = value[0];
+                }
+            } else {
+                let mut values = vec![0u8; count];
+                reader.read_exact(&mut values)?;
+                for (pixel, value) in scanline[x..x + count].iter_mut().zip(values) {
+                    pixel[channel] = value;
+                }
+            }
+            x += count;
+        }
+    }
+
+    Ok(scanline)
+}
+
+fn rgbe_to_color([r, g, b, e]: [u8; 4]) -> Color {
+    if e == 0 {
+        return Color::default();
+    }
+
+    let scale = 2f64.powi(e as i32 - (128 + 8));
+    Color::new(r as f64 * scale, g as f64 * scale, b as f64 * scale)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::read;
+    use crate::color::Color;
+
+    fn header(width: usize, height: usize) -> Vec<u8> {
+        format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {height} +X {width}\n").into_bytes()
+    }
+
+    #[test]
+    fn flat_scanlines() {
+        let mut data = header(2, 2);
+        // An exponent of 129 gives values of mantissa / 128.
+        data.extend([128, 64, 0, 129, 0, 0, 0, 0]);
+        data.extend([255, 255, 255, 130, 32, 1, 0, 128]);
+
+        let pixels = read(data.as_slice()).unwrap();
+        assert_eq!(2, pixels.len());
+        assert_eq!(Color::new(1.0, 0.5, 0.0), pixels[0][0]);
+        assert_eq!(Color::default(), pixels[0][1]);
+        assert_eq!(Color::new(3.984375, 3.984375, 3.984375), pixels[1][0]);
+        assert_eq!(Color::new(0.125, 1.0 / 256.0, 0.0), pixels[1][1]);
+    }
+
+    #[test]
+    fn run_length_encoded_scanlines() {
+        let width = 10;
+        let mut data = header(width, 1);
+        data.extend([2, 2, 0, width as u8]);
+        // Red: a run of 10.
+        data.extend([128 + 10, 128]);
+        // Green: 5 literal values then a run of 5.
+        data.extend([5, 1, 3, 5, 7, 9, 128 + 5, 64]);
+        // Blue: a run of 10 zeros.
+        data.extend([128 + 10, 0]);
+        // Exponent: a run of 10.
+        data.extend([128 + 10, 129]);
+
+        let pixels = read(data.as_slice()).unwrap();
+        assert_eq!(1, pixels.len());
+        assert_eq!(width, pixels[0].len());
+        assert_eq!(Color::new(1.0, 1.0 / 128.0, 0.0), pixels[0][0]);
+        assert_eq!(Color::new(1.0, 9.0 / 128.0, 0.0), pixels[0][4]);
+        assert_eq!(Color::new(1.0, 0.5, 0.0), pixels[0][9]);
+    }
+
+    #[test]
+    fn flipped_vertical_axis() {
+        let mut data = b"#?RGBE\n\n+Y 2 +X 1\n".to_vec();
+        data.extend([128, 128, 128, 129, 0, 0, 0, 0]);
+
+        let pixels = read(data.as_slice()).unwrap();
+        assert_eq!(Color::default(), pixels[0][0]);
+        assert_eq!(Color::new(1.0, 1.0, 1.0), pixels[1][0]);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(read(b"P3\n1 1\n255\n".as_slice()).is_err());
+        assert!(read(b"#?RADIANCE\nFORMAT=32-bit_rle_xyze\n\n-Y 1 +X 1\n".as_slice()).is_err());
+
+        let mut truncated = header(2, 2);
+        truncated.extend([128, 64, 0, 129]);
+        assert!(read(truncated.as_slice()).is_err());
+
+        let mut huge = header(1 << 40, 1 << 40);
+        huge.extend([128, 64, 0, 129]);
+        assert!(read(huge.as_slice()).is_err());
+        let mut huge = header(1 << 40, 1);
+        huge.extend([128, 64, 0, 129]);
+        assert!(read(huge.as_slice()).is_err());
+    }
+}
@@ -0,0 +1,139 @@
+//! Reader and writer for Portable FloatMap (`.pfm`) images.
+//!
+//! PFM images store 32-bit floating-point samples, with scanlines ordered from the
+//! bottom of the image to the top.
+use std::io::{self, BufRead, Write};
+
+use super::{invalid_data, read_bytes, read_header_token, sample_count};
+use crate::{color::Color, tracer::ImagePixels};
+
+/// Write a color PFM image of linear colors, with little-endian samples.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty or its scanlines
+/// differ in length.
+pub fn write(mut writer: impl Write, pixels: &ImagePixels) -> io::Result<()> {
+    let (width, height) = super::dimensions(pixels)?;
+    // A negative scale indicates little-endian samples.
+    write!(writer, "PF\n{width} {height}\n-1.0\n")?;
+
+    for row in pixels.iter().rev() {
+        let bytes: Vec<u8> = row
+            .iter()
+            .flat_map(|c| [c.red, c.green, c.blue])
+            .flat_map(|sample| (sample as f32).to_le_bytes())
+            .collect();
+        writer.write_all(&bytes)?;
+    }
+    Ok(())
+}
+
+/// Read a color (`PF`) or greyscale (`Pf`) PFM image.
+///
+/// # Errors
+/// Returns an error if reading fails, or if the data is not a valid PFM image.
+pub fn read(mut reader: impl BufRead) -> io::Result<ImagePixels> {
+    let channels = match read_header_token(&mut reader)?.as_str() {
+        "PF" => 3,
+        "Pf" => 1,
+        magic => return Err(invalid_data(format!("Bad PFM magic number {magic:?}"))),
+    };
+    let width: usize = read_header_token(&mut reader)?
+        .parse()
+        .map_err(invalid_data)?;
+    let height: usize = read_header_token(&mut reader)?
+        .parse()
+        .map_err(invalid_data)?;
+    let scale: f32 = read_header_token(&mut reader)?
+        .parse()
+        .map_err(invalid_data)?;
+
+    // The sign of the scale gives the byte order of the samples.
+    let from_bytes = if scale < 0.0 {
+        f32::from_le_bytes
+    } else {
+        f32::from_be_bytes
+    };
+
+    // The header isn't trusted to preallocate the image.
+    sample_count(width, height, channels * 4)?;
+    let mut pixels: ImagePixels = vec![];
+    for _ in 0..height {
+        let row = read_bytes(&mut reader, width * channels * 4)?;
+        let samples: Vec<f64> = row
+            .chunks_exact(4)
+            .map(|b| from_bytes([b[0], b[1], b[2], b[3]]) as f64)
+            .collect();
+        pixels.push(
+            samples
+                .chunks_exact(channels)
+                .map(|s| match *s {
+                    [grey] => Color::new(grey, grey, grey),
+                    [red, green, blue] => Color::new(red, green, blue),
+                    _ => unreachable!("PFM images have 1 or 3 channels"),
+                })
+                .collect(),
+        );
+    }
+
+    pixels.reverse();
+    Ok(pixels)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{read, write};
+    use crate::color::Color;
+
+    #[test]
+    fn round_trip() {
+        let pixels = vec![
+            vec![Color::new(1.0, 0.5, 0.25), Color::new(100.0, 0.0, 3.0)],
+            vec![Color::new(0.0, 0.125, 7.5), Color::new(2.0, 4.0, 8.0)],
+        ];
+        let mut data = vec![];
+        write(&mut data, &pixels).unwrap();
+
+        assert!(data.starts_with(b"PF\n2 2\n-1.0\n"));
+        assert_eq!(pixels, read(data.as_slice()).unwrap());
+    }
+
+    #[test]
+    fn little_endian_color() {
+        let mut data = b"PF\n2 1\n-1.0\n".to_vec();
+        for sample in [1.0f32, 0.5, 0.25, 8.0, 0.0, -1.0] {
+            data.extend(sample.to_le_bytes());
+        }
+
+        let pixels = read(data.as_slice()).unwrap();
+        assert_eq!(
+            vec![vec![Color::new(1.0, 0.5, 0.25), Color::new(8.0, 0.0, -1.0)]],
+            pixels
+        );
+    }
+
+    #[test]
+    fn big_endian_greyscale_bottom_to_top() {
+        let mut data = b"Pf 1 2 1.0\n".to_vec();
+        for sample in [2.0f32, 0.125] {
+            data.extend(sample.to_be_bytes());
+        }
+
+        let pixels = read(data.as_slice()).unwrap();
+        assert_eq!(
+            vec![
+                vec![Color::new(0.125, 0.125, 0.125)],
+                vec![Color::new(2.0, 2.0, 2.0)]
+            ],
+            pixels
+        );
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(read(b"P6\n1 1\n255\n".as_slice()).is_err());
+        assert!(read(b"PF\n1 1\n-1.0\n\0\0".as_slice()).is_err());
+        assert!(read(b"PF\n4294967296 4294967296\n-1.0\n\0".as_slice()).is_err());
+        assert!(read(b"PF\n1000000000 1\n-1.0\n\0".as_slice()).is_err());
+    }
+}
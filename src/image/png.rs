@@ -0,0 +1,258 @@
+//! Writer for PNG images.
+use std::io::{self, Write};
+
+use super::zlib;
+use crate::tracer::ImagePixels;
+
+const SIGNATURE: [u8; 8] = [0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1A, b'\n'];
+
+/// The number of bits used to store each color channel.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BitDepth {
+    Eight,
+    Sixteen,
+}
+
+/// Write an RGB PNG image. Colors are gamma encoded, which is recorded in the
+/// image's `gAMA` chunk.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty or its scanlines
+/// differ in length.
+pub fn write(mut writer: impl Write, pixels: &ImagePixels, bit_depth: BitDepth) -> io::Result<()> {
+    let (width, height) = super::dimensions(pixels)?;
+    let bytes_per_pixel = match bit_depth {
+        BitDepth::Eight => 3,
+        BitDepth::Sixteen => 6,
+    };
+
+    let mut header = vec![];
+    header.extend((width as u32).to_be_bytes());
+    header.extend((height as u32).to_be_bytes());
+    header.push(match bit_depth {
+        BitDepth::Eight => 8,
+        BitDepth::Sixteen => 16,
+    });
+    // Truecolor, deflate compression, adaptive filtering and no interlacing.
+    header.extend([2, 0, 0, 0]);
+
+    let mut filtered = Vec::with_capacity(height * (1 + width * bytes_per_pixel));
+    let mut previous = vec![0u8; width * bytes_per_pixel];
+    for row in pixels {
+        let raw: Vec<u8> = row
+            .iter()
+            .flat_map(|color| match bit_depth {
+                BitDepth::Eight => color.to_rgb8().to_vec(),
+                BitDepth::Sixteen => color
+                    .to_rgb16()
+                    .iter()
+                    .flat_map(|c| c.to_be_bytes())
+                    .collect(),
+            })
+            .collect();
+        filter_scanline(&raw, &previous, bytes_per_pixel, &mut filtered);
+        previous = raw;
+    }
+
+    writer.write_all(&SIGNATURE)?;
+    write_chunk(&mut writer, b"IHDR", &header)?;
+    // Colors are encoded with gamma 2, stored as 1/2 * 100000.
+    write_chunk(&mut writer, b"gAMA", &50_000u32.to_be_bytes())?;
+    write_chunk(&mut writer, b"IDAT", &zlib::compress(&filtered))?;
+    write_chunk(&mut writer, b"IEND", &[])
+}
+
+fn write_chunk(writer: &mut impl Write, chunk_type: &[u8; 4], data: &[u8]) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(chunk_type)?;
+    writer.write_all(data)?;
+    let crc = zlib::crc32_update(zlib::crc32(chunk_type), data);
+    writer.write_all(&crc.to_be_bytes())
+}
+
+/// Filter a scanline with whichever filter type gives the smallest sum of absolute
+/// differences, and append the filter type and filtered bytes to `out`.
+fn filter_scanline(raw: &[u8], previous: &[u8], bpp: usize, out: &mut Vec<u8>) {
+    let left = |i: usize| if i >= bpp { raw[i - bpp] } else { 0 };
+    let up_left = |i: usize| if i >= bpp { previous[i - bpp] } else { 0 };
+
+    let candidates: [Vec<u8>; 5] = [
+        raw.to_vec(),
+        (0..raw.len())
+            .map(|i| raw[i].wrapping_sub(left(i)))
+            .collect(),
+        (0..raw.len())
+            .map(|i| raw[i].wrapping_sub(previous[i]))
+            .collect(),
+        (0..raw.len())
+            .map(|i| raw[i].wrapping_sub(((left(i) as u16 + previous[i] as u16) / 2) as u8))
+            .collect(),
+        (0..raw.len())
+            .map(|i| raw[i].wrapping_sub(paeth(left(i), previous[i], up_left(i))))
+            .collect(),
+    ];
+
+    let score = |filtered: &Vec<u8>| -> u64 {
+        filtered
+            .iter()
+            .map(|&b| (b as i8).unsigned_abs() as u64)
+            .sum()
+    };
+    let (filter_type, best) = candidates
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, filtered)| score(filtered))
+        .expect("There are filter candidates");
+
+    out.push(filter_type as u8);
+    out.extend(best);
+}
+
+fn paeth(a: u8, b: u8, c: u8) -> u8 {
+    let p = a as i16 + b as i16 - c as i16;
+    let (pa, pb, pc) = (
+        (p - a as i16).abs(),
+        (p - b as i16).abs(),
+        (p - c as i16).abs(),
+    );
+    if pa <= pb && pa <= pc {
+        a
+    } else if pb <= pc {
+        b
+    } else {
+        c
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, process};
+
+    use super::{paeth, write, BitDepth};
+    use crate::{
+        color::Color,
+        image::{
+            self,
+            zlib::{self, tests::decompress},
+            Format,
+        },
+        tracer::ImagePixels,
+    };
+
+    struct Chunk {
+        chunk_type: [u8; 4],
+        data: Vec<u8>,
+    }
+
+    fn read_chunks(png: &[u8]) -> Vec<Chunk> {
+        assert_eq!(super::SIGNATURE, png[..8]);
+        let mut chunks = vec![];
+        let mut pos = 8;
+        while pos < png.len() {
+            let length = u32::from_be_bytes(png[pos..pos + 4].try_into().unwrap()) as usize;
+            let chunk_type: [u8; 4] = png[pos + 4..pos + 8].try_into().unwrap();
+            let data = png[pos + 8..pos + 8 + length].to_vec();
+            let crc =
+                u32::from_be_bytes(png[pos + 8 + length..pos + 12 + length].try_into().unwrap());
+            assert_eq!(zlib::crc32(&png[pos + 4..pos + 8 + length]), crc);
+            chunks.push(Chunk { chunk_type, data });
+            pos += 12 + length;
+        }
+        chunks
+    }
+
+    /// Undo the PNG filters to recover the raw scanlines.
+    fn unfilter(data: &[u8], stride: usize, bpp: usize) -> Vec<Vec<u8>> {
+        let mut rows: Vec<Vec<u8>> = vec![];
+        for line in data.chunks(stride + 1) {
+            let previous = rows.last().cloned().unwrap_or(vec![0; stride]);
+            let mut row: Vec<u8> = vec![0; stride];
+            for i in 0..stride {
+                let a = if i >= bpp { row[i - bpp] } else { 0 };
+                let b = previous[i];
+                let c = if i >= bpp { previous[i - bpp] } else { 0 };
+                let predictor = match line[0] {
+                    0 => 0,
+                    1 => a,
+                    2 => b,
+                    3 => ((a as u16 + b as u16) / 2) as u8,
+                    4 => paeth(a, b, c),
+                    f => panic!("Bad filter type {f}"),
+                };
+                row[i] = line[1 + i].wrapping_add(predictor);
+            }
+            rows.push(row);
+        }
+        rows
+    }
+
+    fn test_image() -> ImagePixels {
+        (0..7)
+            .map(|row| {
+                (0..5)
+                    .map(|col| Color::new(row as f64 / 6.0, col as f64 / 4.0, 0.5))
+                    .collect()
+            })
+            .collect()
+    }
+
+    #[test]
+    fn eight_bit() {
+        let pixels = test_image();
+        let mut png = vec![];
+        write(&mut png, &pixels, BitDepth::Eight).unwrap();
+
+        let chunks = read_chunks(&png);
+        let types: Vec<_> = chunks.iter().map(|c| &c.chunk_type).collect();
+        assert_eq!(vec![b"IHDR", b"gAMA", b"IDAT", b"IEND"], types);
+        assert_eq!(vec![0, 0, 0, 5, 0, 0, 0, 7, 8, 2, 0, 0, 0], chunks[0].data);
+
+        let rows = unfilter(&decompress(&chunks[2].data), 15, 3);
+        for (row, expected) in rows.iter().zip(&pixels) {
+            let expected: Vec<u8> = expected.iter().flat_map(|c| c.to_rgb8()).collect();
+            assert_eq!(&expected, row);
+        }
+    }
+
+    #[test]
+    fn sixteen_bit() {
+        let pixels = test_image();
+        let mut png = vec![];
+        write(&mut png, &pixels, BitDepth::Sixteen).unwrap();
+
+        let chunks = read_chunks(&png);
+        assert_eq!(16, chunks[0].data[8]);
+
+        let rows = unfilter(&decompress(&chunks[2].data), 30, 6);
+        for (row, expected) in rows.iter().zip(&pixels) {
+            let expected: Vec<u8> = expected
+                .iter()
+                .flat_map(|c| c.to_rgb16())
+                .flat_map(u16::to_be_bytes)
+                .collect();
+            assert_eq!(&expected, row);
+        }
+    }
+
+    #[test]
+    fn write_file_with_bit_depth() {
+        let path = env::temp_dir().join(format!("ray-tracing-png-{}.png", process::id()));
+        image::write_as(&path, &test_image(), Format::Png(BitDepth::Sixteen)).unwrap();
+        let png = fs::read(&path);
+        fs::remove_file(&path).unwrap();
+
+        let chunks = read_chunks(&png.unwrap());
+        assert_eq!(16, chunks[0].data[8]);
+    }
+
+    #[test]
+    fn empty_image() {
+        assert!(write(vec![], &vec![], BitDepth::Eight).is_err());
+        assert!(write(
+            vec![],
+            &vec![vec![Color::default()], vec![]],
+            BitDepth::Eight
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,184 @@
+//! Reader and writer for Portable PixMap (`.ppm`) images, in both the ASCII (`P3`)
+//! and binary (`P6`) variants.
+use std::io::{self, BufRead, Write};
+
+use super::{invalid_data, read_bytes, read_header_token, sample_count};
+use crate::{color::Color, tracer::ImagePixels};
+
+/// How the samples of a PPM image are stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    /// Samples are written as decimal text (`P3`).
+    Ascii,
+    /// Samples are written as bytes (`P6`).
+    Binary,
+}
+
+/// Write a PPM image with 8 bits per channel. Colors are gamma encoded.
+///
+/// # Errors
+/// Returns an error if writing fails, or if the image is empty or its scanlines
+/// differ in length.
+pub fn write(mut writer: impl Write, pixels: &ImagePixels, encoding: Encoding) -> io::Result<()> {
+    let (width, height) = super::dimensions(pixels)?;
+    let magic = match encoding {
+        Encoding::Ascii => "P3",
+        Encoding::Binary => "P6",
+    };
+    write!(writer, "{magic}\n{width} {height}\n255\n")?;
+
+    for color in pixels.iter().flatten() {
+        let rgb = color.to_rgb8();
+        match encoding {
+            Encoding::Ascii => writeln!(writer, "{} {} {}", rgb[0], rgb[1], rgb[2])?,
+            Encoding::Binary => writer.write_all(&rgb)?,
+        }
+    }
+    Ok(())
+}
+
+/// Read an ASCII (`P3`) or binary (`P6`) PPM image. Samples are decoded from gamma
+/// 2 to linear colors.
+///
+/// # Errors
+/// Returns an error if reading fails, or if the data is not a valid PPM image.
+pub fn read(mut reader: impl BufRead) -> io::Result<ImagePixels> {
+    let encoding = match read_header_token(&mut reader)?.as_str() {
+        "P3" => Encoding::Ascii,
+        "P6" => Encoding::Binary,
+        magic => return Err(invalid_data(format!("Bad PPM magic number {magic:?}"))),
+    };
+    let width = read_number(&mut reader)?;
+    let height = read_number(&mut reader)?;
+    let max_value = read_number(&mut reader)?;
+    if !(1..=u16::MAX as usize).contains(&max_value) {
+        return Err(invalid_data(format!("Bad PPM maximum value {max_value}")));
+    }
+
+    // The header isn't trusted to preallocate the samples.
+    let count = sample_count(width, height, 3)?;
+    let mut samples = vec![];
+    match encoding {
+        Encoding::Ascii => {
+            for _ in 0..count {
+                samples.push(read_number(&mut reader)?);
+            }
+        }
+        Encoding::Binary => {
+            let bytes_per_sample = if max_value < 256 { 1 } else { 2 };
+            let len = sample_count(count, bytes_per_sample, 1)?;
+            let data = read_bytes(&mut reader, len)?;
+            samples.extend(data.chunks_exact(bytes_per_sample).map(|b| match *b {
+                [v] => v as usize,
+                [hi, lo] => (hi as usize) << 8 | lo as usize,
+                _ => unreachable!("Samples are 1 or 2 bytes"),
+            }));
+        }
+    }
+
+    if samples.iter().any(|&s| s > max_value) {
+        return Err(invalid_data("PPM sample exceeds maximum value"));
+    }
+
+    let max_value = max_value as f64;
+    let pixels = samples
+        .chunks_exact(3)
+        .map(|s| {
+            Color::from_encoded(
+                s[0] as f64 / max_value,
+                s[1] as f64 / max_value,
+                s[2] as f64 / max_value,
+            )
+        })
+        .collect::<Vec<_>>()
+        .chunks(width.max(1))
+        .map(<[Color]>::to_vec)
+        .collect();
+    Ok(pixels)
+}
+
+fn read_number(reader: &mut impl BufRead) -> io::Result<usize> {
+    read_header_token(reader)?.parse().map_err(invalid_data)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{fs::File, io::BufReader, path::PathBuf};
+
+    use super::{read, write, Encoding};
+    use crate::{color::Color, tracer::ImagePixels};
+
+    fn test_image() -> ImagePixels {
+        (0..3)
+            .map(|row| {
+                (0..4)
+                    .map(|col| Color::new(row as f64 / 2.0, col as f64 / 3.0, 0.3))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn quantized(pixels: &ImagePixels) -> Vec<Vec<[u8; 3]>> {
+        pixels
+            .iter()
+            .map(|row| row.iter().map(Color::to_rgb8).collect())
+            .collect()
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let pixels = test_image();
+        let mut data = vec![];
+        write(&mut data, &pixels, Encoding::Ascii).unwrap();
+
+        assert!(data.starts_with(b"P3\n4 3\n255\n0 0 140\n0 147 140\n"));
+        assert_eq!(
+            quantized(&pixels),
+            quantized(&read(data.as_slice()).unwrap())
+        );
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let pixels = test_image();
+        let mut data = vec![];
+        write(&mut data, &pixels, Encoding::Binary).unwrap();
+
+        assert!(data.starts_with(b"P6\n4 3\n255\n"));
+        assert_eq!(11 + 4 * 3 * 3, data.len());
+        assert_eq!(
+            quantized(&pixels),
+            quantized(&read(data.as_slice()).unwrap())
+        );
+    }
+
+    #[test]
+    fn comments_and_16_bit_samples() {
+        let data = b"P6 # binary\n1 1\n# maximum value\n65535\n\xff\xff\x80\x00\x00\x00";
+        let pixels = read(data.as_slice()).unwrap();
+        let expected = Color::from_encoded(1.0, 32768.0 / 65535.0, 0.0);
+        assert_eq!(vec![vec![expected]], pixels);
+    }
+
+    #[test]
+    fn invalid_data() {
+        assert!(read(b"P5\n1 1\n255\n\0".as_slice()).is_err());
+        assert!(read(b"P3\n1 1\n255\n0 0".as_slice()).is_err());
+        assert!(read(b"P3\n1 1\n100\n0 0 101\n".as_slice()).is_err());
+        assert!(read(b"P6\n2 1\n255\n\0\0\0".as_slice()).is_err());
+        assert!(read(b"P6\n4294967296 4294967296\n255\n\0".as_slice()).is_err());
+        assert!(read(b"P6\n1000000 1000000\n255\n\0".as_slice()).is_err());
+    }
+
+    #[test]
+    fn read_reference_image() {
+        let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("images/01_hello.ppm");
+        let pixels = read(BufReader::new(File::open(path).unwrap())).unwrap();
+
+        assert_eq!(256, pixels.len());
+        assert!(pixels.iter().all(|row| row.len() == 256));
+        // The image is a red-green gradient, with blue fixed at 63.
+        assert_eq!([0, 255, 63], pixels[0][0].to_rgb8());
+        assert_eq!([255, 0, 63], pixels[255][255].to_rgb8());
+    }
+}
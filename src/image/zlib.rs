@@ -0,0 +1,328 @@
+//! A small zlib (RFC 1950) encoder, compressing with deflate (RFC 1951) using
+//! LZ77 and the fixed Huffman codes.
+
+/// Size of the deflate sliding window.
+const WINDOW_SIZE: usize = 32 * 1024;
+const MIN_MATCH: usize = 3;
+const MAX_MATCH: usize = 258;
+
+/// How many earlier positions with the same hash are searched for a match. Larger
+/// values compress better, but more slowly.
+const MAX_CHAIN: usize = 64;
+
+const HASH_BITS: u32 = 15;
+
+/// Base lengths for length codes 257 to 285, and their extra bits.
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+
+/// Base distances for distance codes 0 to 29, and their extra bits.
+const DIST_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DIST_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+
+/// Compress data into a zlib stream.
+pub fn compress(data: &[u8]) -> Vec<u8> {
+    // Deflate with a 32K window, and no preset dictionary.
+    let mut out = vec![0x78, 0x01];
+    out.extend(deflate(data));
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+/// Compute the CRC-32 checksum used by PNG and zlib's gzip format.
+pub fn crc32(data: &[u8]) -> u32 {
+    crc32_update(0, data)
+}
+
+/// Continue a CRC-32 checksum with more data.
+pub fn crc32_update(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            crc = if crc & 1 != 0 {
+                0xEDB8_8320 ^ (crc >> 1)
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+/// Compute the Adler-32 checksum of data.
+pub fn adler32(data: &[u8]) -> u32 {
+    const MOD: u32 = 65521;
+    let (mut a, mut b) = (1u32, 0u32);
+    // 5552 is the most bytes that can be summed before `b` might overflow.
+    for chunk in data.chunks(5552) {
+        for &byte in chunk {
+            a += byte as u32;
+            b += a;
+        }
+        a %= MOD;
+        b %= MOD;
+    }
+    (b << 16) | a
+}
+
+/// Writes bits to a byte vector, least significant bit first.
+struct BitWriter {
+    out: Vec<u8>,
+    buffer: u64,
+    bit_count: u32,
+}
+
+impl BitWriter {
+    fn new() -> Self {
+        Self {
+            out: vec![],
+            buffer: 0,
+            bit_count: 0,
+        }
+    }
+
+    fn write_bits(&mut self, bits: u32, count: u32) {
+        self.buffer |= (bits as u64) << self.bit_count;
+        self.bit_count += count;
+        while self.bit_count >= 8 {
+            self.out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bit_count -= 8;
+        }
+    }
+
+    /// Write a Huffman code, which is packed starting from its most significant bit.
+    fn write_code(&mut self, code: u32, length: u32) {
+        let reversed = code.reverse_bits() >> (32 - length);
+        self.write_bits(reversed, length);
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bit_count > 0 {
+            self.out.push(self.buffer as u8);
+        }
+        self.out
+    }
+}
+
+/// Write a literal or length symbol with the fixed Huffman code.
+fn write_literal_length(writer: &mut BitWriter, symbol: u16) {
+    let symbol = symbol as u32;
+    match symbol {
+        0..=143 => writer.write_code(0x30 + symbol, 8),
+        144..=255 => writer.write_code(0x190 + symbol - 144, 9),
+        256..=279 => writer.write_code(symbol - 256, 7),
+        _ => writer.write_code(0xC0 + symbol - 280, 8),
+    }
+}
+
+fn write_match(writer: &mut BitWriter, length: usize, distance: usize) {
+    let code = LENGTH_BASE.partition_point(|&base| base as usize <= length) - 1;
+    write_literal_length(writer, 257 + code as u16);
+    writer.write_bits(
+        (length - LENGTH_BASE[code] as usize) as u32,
+        LENGTH_EXTRA[code] as u32,
+    );
+
+    let code = DIST_BASE.partition_point(|&base| base as usize <= distance) - 1;
+    writer.write_code(code as u32, 5);
+    writer.write_bits(
+        (distance - DIST_BASE[code] as usize) as u32,
+        DIST_EXTRA[code] as u32,
+    );
+}
+
+fn hash(data: &[u8]) -> usize {
+    let v = (data[0] as u32) << 16 | (data[1] as u32) << 8 | data[2] as u32;
+    (v.wrapping_mul(0x9E37_79B1) >> (32 - HASH_BITS)) as usize
+}
+
+/// Compress data into a single deflate block using the fixed Huffman codes.
+fn deflate(data: &[u8]) -> Vec<u8> {
+    let mut writer = BitWriter::new();
+    // BFINAL = 1, BTYPE = 01 (fixed Huffman codes).
+    writer.write_bits(0b011, 3);
+
+    // `head` gives the most recent position with a given hash, and `prev` links each
+    // position to the previous one with the same hash.
+    let mut head = vec![usize::MAX; 1 << HASH_BITS];
+    let mut prev = vec![usize::MAX; data.len()];
+    let insert = |pos: usize, head: &mut [usize], prev: &mut [usize]| {
+        if pos + MIN_MATCH <= data.len() {
+            let h = hash(&data[pos..]);
+            prev[pos] = head[h];
+            head[h] = pos;
+        }
+    };
+
+    let mut pos = 0;
+    while pos < data.len() {
+        let (length, distance) = longest_match(data, pos, &head, &prev);
+        if length >= MIN_MATCH {
+            write_match(&mut writer, length, distance);
+            for p in pos..pos + length {
+                insert(p, &mut head, &mut prev);
+            }
+            pos += length;
+        } else {
+            write_literal_length(&mut writer, data[pos] as u16);
+            insert(pos, &mut head, &mut prev);
+            pos += 1;
+        }
+    }
+
+    // End of block.
+    write_literal_length(&mut writer, 256);
+    writer.finish()
+}
+
+/// Find the longest earlier match for the data at `pos`, returning its length and
+/// distance.
+fn longest_match(data: &[u8], pos: usize, head: &[usize], prev: &[usize]) -> (usize, usize) {
+    if pos + MIN_MATCH > data.len() {
+        return (0, 0);
+    }
+
+    let max_length = MAX_MATCH.min(data.len() - pos);
+    let (mut best_length, mut best_distance) = (0, 0);
+    let mut candidate = head[hash(&data[pos..])];
+    for _ in 0..MAX_CHAIN {
+        if candidate == usize::MAX || pos - candidate > WINDOW_SIZE {
+            break;
+        }
+
+        let length = data[candidate..]
+            .iter()
+            .zip(&data[pos..pos + max_length])
+            .take_while(|(a, b)| a == b)
+            .count();
+        if length > best_length {
+            (best_length, best_distance) = (length, pos - candidate);
+            if length == max_length {
+                break;
+            }
+        }
+        candidate = prev[candidate];
+    }
+
+    (best_length, best_distance)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::{adler32, compress, crc32, DIST_BASE, DIST_EXTRA, LENGTH_BASE, LENGTH_EXTRA};
+    use crate::utils::rand::RTRng;
+
+    /// Reads bits from a byte slice, least significant bit first.
+    struct BitReader<'a> {
+        data: &'a [u8],
+        pos: usize,
+    }
+
+    impl BitReader<'_> {
+        fn bit(&mut self) -> u32 {
+            let bit = (self.data[self.pos / 8] >> (self.pos % 8)) & 1;
+            self.pos += 1;
+            bit as u32
+        }
+
+        fn bits(&mut self, count: u32) -> u32 {
+            (0..count).fold(0, |acc, i| acc | self.bit() << i)
+        }
+
+        /// Read a Huffman code of `length` bits, most significant bit first.
+        fn code(&mut self, length: u32) -> u32 {
+            (0..length).fold(0, |acc, _| acc << 1 | self.bit())
+        }
+
+        fn literal_length(&mut self) -> u32 {
+            let code = self.code(7);
+            if code <= 0x17 {
+                return code + 256;
+            }
+            let code = code << 1 | self.bit();
+            match code {
+                0x30..=0xBF => code - 0x30,
+                0xC0..=0xC7 => code - 0xC0 + 280,
+                _ => (code << 1 | self.bit()) - 0x190 + 144,
+            }
+        }
+    }
+
+    /// Decompress a zlib stream containing fixed Huffman blocks.
+    pub(crate) fn decompress(data: &[u8]) -> Vec<u8> {
+        assert_eq!(0, (data[0] as u32 * 256 + data[1] as u32) % 31);
+        let mut reader = BitReader { data, pos: 16 };
+        let mut out: Vec<u8> = vec![];
+        loop {
+            let is_final = reader.bits(1) == 1;
+            assert_eq!(1, reader.bits(2), "Expected a fixed Huffman block");
+            loop {
+                let symbol = reader.literal_length();
+                match symbol {
+                    0..=255 => out.push(symbol as u8),
+                    256 => break,
+                    _ => {
+                        let code = (symbol - 257) as usize;
+                        let length = LENGTH_BASE[code] as usize
+                            + reader.bits(LENGTH_EXTRA[code] as u32) as usize;
+                        let code = reader.code(5) as usize;
+                        let distance = DIST_BASE[code] as usize
+                            + reader.bits(DIST_EXTRA[code] as u32) as usize;
+                        for _ in 0..length {
+                            out.push(out[out.len() - distance]);
+                        }
+                    }
+                }
+            }
+            if is_final {
+                break;
+            }
+        }
+
+        let checksum_start = reader.pos.div_ceil(8);
+        let checksum = &data[checksum_start..checksum_start + 4];
+        assert_eq!(adler32(&out).to_be_bytes(), checksum);
+        out
+    }
+
+    #[test]
+    fn checksums() {
+        assert_eq!(0xCBF4_3926, crc32(b"123456789"));
+        assert_eq!(0, crc32(b""));
+        assert_eq!(0x11E6_0398, adler32(b"Wikipedia"));
+        assert_eq!(1, adler32(b""));
+    }
+
+    #[test]
+    fn round_trip() {
+        let rng = RTRng::new();
+        let random: Vec<u8> = (0..5000)
+            .map(|_| (rng.random_f64() * 256.0) as u8)
+            .collect();
+        let repetitive: Vec<u8> = b"abcabcabcabd".repeat(1000);
+        let runs: Vec<u8> = (0..100_000).map(|i| (i / 1000) as u8).collect();
+
+        for data in [vec![], vec![42], random, repetitive, runs] {
+            assert_eq!(data, decompress(&compress(&data)));
+        }
+    }
+
+    #[test]
+    fn compresses_repetitive_data() {
+        let data = vec![7u8; 100_000];
+        assert!(compress(&data).len() < 1000);
+    }
+}
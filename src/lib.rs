@@ -1,8 +1,12 @@
 //! Simple ray-tracing library.
 
+pub mod background;
 pub mod camera;
 pub mod color;
 pub mod geometry;
+pub mod image;
 pub mod material;
+pub mod scene;
+pub mod texture;
 pub mod tracer;
 pub mod utils;
@@ -1,74 +1,206 @@
-use std::{io, rc::Rc};
+use std::{
+    ffi::OsString,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
 
+use clap::{error::ErrorKind, CommandFactory, Parser};
 use log::{error, info};
 
 use ray_tracing_1::{
+    background::Gradient,
     camera::{Camera, Config as CameraConfig},
-    geometry::{sphere::Sphere, vec3::Vec3},
+    geometry::{plane::Plane, sphere::Sphere, vec3::Vec3},
+    image::{exr, Format},
     material::{Dielectric, Lambertian, Material, Metal},
+    scene::{self, Scene},
     tracer::{self, World},
-    utils::{correct_gamma, rand::RTRng},
+    utils::rand::RTRng,
 };
 
-type RcMaterial = Rc<dyn Material>;
+use cli::Args;
+
+mod cli;
 
-const ASPECT_RATIO: f64 = 3.0 / 2.0;
+type ArcMaterial = Arc<dyn Material>;
 
 fn main() {
-    env_logger::init();
-    if let Err(e) = generate_ppm() {
+    let args = Args::parse();
+
+    let mut logger = env_logger::Builder::from_default_env();
+    if let Some(level) = args.log_level() {
+        logger.filter_level(level);
+    }
+    logger.init();
+
+    // Some arguments can only be checked in combination. Report these the same way
+    // as other invalid arguments.
+    let format = args.format().unwrap_or_else(|e| usage_error(e));
+
+    let scene = match &args.scene {
+        Some(path) => {
+            info!("Loading scene from {}.", path.display());
+            scene::load(path).unwrap_or_else(|e| {
+                error!("Error loading scene: {}", e);
+                std::process::exit(1);
+            })
+        }
+        None => Scene {
+            image: default_image_config(),
+            camera: default_camera(),
+            world: random_scene(&RTRng::with_seed(args.seed)),
+        },
+    };
+    let camera_config = args
+        .camera_config(scene.camera)
+        .unwrap_or_else(|e| usage_error(e));
+    let image_config = args.image_config(scene.image);
+
+    if let Some(path) = &args.save_scene {
+        let scene = Scene {
+            image: image_config,
+            camera: camera_config,
+            world: scene.world,
+        };
+        if let Err(e) = scene::save(path, &scene) {
+            error!("Error saving scene: {}", e);
+            std::process::exit(1);
+        }
+        info!("Saved scene to {}.", path.display());
+        return;
+    }
+
+    if let Err(e) = generate_image(&args, format, image_config, camera_config, scene.world) {
         error!("Error generating image: {}", e);
         std::process::exit(1);
     }
 }
 
-fn generate_ppm() -> io::Result<()> {
-    let image_config = tracer::ImageConfig {
+/// Exit after reporting a problem with the command-line arguments.
+fn usage_error(message: String) -> ! {
+    Args::command()
+        .error(ErrorKind::ValueValidation, message)
+        .exit()
+}
+
+/// The image settings used for the random scene, when not overridden by the
+/// command-line arguments.
+fn default_image_config() -> tracer::ImageConfig {
+    tracer::ImageConfig {
         width: 1200,
         height: 800,
         samples_per_pixel: 500,
         ray_bounce_limit: 50,
-    };
+        threads: 0,
+        seed: 0,
+        background: Arc::new(Gradient::sky()),
+    }
+}
 
-    assert_eq!(
-        image_config.width as f64 / image_config.height as f64,
-        ASPECT_RATIO,
-        "Dimensions don't match aspect ratio!"
-    );
-
-    let world = random_scene(&RTRng::new());
-
-    let look_from = Vec3::from((13, 2, 3));
-    let look_at = Vec3::from((0, 0, 0));
-    let vup = Vec3::from((0, 1, 0));
-    let camera_config = CameraConfig {
-        look_from,
-        look_at,
-        vup,
+/// The camera used for the random scene, when not overridden by the command-line
+/// arguments.
+fn default_camera() -> CameraConfig {
+    CameraConfig {
+        look_from: Vec3::from((13, 2, 3)),
+        look_at: Vec3::from((0, 0, 0)),
+        vup: Vec3::from((0, 1, 0)),
         vfov_degrees: 20.0,
-        aspect_ratio: ASPECT_RATIO,
+        aspect_ratio: 3.0 / 2.0,
         aperture_diameter: 0.1,
         focus_dist: 10.0,
-    };
-    let camera = Camera::new(camera_config);
+        shutter_open: 0.0,
+        shutter_close: 0.0,
+    }
+}
 
-    println!("P3");
-    println!("{} {}", image_config.width, image_config.height);
-    println!("255");
+fn generate_image(
+    args: &Args,
+    format: Format,
+    image_config: tracer::ImageConfig,
+    camera_config: CameraConfig,
+    world: World,
+) -> io::Result<()> {
+    let camera = Camera::new(camera_config);
+    let Some(path) = &args.output else {
+        return write_image(
+            &mut io::stdout().lock(),
+            format,
+            image_config,
+            camera,
+            world,
+        );
+    };
 
-    info!("Rendering world...");
-    let mut scanlines = tracer::render(image_config, camera, world);
+    // Render into a temporary file next to the output, and only replace the output once
+    // the image is complete, so that a failed render leaves an existing image alone.
+    // The temporary file is created before rendering, so that we fail early if the
+    // output can't be written.
+    if fs::metadata(path)
+        .is_ok_and(|metadata| metadata.is_dir() || metadata.permissions().readonly())
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::PermissionDenied,
+            format!("{} can't be overwritten", path.display()),
+        ));
+    }
+    let temp_path = temp_path(path)?;
+    let result = File::create(&temp_path).and_then(|file| {
+        write_image(
+            &mut BufWriter::new(file),
+            format,
+            image_config,
+            camera,
+            world,
+        )?;
+        fs::rename(&temp_path, path)
+    });
+    if result.is_err() {
+        // The temporary file may not exist, if creating it failed.
+        let _ = fs::remove_file(&temp_path);
+    }
+    result
+}
 
-    info!("Correcting gamma.");
-    correct_gamma(&mut scanlines);
+/// The path of a hidden file in the same directory as `path`, which can be renamed to
+/// `path` without copying.
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let name = path.file_name().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{} isn't a file name", path.display()),
+        )
+    })?;
+    let mut temp_name = OsString::from(".");
+    temp_name.push(name);
+    temp_name.push(format!(".{}.tmp", std::process::id()));
+    Ok(path.with_file_name(temp_name))
+}
 
-    info!("Writing scanlines.");
-    for pixel_color in scanlines.iter().flatten() {
-        println!(
-            "{} {} {}",
-            pixel_color.red, pixel_color.green, pixel_color.blue
-        );
+/// Render the world and write the image to `writer`.
+fn write_image(
+    writer: &mut impl Write,
+    format: Format,
+    image_config: tracer::ImageConfig,
+    camera: Camera,
+    world: World,
+) -> io::Result<()> {
+    info!("Rendering world...");
+    match format {
+        Format::Exr(compression) => {
+            // EXR images can hold the AOVs alongside the rendered colors.
+            let (scanlines, aovs) = tracer::render_with_aovs(image_config, camera, world);
+            info!("Writing image and AOVs.");
+            exr::write_with_aovs(&mut *writer, &scanlines, &aovs, compression)?;
+        }
+        format => {
+            let scanlines = tracer::render(image_config, camera, world);
+            info!("Writing image.");
+            format.write(&mut *writer, &scanlines)?;
+        }
     }
+    writer.flush()?;
 
     info!("Done!");
     Ok(())
@@ -77,13 +209,13 @@ fn generate_ppm() -> io::Result<()> {
 pub fn random_scene(rng: &RTRng) -> World {
     let mut world: World = vec![];
 
-    let material_ground = Rc::new(Lambertian::new(0.5, 0.5, 0.5));
-    let ground_sphere = Rc::new(Sphere::new(
-        (0, -1000, 0).into(),
-        1000,
-        Rc::clone(&material_ground) as RcMaterial,
+    let material_ground = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+    let ground = Arc::new(Plane::new(
+        Vec3::new(0, 0, 0),
+        Vec3::new(0, 1, 0),
+        Arc::clone(&material_ground) as ArcMaterial,
     ));
-    world.push(ground_sphere);
+    world.push(ground);
 
     for i in -11..11 {
         for j in -11..11 {
@@ -98,31 +230,31 @@ pub fn random_scene(rng: &RTRng) -> World {
                 if choose_mat < 0.8 {
                     // diffuse
                     let albedo = rng.random_vec3() * rng.random_vec3();
-                    let material = Rc::new(Lambertian::new(albedo.x(), albedo.y(), albedo.z()));
-                    world.push(Rc::new(Sphere::new(center, 0.2, material)));
+                    let material = Arc::new(Lambertian::new(albedo.x(), albedo.y(), albedo.z()));
+                    world.push(Arc::new(Sphere::new(center, 0.2, material)));
                 } else if choose_mat < 0.95 {
                     // metal
                     let albedo = rng.random_vec3_range(0.5..1.0);
                     let fuzz = rng.random_f64_range(0.0..0.5);
-                    let material = Rc::new(Metal::new((albedo.x(), albedo.y(), albedo.z()), fuzz));
-                    world.push(Rc::new(Sphere::new(center, 0.2, material)));
+                    let material = Arc::new(Metal::new((albedo.x(), albedo.y(), albedo.z()), fuzz));
+                    world.push(Arc::new(Sphere::new(center, 0.2, material)));
                 } else {
                     // glass
-                    let material = Rc::new(Dielectric::new(1.5));
-                    world.push(Rc::new(Sphere::new(center, 0.2, material)));
+                    let material = Arc::new(Dielectric::new(1.5));
+                    world.push(Arc::new(Sphere::new(center, 0.2, material)));
                 }
             }
         }
     }
 
-    let material1 = Rc::new(Dielectric::new(1.5));
-    world.push(Rc::new(Sphere::new((0, 1, 0).into(), 1.0, material1)));
+    let material1 = Arc::new(Dielectric::new(1.5));
+    world.push(Arc::new(Sphere::new((0, 1, 0).into(), 1.0, material1)));
 
-    let material2 = Rc::new(Lambertian::new(0.4, 0.2, 0.1));
-    world.push(Rc::new(Sphere::new((-4, 1, 0).into(), 1.0, material2)));
+    let material2 = Arc::new(Lambertian::new(0.4, 0.2, 0.1));
+    world.push(Arc::new(Sphere::new((-4, 1, 0).into(), 1.0, material2)));
 
-    let material3 = Rc::new(Metal::new((0.7, 0.6, 0.5), 0.0));
-    world.push(Rc::new(Sphere::new((4, 1, 0).into(), 1.0, material3)));
+    let material3 = Arc::new(Metal::new((0.7, 0.6, 0.5), 0.0));
+    world.push(Arc::new(Sphere::new((4, 1, 0).into(), 1.0, material3)));
 
     world
 }
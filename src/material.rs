@@ -1,45 +1,85 @@
-use std::fmt::Debug;
+use std::{f64::consts::PI, fmt::Debug, io, sync::Arc};
 
 use crate::{
-    color::Color,
-    geometry::{hittable::HitRecord, ray::Ray},
+    color::{self, Color},
+    geometry::{frame::Frame, hittable::HitRecord, ray::Ray, vec3::Vec3},
+    scene::description::{self, MaterialDescription},
+    texture::{SolidColor, Texture},
     utils::rand::RTRng,
 };
 
-pub trait Material: Debug {
-    /// Apply the properties of the material to attenuate the color of a reflected ray.
-    fn attenuate(&self, color: Color) -> Color;
+/// How light interacts with a surface.
+pub trait Material: Debug + Send + Sync {
+    /// Apply the properties of the material at a hit to attenuate the color of a
+    /// reflected ray.
+    fn attenuate(&self, hit_record: &HitRecord, color: Color) -> Color;
 
     /// Compute how a ray reflects off the material. Return `None` when the ray is absorbed
     /// rather than reflected.
     fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, rng: &RTRng) -> Option<Ray>;
+
+    /// The light emitted by the material at the point where it was hit. Materials
+    /// that are not light sources emit nothing.
+    fn emitted(&self, _hit_record: &HitRecord) -> Color {
+        color::BLACK
+    }
+
+    /// The probability density, with respect to solid angle, that [`Material::scatter`]
+    /// scatters a ray in `direction`. This lets the tracer sample light sources
+    /// directly, and weigh those samples against scattered rays.
+    ///
+    /// Materials returning `Some` must scatter in proportion to their BRDF times
+    /// the cosine of the scattered angle, so that `attenuate(hit_record, color) * pdf` is the
+    /// light reflected from `color` arriving from `direction`. Materials that
+    /// scatter into a single direction, such as mirrors, return `None`.
+    fn scattering_pdf(&self, _hit_record: &HitRecord, _direction: Vec3) -> Option<f64> {
+        None
+    }
+
+    /// Describe the material for saving a scene. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Err(description::invalid_input(
+            "the scene contains a material that can't be saved",
+        ))
+    }
+}
+
+/// The color of a texture at a hit.
+fn texture_value(texture: &dyn Texture, hit_record: &HitRecord) -> Color {
+    let (u, v) = hit_record.uv.unwrap_or((0.0, 0.0));
+    texture.value(u, v, hit_record.point)
 }
 
 #[derive(Debug)]
 pub struct Lambertian {
-    /// The reflection coefficient broken by color (red, green, blue).
-    albedo: (f64, f64, f64),
+    /// The reflection coefficient broken by color, which may vary over the surface.
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
     pub fn new(albedo_red: f64, albedo_green: f64, albedo_blue: f64) -> Self {
-        Self {
-            albedo: (albedo_red, albedo_green, albedo_blue),
-        }
+        let albedo = Color::new(albedo_red, albedo_green, albedo_blue);
+        Self::textured(Arc::new(SolidColor::new(albedo)))
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>) -> Self {
+        Self { albedo }
+    }
+
+    pub fn albedo(&self) -> &Arc<dyn Texture> {
+        &self.albedo
     }
 }
 
 impl Material for Lambertian {
-    fn attenuate(&self, color: Color) -> Color {
-        let (red_att, green_att, blue_att) = self.albedo;
-        Color {
-            red: (color.red as f64 * red_att) as u8,
-            green: (color.green as f64 * green_att) as u8,
-            blue: (color.blue as f64 * blue_att) as u8,
-        }
+    /// The albedo is multiplied by the color of the vertices of meshes that have them.
+    fn attenuate(&self, hit_record: &HitRecord, color: Color) -> Color {
+        let albedo = texture_value(self.albedo.as_ref(), hit_record);
+        color * hit_record.vertex_color.map_or(albedo, |c| albedo * c)
     }
 
-    fn scatter(&self, _ray_in: &Ray, hit_record: &HitRecord, rng: &RTRng) -> Option<Ray> {
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, rng: &RTRng) -> Option<Ray> {
         let mut scatter_dir = hit_record.normal + rng.random_unit_vector();
         if scatter_dir.near_zero() {
             scatter_dir = hit_record.normal;
@@ -47,14 +87,26 @@ impl Material for Lambertian {
 
         // Lambertian diffusion always reflects the incident ray. `None` is never
         // returned.
-        Some(Ray::new(hit_record.point, scatter_dir))
+        Some(Ray::new(hit_record.point, scatter_dir).with_time(ray_in.time()))
+    }
+
+    /// Scattered rays have a cosine distribution about the normal.
+    fn scattering_pdf(&self, hit_record: &HitRecord, direction: Vec3) -> Option<f64> {
+        let cosine = hit_record.normal.dot(direction.normalized());
+        Some(cosine.max(0.0) / PI)
+    }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::Lambertian {
+            albedo: self.albedo.describe()?,
+        })
     }
 }
 
 #[derive(Debug)]
 pub struct Metal {
-    /// The reflection coefficient broken by colors (red, green, blue).
-    albedo_rgb: (f64, f64, f64),
+    /// The reflection coefficient broken by color, which may vary over the surface.
+    albedo: Arc<dyn Texture>,
 
     /// Fuzziness factor for rays reflected off the metal. A value of `0.0` gives
     /// no perturbation (angle of incidence equal to angle or reflection), and larger
@@ -65,21 +117,28 @@ pub struct Metal {
 
 impl Metal {
     pub fn new(albedo_rgb: (f64, f64, f64), fuzz: f64) -> Self {
+        Self::textured(Arc::new(SolidColor::new(Color::from(albedo_rgb))), fuzz)
+    }
+
+    pub fn textured(albedo: Arc<dyn Texture>, fuzz: f64) -> Self {
         Self {
-            albedo_rgb,
+            albedo,
             fuzz: fuzz.min(1.0),
         }
     }
+
+    pub fn albedo(&self) -> &Arc<dyn Texture> {
+        &self.albedo
+    }
+
+    pub fn fuzz(&self) -> f64 {
+        self.fuzz
+    }
 }
 
 impl Material for Metal {
-    fn attenuate(&self, color: Color) -> Color {
-        let (red_att, green_att, blue_att) = self.albedo_rgb;
-        Color {
-            red: (color.red as f64 * red_att) as u8,
-            green: (color.green as f64 * green_att) as u8,
-            blue: (color.blue as f64 * blue_att) as u8,
-        }
+    fn attenuate(&self, hit_record: &HitRecord, color: Color) -> Color {
+        color * texture_value(self.albedo.as_ref(), hit_record)
     }
 
     /// Metal simply scatters rays by reflection.
@@ -88,7 +147,8 @@ impl Material for Metal {
         let scattered = Ray::new(
             hit_record.point,
             reflected + self.fuzz * rng.random_in_unit_sphere(),
-        );
+        )
+        .with_time(ray_in.time());
 
         if scattered.direction().dot(hit_record.normal) > 0.0 {
             Some(scattered)
@@ -96,6 +156,13 @@ impl Material for Metal {
             None
         }
     }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::Metal {
+            albedo: self.albedo.describe()?,
+            fuzz: self.fuzz,
+        })
+    }
 }
 
 #[derive(Debug)]
@@ -108,6 +175,10 @@ impl Dielectric {
         Self { refractive_index }
     }
 
+    pub fn refractive_index(&self) -> f64 {
+        self.refractive_index
+    }
+
     fn reflectance(cosine: f64, ref_idx: f64) -> f64 {
         // Use Schlick's approximation for reflectance.
         let r0 = (1.0 - ref_idx) / (1.0 + ref_idx);
@@ -138,10 +209,216 @@ impl Material for Dielectric {
                 .refract(hit_record.normal, refraction_ratio)
         };
 
-        Some(Ray::new(hit_record.point, direction))
+        Some(Ray::new(hit_record.point, direction).with_time(ray_in.time()))
     }
 
-    fn attenuate(&self, color: Color) -> Color {
+    fn attenuate(&self, _hit_record: &HitRecord, color: Color) -> Color {
         color
     }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::Dielectric {
+            refractive_index: self.refractive_index,
+        })
+    }
+}
+
+/// A light source that emits light equally in all directions, and absorbs all
+/// light that hits it.
+#[derive(Debug)]
+pub struct DiffuseLight {
+    color: Color,
+    intensity: f64,
+}
+
+impl DiffuseLight {
+    /// Create a light of the given color. The intensity scales the color and may be
+    /// greater than `1.0` for bright lights.
+    pub fn new(color: Color, intensity: f64) -> Self {
+        Self { color, intensity }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+
+    pub fn intensity(&self) -> f64 {
+        self.intensity
+    }
+
+    /// The light emitted, which is the color scaled by the intensity.
+    pub fn emit(&self) -> Color {
+        self.color * self.intensity
+    }
+}
+
+impl Material for DiffuseLight {
+    fn attenuate(&self, _hit_record: &HitRecord, _color: Color) -> Color {
+        color::BLACK
+    }
+
+    /// Lights absorb every incident ray.
+    fn scatter(&self, _ray_in: &Ray, _hit_record: &HitRecord, _rng: &RTRng) -> Option<Ray> {
+        None
+    }
+
+    fn emitted(&self, _hit_record: &HitRecord) -> Color {
+        self.emit()
+    }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::DiffuseLight {
+            color: description::rgb(self.color),
+            intensity: self.intensity,
+        })
+    }
+}
+
+/// The phase function of a volume that scatters light equally in all directions,
+/// such as the smoke inside a [`crate::geometry::constant_medium::ConstantMedium`].
+#[derive(Debug)]
+pub struct Isotropic {
+    /// The fraction of light scattered, rather than absorbed, by colors (red,
+    /// green, blue).
+    albedo: (f64, f64, f64),
+}
+
+impl Isotropic {
+    pub fn new(albedo_red: f64, albedo_green: f64, albedo_blue: f64) -> Self {
+        Self {
+            albedo: (albedo_red, albedo_green, albedo_blue),
+        }
+    }
+
+    pub fn albedo(&self) -> (f64, f64, f64) {
+        self.albedo
+    }
+}
+
+impl Material for Isotropic {
+    fn attenuate(&self, _hit_record: &HitRecord, color: Color) -> Color {
+        color * Color::from(self.albedo)
+    }
+
+    /// Scattered rays leave in any direction, regardless of the normal.
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, rng: &RTRng) -> Option<Ray> {
+        Some(Ray::new(hit_record.point, rng.random_unit_vector()).with_time(ray_in.time()))
+    }
+
+    /// Scattered rays are uniform over the sphere of directions. There is no cosine
+    /// term, as light is scattered inside the volume rather than from a surface.
+    fn scattering_pdf(&self, _hit_record: &HitRecord, _direction: Vec3) -> Option<f64> {
+        Some(1.0 / (4.0 * PI))
+    }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::Isotropic {
+            albedo: self.albedo,
+        })
+    }
+}
+
+/// The Henyey–Greenstein phase function, of a volume that scatters light mostly
+/// forwards or backwards, such as a cloud.
+#[derive(Debug)]
+pub struct HenyeyGreenstein {
+    /// The fraction of light scattered, rather than absorbed, by colors (red,
+    /// green, blue).
+    albedo_rgb: (f64, f64, f64),
+
+    /// The mean cosine of the angle that light is scattered by, often called `g`.
+    /// Positive values scatter light forwards, negative values scatter it back, and
+    /// `0.0` scatters it equally in all directions. Between `-1.0` and `1.0`.
+    asymmetry: f64,
+}
+
+impl HenyeyGreenstein {
+    /// # Panics
+    /// Panics if `asymmetry` isn't between `-1.0` and `1.0`, exclusive. At either end
+    /// all light would scatter in one direction, which has no density.
+    pub fn new(albedo_rgb: (f64, f64, f64), asymmetry: f64) -> Self {
+        assert!(
+            asymmetry > -1.0 && asymmetry < 1.0,
+            "the asymmetry must be between -1 and 1"
+        );
+        Self {
+            albedo_rgb,
+            asymmetry,
+        }
+    }
+
+    pub fn albedo(&self) -> (f64, f64, f64) {
+        self.albedo_rgb
+    }
+
+    pub fn asymmetry(&self) -> f64 {
+        self.asymmetry
+    }
+
+    /// The density of scattering by an angle with the given cosine.
+    fn phase(&self, cosine: f64) -> f64 {
+        let g = self.asymmetry;
+        let denominator = 1.0 + g * g - 2.0 * g * cosine;
+        (1.0 - g * g) / (4.0 * PI * denominator * denominator.sqrt())
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn attenuate(&self, _hit_record: &HitRecord, color: Color) -> Color {
+        color * Color::from(self.albedo_rgb)
+    }
+
+    /// Media give hits a normal facing against the ray, so the ray travels along
+    /// `-normal`, and the scattered ray is at an angle to that.
+    fn scatter(&self, ray_in: &Ray, hit_record: &HitRecord, rng: &RTRng) -> Option<Ray> {
+        let g = self.asymmetry;
+        let cosine = if g.abs() < 1e-3 {
+            1.0 - 2.0 * rng.random_f64()
+        } else {
+            // Invert the cumulative distribution of the cosine.
+            let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * rng.random_f64());
+            ((1.0 + g * g - s * s) / (2.0 * g)).clamp(-1.0, 1.0)
+        };
+        let sine = (1.0 - cosine * cosine).sqrt();
+        let phi = 2.0 * PI * rng.random_f64();
+
+        let frame = Frame::from_axis(hit_record.point, -hit_record.normal);
+        let local = Vec3::new(sine * phi.cos(), sine * phi.sin(), cosine);
+        Some(Ray::new(hit_record.point, frame.vector_to_world(local)).with_time(ray_in.time()))
+    }
+
+    fn scattering_pdf(&self, hit_record: &HitRecord, direction: Vec3) -> Option<f64> {
+        let cosine = -hit_record.normal.dot(direction.normalized());
+        Some(self.phase(cosine))
+    }
+
+    fn describe(&self) -> io::Result<MaterialDescription> {
+        Ok(MaterialDescription::HenyeyGreenstein {
+            albedo: self.albedo_rgb,
+            g: self.asymmetry,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rstest::rstest;
+
+    use super::HenyeyGreenstein;
+
+    #[rstest]
+    #[should_panic(expected = "the asymmetry must be between -1 and 1")]
+    #[case(1.0)]
+    #[should_panic(expected = "the asymmetry must be between -1 and 1")]
+    #[case(-1.0)]
+    #[should_panic(expected = "the asymmetry must be between -1 and 1")]
+    #[case(f64::NAN)]
+    #[case(0.0)]
+    #[case(0.99)]
+    #[case(-0.99)]
+    fn henyey_greenstein_asymmetry(#[case] asymmetry: f64) {
+        let phase_function = HenyeyGreenstein::new((1.0, 1.0, 1.0), asymmetry);
+        assert!(phase_function.phase(1.0).is_finite());
+        assert!(phase_function.phase(-1.0).is_finite());
+    }
 }
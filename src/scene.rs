@@ -0,0 +1,1180 @@
+//! Loading scenes from a text description.
+//!
+//! Scenes are described in [TOML](https://toml.io). Materials are given names, which
+//! objects use to refer to them:
+//!
+//! ```toml
+//! [image]
+//! width = 600
+//! height = 400
+//! samples_per_pixel = 100  # Optional, defaults to 100.
+//! ray_bounce_limit = 50    # Optional, defaults to 50.
+//!
+//! [camera]
+//! look_from = [13, 2, 3]
+//! look_at = [0, 0, 0]
+//! vup = [0, 1, 0]          # Optional, defaults to [0, 1, 0].
+//! vfov = 20
+//! aperture = 0.1           # Optional, defaults to 0.
+//! focus_dist = 10          # Optional, defaults to the distance to `look_at`.
+//! shutter = [0, 1]         # Optional, the times the shutter opens and closes.
+//!                          # Defaults to [0, 0], which doesn't blur moving objects.
+//!
+//! # Optional, defaults to a sky gradient. Also "solid" with a `color`, or
+//! # "environment" with a `path` to an image, and optional `rotation` and `intensity`.
+//! [background]
+//! type = "gradient"
+//! bottom = [1, 1, 1]
+//! top = [0.5, 0.7, 1.0]
+//!
+//! [materials.ground]
+//! type = "lambertian"
+//! albedo = [0.5, 0.5, 0.5]
+//!
+//! [materials.gold]
+//! type = "metal"
+//! albedo = [0.8, 0.6, 0.2]
+//! fuzz = 0.1               # Optional, defaults to 0.
+//!
+//! # Albedos can also be textures. A checkerboard alternates between two textures,
+//! # with `scale` squares per unit of the surface's texture coordinates. Spheres
+//! # have coordinates from 0 to 1 around and up them.
+//! [materials.tiles]
+//! type = "lambertian"
+//! albedo = { type = "checker", scale = 10, even = [0.9, 0.9, 0.9], odd = [0.1, 0.1, 0.1] }
+//!
+//! [materials.glass]
+//! type = "dielectric"
+//! refractive_index = 1.5
+//!
+//! [materials.lamp]
+//! type = "diffuse_light"
+//! color = [1, 0.9, 0.8]
+//! intensity = 4            # Optional, defaults to 1.
+//!
+//! # Scatters light equally in all directions, for use inside a "constant_medium".
+//! [materials.smoke]
+//! type = "isotropic"
+//! albedo = [0.9, 0.9, 0.9]
+//!
+//! # Scatters light mostly forwards for positive `g`, or back for negative `g`.
+//! [materials.cloud]
+//! type = "henyey_greenstein"
+//! albedo = [0.95, 0.95, 0.95]
+//! g = 0.6                  # From -1 to 1. 0 scatters equally in all directions.
+//!
+//! [[objects]]
+//! type = "plane"
+//! point = [0, 0, 0]
+//! normal = [0, 1, 0]
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "sphere"
+//! center = [0, 1, 0]
+//! radius = 1
+//! material = "ground"
+//!
+//! # A sphere that moves from the first center to the second over an interval of
+//! # time, and is blurred if the shutter is open while it moves.
+//! [[objects]]
+//! type = "moving_sphere"
+//! centers = [[-1, 1, 0], [-1, 1.5, 0]]
+//! times = [0, 1]           # Optional, defaults to [0, 1].
+//! radius = 0.5
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "cuboid"
+//! center = [2, 0.5, 0]
+//! size = [1, 1, 2]
+//! x_axis = [1, 0, 1]       # Optional, the directions of the box's edges. The
+//! y_axis = [0, 1, 0]       # defaults give an axis-aligned box.
+//! material = "glass"
+//!
+//! # A parallelogram with corners at origin, origin + u, origin + v and
+//! # origin + u + v.
+//! [[objects]]
+//! type = "quad"
+//! origin = [-1, 3, -1]
+//! u = [2, 0, 0]
+//! v = [0, 0, 2]
+//! material = "lamp"
+//!
+//! [[objects]]
+//! type = "triangle"
+//! vertices = [[-1, 0, -1], [1, 0, -1], [0, 1, -1]]
+//! normals = [[0, 0, 1], [0, 0, 1], [0, 0, 1]]  # Optional, for smooth shading.
+//! uvs = [[0, 0], [1, 0], [0.5, 1]]              # Optional.
+//! material = "ground"
+//!
+//! [[objects]]
+//! type = "disk"
+//! center = [0, 2, -3]
+//! normal = [0, 0, 1]
+//! radius = 0.5
+//! material = "gold"
+//!
+//! [[objects]]
+//! type = "cylinder"
+//! base = [-2, 0, 0]        # The centers of the ends.
+//! top = [-2, 1, 0]
+//! radius = 0.5
+//! capped = false           # Optional, defaults to true.
+//! material = "gold"
+//!
+//! [[objects]]
+//! type = "cone"
+//! base = [-2, 1, 2]
+//! apex = [-2, 2, 2]
+//! radius = 0.5
+//! capped = true            # Optional, defaults to true.
+//! material = "gold"
+//!
+//! [[objects]]
+//! type = "torus"
+//! center = [0, 0.25, 2]
+//! axis = [0, 1, 0]         # The axis that the tube is swept around.
+//! major_radius = 1         # The distance from the center to the middle of the tube.
+//! minor_radius = 0.25      # The radius of the tube.
+//! material = "gold"
+//!
+//! [[objects]]
+//! type = "mesh"
+//! path = "model.obj"    # An OBJ or PLY file, relative to the scene file.
+//! material = "ground"   # Used by faces without a material from an MTL file.
+//!
+//! # Any object can be moved, rotated and scaled. The steps of the transform are
+//! # applied in order. `matrix = [[...], [...], [...]]` gives the first three rows
+//! # of an affine transform's matrix directly.
+//! [[objects]]
+//! type = "transformed"
+//! transform = [
+//!     { scale = [2, 1, 1] },
+//!     { rotate = { axis = [0, 1, 0], degrees = 45 } },
+//!     { translate = [0, 1, 4] },
+//! ]
+//! object = { type = "sphere", center = [0, 0, 0], radius = 0.5, material = "gold" }
+//!
+//! # Spheres, and CSG objects themselves, can be combined by "union",
+//! # "intersection" or "difference". A difference is the left object with the right
+//! # object taken away.
+//! [[objects]]
+//! type = "csg"
+//! operation = "difference"
+//! left = { type = "sphere", center = [2, 2, 0], radius = 0.5, material = "gold" }
+//! right = { type = "sphere", center = [2.3, 2.3, 0.3], radius = 0.3, material = "ground" }
+//!
+//! # Surfaces given by signed distance functions. Shapes are "sphere", "cuboid",
+//! # "torus" and the "mandelbulb" fractal, which can be combined by "smooth_union"
+//! # and "blend".
+//! [[objects]]
+//! type = "sdf"
+//! epsilon = 0.0001         # Optional. Rays this close to the surface hit it.
+//! max_steps = 256          # Optional. Rays that take more steps miss the surface.
+//! material = "gold"
+//! shape = { type = "mandelbulb", center = [0, 1, -2], scale = 0.5, power = 8, iterations = 10 }
+//!
+//! [[objects]]
+//! type = "sdf"
+//! material = "gold"
+//! [objects.shape]
+//! type = "smooth_union"
+//! smoothness = 0.2         # The size of the fillet where the shapes meet.
+//! a = { type = "sphere", center = [2, 1, -2], radius = 0.4 }
+//! b = { type = "torus", center = [2, 1, -2], axis = [0, 1, 0], major_radius = 0.5, minor_radius = 0.1 }
+//!
+//! # A volume of smoke or fog, filling a convex boundary. The boundary's material
+//! # isn't used.
+//! [[objects]]
+//! type = "constant_medium"
+//! density = 2              # The chance of scattering per unit distance.
+//! material = "smoke"
+//! boundary = { type = "sphere", center = [-2, 1, -2], radius = 0.5, material = "smoke" }
+//!
+//! # A volume whose density varies. Densities are "noise", which varies smoothly at
+//! # random, or a "grid" of samples spanning a box, with x varying fastest.
+//! [[objects]]
+//! type = "heterogeneous_medium"
+//! material = "cloud"
+//! boundary = { type = "cuboid", center = [0, 3, 0], size = [4, 1, 4], material = "cloud" }
+//! [objects.density]
+//! type = "noise"
+//! density = 4              # The highest density.
+//! frequency = 1            # How many times the density varies per unit distance.
+//! octaves = 4              # Optional, defaults to 4. Layers of finer detail.
+//! seed = 0                 # Optional, defaults to 0.
+//!
+//! [[objects]]
+//! type = "heterogeneous_medium"
+//! material = "smoke"
+//! boundary = { type = "sphere", center = [2, 1, -2], radius = 0.5, material = "smoke" }
+//! density = { type = "grid", min = [1.5, 0.5, -2.5], max = [2.5, 1.5, -1.5], size = [2, 1, 1], values = [0, 5] }
+//! ```
+//!
+//! The aspect ratio of the camera is that of the image.
+//!
+//! Scenes built in code can be saved in the same format with [`save`]. Each part of
+//! a scene describes itself for saving, with a `describe` method of its trait, such
+//! as [`Hittable`] or [`Material`]. The descriptions are private to this crate, so
+//! those methods are hidden, and only the built-in objects, materials and
+//! backgrounds can be saved. Types from other crates keep the default method, which
+//! returns an error.
+//!
+//! [`Hittable`]: crate::geometry::hittable::Hittable
+use std::{collections::BTreeMap, fs, io, ops::Range, path::Path, sync::Arc};
+
+use serde::{Deserialize, Serialize};
+use toml::Spanned;
+
+use crate::{
+    background::Gradient,
+    camera::Config as CameraConfig,
+    geometry::vec3::Vec3,
+    material::Material,
+    tracer::{ImageConfig, World},
+};
+
+use description::{
+    invalid_data, invalid_input, tuple, BackgroundDescription, MaterialDescription, Materials,
+    Meshes, ObjectDescription,
+};
+
+pub(crate) mod description;
+
+/// A scene loaded from a description: how to render it, where the camera is, and
+/// what it sees.
+pub struct Scene {
+    /// The image settings. The scene doesn't choose the number of threads, and its
+    /// seed is `0`.
+    pub image: ImageConfig,
+    pub camera: CameraConfig,
+    pub world: World,
+}
+
+/// Load a scene from a file. Relative paths in the scene, such as those of
+/// environment maps and meshes, are relative to the directory containing the file.
+///
+/// # Errors
+/// Returns an error if the file can't be read, or if it isn't a valid scene. The
+/// error message gives the line of the invalid input.
+pub fn load(path: impl AsRef<Path>) -> io::Result<Scene> {
+    let path = path.as_ref();
+    let text = fs::read_to_string(path)?;
+    let base_dir = path.parent().unwrap_or(Path::new(""));
+    parse(&text, base_dir).map_err(|e| io::Error::new(e.kind(), format!("{}: {e}", path.display())))
+}
+
+/// Parse a scene description. Relative paths in the scene are relative to
+/// `base_dir`.
+///
+/// # Errors
+/// Returns an error if the text isn't a valid scene. The error message gives the
+/// line of the invalid input.
+pub fn parse(text: &str, base_dir: &Path) -> io::Result<Scene> {
+    let description: SceneDescription = toml::from_str(text).map_err(invalid_data)?;
+    let error_at = |span: Range<usize>, message: String| {
+        let line = text[..span.start].matches('\n').count() + 1;
+        invalid_data(format!("line {line}: {message}"))
+    };
+
+    let image_span = description.image.span();
+    let image = description.image.into_inner();
+    if image.width < 2 || image.height < 2 {
+        return Err(error_at(
+            image_span,
+            "the image must be at least 2 pixels wide and high".into(),
+        ));
+    }
+    if image.samples_per_pixel == 0 {
+        return Err(error_at(
+            image_span,
+            "the image must have at least one sample per pixel".into(),
+        ));
+    }
+
+    let camera_span = description.camera.span();
+    let camera = description.camera.into_inner();
+    let camera = camera
+        .to_config(image.width as f64 / image.height as f64)
+        .map_err(|message| error_at(camera_span, message))?;
+
+    let background = match description.background {
+        Some(background) => {
+            let span = background.span();
+            background
+                .into_inner()
+                .build(base_dir)
+                .map_err(|e| error_at(span, e.to_string()))?
+        }
+        None => Arc::new(Gradient::sky()),
+    };
+
+    let mut materials = Materials::new();
+    for (name, material) in description.materials {
+        let span = material.span();
+        let material = material
+            .into_inner()
+            .build()
+            .map_err(|message| error_at(span, format!("material {name:?}: {message}")))?;
+        materials.insert(name, material);
+    }
+
+    let mut meshes = Meshes::new();
+    let mut world: World = vec![];
+    for object in description.objects {
+        let span = object.span();
+        world.push(
+            object
+                .into_inner()
+                .build(&materials, &mut meshes, base_dir)
+                .map_err(|message| error_at(span, message))?,
+        );
+    }
+
+    Ok(Scene {
+        image: ImageConfig {
+            width: image.width,
+            height: image.height,
+            samples_per_pixel: image.samples_per_pixel,
+            ray_bounce_limit: image.ray_bounce_limit,
+            threads: 0,
+            seed: 0,
+            background,
+        },
+        camera,
+        world,
+    })
+}
+
+/// Save a scene to a file, in the format read by [`load`].
+///
+/// # Errors
+/// Returns an error if the scene can't be described (see [`to_string`]), or the
+/// file can't be written.
+pub fn save(path: impl AsRef<Path>, scene: &Scene) -> io::Result<()> {
+    fs::write(path, to_string(scene)?)
+}
+
+/// Describe a scene in the format read by [`parse`]. Materials shared by several
+/// objects are described once, and named after their type.
+///
+/// # Errors
+/// Returns an error if the scene contains an object, material or background that the
+/// format can't describe, such as an environment map that wasn't loaded from a file,
+/// or if the aspect ratio of the camera doesn't match the image.
+pub fn to_string(scene: &Scene) -> io::Result<String> {
+    let image = &scene.image;
+    let aspect_ratio = image.width as f64 / image.height as f64;
+    if (scene.camera.aspect_ratio - aspect_ratio).abs() > 1e-9 {
+        return Err(invalid_input(
+            "the camera's aspect ratio must match the image",
+        ));
+    }
+
+    // Shared materials are found by comparing pointers.
+    let mut named_materials: Vec<(Arc<dyn Material>, String)> = vec![];
+    let mut kind_counts: BTreeMap<&str, usize> = BTreeMap::new();
+    let mut materials = BTreeMap::new();
+    let mut objects = vec![];
+    for hittable in &scene.world {
+        let object = hittable.describe(&mut |material| {
+            if let Some((_, name)) = named_materials
+                .iter()
+                .find(|(m, _)| Arc::ptr_eq(m, material))
+            {
+                return Ok(name.clone());
+            }
+
+            let description = material.describe()?;
+            let count = kind_counts.entry(description.kind()).or_default();
+            *count += 1;
+            let name = format!("{}_{count}", description.kind());
+            materials.insert(name.clone(), Spanned::new(0..0, description));
+            named_materials.push((Arc::clone(material), name.clone()));
+            Ok(name)
+        })?;
+        objects.push(Spanned::new(0..0, object));
+    }
+
+    let description = SceneDescription {
+        image: Spanned::new(
+            0..0,
+            ImageDescription {
+                width: image.width,
+                height: image.height,
+                samples_per_pixel: image.samples_per_pixel,
+                ray_bounce_limit: image.ray_bounce_limit,
+            },
+        ),
+        camera: Spanned::new(0..0, CameraDescription::describe(&scene.camera)),
+        background: Some(Spanned::new(0..0, image.background.describe()?)),
+        materials,
+        objects,
+    };
+    toml::to_string(&description).map_err(invalid_input)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct SceneDescription {
+    image: Spanned<ImageDescription>,
+    camera: Spanned<CameraDescription>,
+    background: Option<Spanned<BackgroundDescription>>,
+    #[serde(default)]
+    materials: BTreeMap<String, Spanned<MaterialDescription>>,
+    #[serde(default)]
+    objects: Vec<Spanned<ObjectDescription>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct ImageDescription {
+    width: u32,
+    height: u32,
+    #[serde(default = "default_samples_per_pixel")]
+    samples_per_pixel: u32,
+    #[serde(default = "default_ray_bounce_limit")]
+    ray_bounce_limit: u32,
+}
+
+fn default_samples_per_pixel() -> u32 {
+    100
+}
+
+fn default_ray_bounce_limit() -> u32 {
+    50
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields)]
+struct CameraDescription {
+    look_from: (f64, f64, f64),
+    look_at: (f64, f64, f64),
+    #[serde(default = "default_vup")]
+    vup: (f64, f64, f64),
+    vfov: f64,
+    #[serde(default)]
+    aperture: f64,
+    focus_dist: Option<f64>,
+    #[serde(default)]
+    shutter: (f64, f64),
+}
+
+fn default_vup() -> (f64, f64, f64) {
+    (0.0, 1.0, 0.0)
+}
+
+impl CameraDescription {
+    fn describe(config: &CameraConfig) -> Self {
+        Self {
+            look_from: tuple(config.look_from),
+            look_at: tuple(config.look_at),
+            vup: tuple(config.vup),
+            vfov: config.vfov_degrees,
+            aperture: config.aperture_diameter,
+            focus_dist: Some(config.focus_dist),
+            shutter: (config.shutter_open, config.shutter_close),
+        }
+    }
+
+    fn to_config(&self, aspect_ratio: f64) -> Result<CameraConfig, String> {
+        let look_from = Vec3::from(self.look_from);
+        let look_at = Vec3::from(self.look_at);
+        let vup = Vec3::from(self.vup);
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (look_at - look_from).length());
+
+        if (look_at - look_from).length() == 0.0 {
+            return Err("the camera must look at a point other than its position".into());
+        }
+        if (look_at - look_from).cross(vup).length() == 0.0 {
+            return Err("the camera's up direction must not be parallel to its view".into());
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err("the field of view must be between 0 and 180 degrees".into());
+        }
+        if self.aperture < 0.0 || focus_dist <= 0.0 {
+            return Err("the aperture and focus distance must be positive".into());
+        }
+        let (shutter_open, shutter_close) = self.shutter;
+        if shutter_open > shutter_close {
+            return Err("the shutter must open before it closes".into());
+        }
+
+        Ok(CameraConfig {
+            look_from,
+            look_at,
+            vup,
+            vfov_degrees: self.vfov,
+            aspect_ratio,
+            aperture_diameter: self.aperture,
+            focus_dist,
+            shutter_open,
+            shutter_close,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs, path::Path, process, sync::Arc};
+
+    use super::{
+        description::{Materials, Meshes, ObjectDescription},
+        load, parse, save, to_string, Scene,
+    };
+    use crate::{
+        background::{environment::EnvironmentMap, Gradient},
+        camera::{Camera, Config as CameraConfig},
+        color::{self, Color},
+        geometry::{
+            aabb::Aabb,
+            cone::Cone,
+            constant_medium::ConstantMedium,
+            csg::Csg,
+            cuboid::Cuboid,
+            cylinder::Cylinder,
+            density::DensityGrid,
+            disk::Disk,
+            heterogeneous_medium::HeterogeneousMedium,
+            mat4::Mat4,
+            moving_sphere::MovingSphere,
+            plane::Plane,
+            quad::Quad,
+            ray::Ray,
+            sdf::{shapes, Sdf},
+            sphere::Sphere,
+            torus::Torus,
+            transformed::Transformed,
+            triangle::Triangle,
+            vec3::Vec3,
+        },
+        material::{
+            Dielectric, DiffuseLight, HenyeyGreenstein, Isotropic, Lambertian, Material, Metal,
+        },
+        texture::{Checker, SolidColor},
+        tracer::{self, ImageConfig, World},
+    };
+
+    const SCENE: &str = r#"
+[image]
+width = 300
+height = 200
+
+[camera]
+look_from = [0, 0, 4]
+look_at = [0, 0, 0]
+vfov = 30
+
+[background]
+type = "solid"
+color = [0.1, 0.2, 0.3]
+
+[materials.red]
+type = "lambertian"
+albedo = [0.8, 0.1, 0.1]
+
+[materials.mirror]
+type = "metal"
+albedo = [1, 1, 1]
+
+[[objects]]
+type = "sphere"
+center = [0, 0, 0]
+radius = 1
+material = "red"
+
+[[objects]]
+type = "sphere"
+center = [0, 2, 0]
+radius = 0.5
+material = "mirror"
+
+[[objects]]
+type = "sphere"
+center = [0, -2, 0]
+radius = 0.5
+material = "red"
+"#;
+
+    fn error_message(text: &str) -> String {
+        match parse(text, Path::new("")) {
+            Ok(_) => panic!("Scene should be invalid"),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    #[test]
+    fn parse_scene() {
+        let scene = parse(SCENE, Path::new("")).unwrap();
+
+        assert_eq!(300, scene.image.width);
+        assert_eq!(200, scene.image.height);
+        assert_eq!(100, scene.image.samples_per_pixel);
+        assert_eq!(50, scene.image.ray_bounce_limit);
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, 1));
+        assert_eq!(
+            Color::new(0.1, 0.2, 0.3),
+            scene.image.background.color(&ray)
+        );
+
+        assert_eq!(Vec3::new(0, 0, 4), scene.camera.look_from);
+        assert_eq!(Vec3::new(0, 1, 0), scene.camera.vup);
+        assert_eq!(1.5, scene.camera.aspect_ratio);
+        assert_eq!(0.0, scene.camera.aperture_diameter);
+        assert_eq!(4.0, scene.camera.focus_dist);
+
+        assert_eq!(3, scene.world.len());
+        let down = Ray::new(Vec3::new(0, 5, 0), Vec3::new(0, -1, 0));
+        let hit = scene.world[1].hit(&down, 0.0, f64::INFINITY).unwrap();
+        assert_eq!(2.5, hit.t);
+    }
+
+    #[test]
+    fn objects_share_named_materials() {
+        let scene = parse(SCENE, Path::new("")).unwrap();
+        let ray = |y| Ray::new(Vec3::new(0, y, 5), Vec3::new(0, 0, -1));
+        let material = |i: usize, y| scene.world[i].hit(&ray(y), 0.0, 10.0).unwrap().material;
+
+        assert!(std::sync::Arc::ptr_eq(&material(0, 0), &material(2, -2)));
+        assert!(!std::sync::Arc::ptr_eq(&material(0, 0), &material(1, 2)));
+    }
+
+    #[test]
+    fn transformed_objects() {
+        let text = SCENE.replacen(
+            "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+            "type = \"transformed\"\n\
+             transform = [{ scale = [2, 2, 2] }, { translate = [1, 0, 0] }]\n\
+             object = { type = \"sphere\", center = [0, 0, 0], radius = 0.5, material = \"red\" }",
+            1,
+        );
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        // Scaled to a radius of 1, then moved along x.
+        let ray = Ray::new(Vec3::new(1, 0, 5), Vec3::new(0, 0, -1));
+        let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+        assert!((hr.t - 4.0).abs() < 1e-12);
+        let ray = Ray::new(Vec3::new(-0.1, 0, 5), Vec3::new(0, 0, -1));
+        assert!(scene.world[0].hit(&ray, 0.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn csg_objects() {
+        let text = SCENE.replacen(
+            "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+            "type = \"csg\"\noperation = \"difference\"\n\
+             left = { type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }\n\
+             right = { type = \"sphere\", center = [0, 0, 1], radius = 0.5, material = \"mirror\" }",
+            1,
+        );
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        // The ray passes through the bite, and hits its mirrored inside.
+        let ray = Ray::new(Vec3::new(0, 0, 5), Vec3::new(0, 0, -1));
+        let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+        assert_eq!(4.5, hr.t);
+        let mirror_ray = Ray::new(Vec3::new(0, 2, 5), Vec3::new(0, 0, -1));
+        let mirror = scene.world[1].hit(&mirror_ray, 0.0, 10.0).unwrap().material;
+        assert!(Arc::ptr_eq(&mirror, &hr.material));
+
+        let hollow = text.replace("radius = 0.5", "radius = -0.5");
+        let message = error_message(&hollow);
+        assert!(message.contains("positive radius"), "{message}");
+
+        let text = text.replace("material = \"mirror\"", "material = \"glass\"");
+        let message = error_message(&text);
+        assert!(message.contains("unknown material \"glass\""), "{message}");
+    }
+
+    #[test]
+    fn sdf_objects() {
+        let text = SCENE.replacen(
+            "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+            "type = \"sdf\"\nmaterial = \"red\"\nmax_steps = 64\n\
+             [objects.shape]\ntype = \"blend\"\nweight = 0.5\n\
+             a = { type = \"sphere\", center = [0, 0, 0], radius = 1 }\n\
+             b = { type = \"cuboid\", center = [0, 0, 0], size = [1, 1, 1] }",
+            1,
+        );
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        // Halfway between the sphere at z = 1 and the box at z = 0.5.
+        let ray = Ray::new(Vec3::new(0, 0, 5), Vec3::new(0, 0, -1));
+        let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+        assert!((hr.t - 4.25).abs() < 1e-4);
+
+        let text = text.replace("weight = 0.5", "weight = 2");
+        let message = error_message(&text);
+        assert!(message.contains("weight from 0 to 1"), "{message}");
+    }
+
+    #[test]
+    fn constant_media() {
+        let text = SCENE.replacen(
+            "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+            "type = \"constant_medium\"\ndensity = 1e9\nmaterial = \"smoke\"\n\
+             boundary = { type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }",
+            1,
+        ) + "[materials.smoke]\ntype = \"isotropic\"\nalbedo = [0.9, 0.9, 0.9]\n";
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        // Dense enough to scatter the ray where it enters.
+        let ray = Ray::new(Vec3::new(0, 0, 5), Vec3::new(0, 0, -1));
+        let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+        assert!((hr.t - 4.0).abs() < 1e-6);
+        assert_eq!("isotropic", hr.material.describe().unwrap().kind());
+
+        for density in ["0", "-1", "nan"] {
+            let text = text.replace("density = 1e9", &format!("density = {density}"));
+            let message = error_message(&text);
+            assert!(message.contains("positive density"), "{message}");
+        }
+    }
+
+    #[test]
+    fn heterogeneous_media() {
+        let text = SCENE.replacen(
+            "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+            "type = \"heterogeneous_medium\"\nmaterial = \"cloud\"\n\
+             boundary = { type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }\n\
+             density = { type = \"noise\", density = 1e9, frequency = 2 }",
+            1,
+        )
+            + "[materials.cloud]\ntype = \"henyey_greenstein\"\nalbedo = [1, 1, 1]\ng = 0.8\n";
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        let ray = Ray::new(Vec3::new(0, 0, 5), Vec3::new(0, 0, -1));
+        let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+        assert!((hr.t - 4.0).abs() < 1e-6);
+        assert_eq!("henyey_greenstein", hr.material.describe().unwrap().kind());
+
+        for (from, to, expected) in [
+            ("g = 0.8", "g = 1", "g must be between -1 and 1"),
+            ("g = 0.8", "g = nan", "g must be between -1 and 1"),
+            ("frequency = 2", "frequency = 0", "positive frequency"),
+            (
+                "{ type = \"noise\", density = 1e9, frequency = 2 }",
+                "{ type = \"grid\", min = [-1, -1, -1], max = [1, 1, 1], size = [2, 2, 2], values = [1] }",
+                "must have 8 values",
+            ),
+            (
+                "{ type = \"noise\", density = 1e9, frequency = 2 }",
+                "{ type = \"grid\", min = [-1, -1, -1], max = [1, 1, 1], size = [4294967296, 4294967296, 4294967296], values = [] }",
+                "too many samples",
+            ),
+            (
+                "{ type = \"noise\", density = 1e9, frequency = 2 }",
+                "{ type = \"grid\", min = [-1, -1, -1], max = [1, 1, 1], size = [2, 1, 1], values = [nan, 1] }",
+                "must be finite",
+            ),
+        ] {
+            let message = error_message(&text.replace(from, to));
+            assert!(message.contains(expected), "{message}");
+        }
+    }
+
+    #[test]
+    fn checker_textures() {
+        let text = SCENE.replace(
+            "albedo = [0.8, 0.1, 0.1]",
+            "albedo = { type = \"checker\", scale = 2, even = [0.8, 0.1, 0.1], odd = [1, 1, 1] }",
+        );
+        let scene = parse(&text, Path::new("")).unwrap();
+
+        // The front of the sphere is three quarters of the way around it, and the side
+        // facing `+x` is at the start.
+        let albedo = |origin: Vec3| {
+            let ray = Ray::new(origin, -origin);
+            let hr = scene.world[0].hit(&ray, 0.0, 10.0).unwrap();
+            hr.material.attenuate(&hr, color::WHITE)
+        };
+        assert_eq!(Color::new(0.8, 0.1, 0.1), albedo(Vec3::new(0, 0, 5)));
+        assert_eq!(color::WHITE, albedo(Vec3::new(5, 0, 0)));
+
+        let message = error_message(&text.replace("scale = 2", "scale = 0"));
+        assert!(message.contains("positive scale"), "{message}");
+    }
+
+    #[test]
+    fn syntax_errors_give_line() {
+        let text = SCENE.replace("vfov = 30", "vfov = ");
+        let message = error_message(&text);
+        assert!(message.contains("line 9"), "{message}");
+    }
+
+    #[test]
+    fn invalid_values_give_line() {
+        let text = SCENE.replace("material = \"mirror\"", "material = \"glass\"");
+        let message = error_message(&text);
+        assert!(message.contains("unknown material \"glass\""), "{message}");
+        // The line of the object's header.
+        assert!(message.starts_with("line 29:"), "{message}");
+
+        let text = SCENE.replace("albedo = [1, 1, 1]", "albedo = [1, 1, 1]\nfuzz = 2");
+        let message = error_message(&text);
+        assert!(message.contains("material \"mirror\""), "{message}");
+        assert!(message.starts_with("line 19:"), "{message}");
+    }
+
+    #[test]
+    fn invalid_scenes() {
+        for (from, to) in [
+            ("width = 300", "width = 1"),
+            ("width = 300", "width = -300"),
+            ("width = 300", ""),
+            ("look_at = [0, 0, 0]", "look_at = [0, 0, 4]"),
+            ("look_at = [0, 0, 0]", "look_at = [0, 0]"),
+            ("vfov = 30", "vfov = 180"),
+            ("radius = 1", "radius = 0"),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1",
+                "type = \"plane\"\npoint = [0, 0, 0]\nnormal = [0, 0, 0]",
+            ),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1",
+                "type = \"quad\"\norigin = [0, 0, 0]\nu = [1, 0, 0]\nv = [2, 0, 0]",
+            ),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1",
+                "type = \"cylinder\"\nbase = [0, 0, 0]\ntop = [0, 0, 0]\nradius = 1",
+            ),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1",
+                "type = \"torus\"\ncenter = [0, 0, 0]\naxis = [0, 1, 0]\n\
+                 major_radius = 1\nminor_radius = 2",
+            ),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+                "type = \"transformed\"\ntransform = [{ scale = [1, 0, 1] }]\n\
+                 object = { type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }",
+            ),
+            (
+                "type = \"sphere\"\ncenter = [0, 0, 0]\nradius = 1\nmaterial = \"red\"",
+                "type = \"csg\"\noperation = \"union\"\n\
+                 left = { type = \"sphere\", center = [0, 0, 0], radius = 1, material = \"red\" }\n\
+                 right = { type = \"plane\", point = [0, 0, 0], normal = [0, 1, 0], material = \"red\" }",
+            ),
+            ("type = \"solid\"", "type = \"checkered\""),
+            ("type = \"lambertian\"", "type = \"lambertian\"\nfuzz = 0.5"),
+        ] {
+            let text = SCENE.replacen(from, to, 1);
+            assert!(
+                parse(&text, Path::new("")).is_err(),
+                "{to:?} should be rejected"
+            );
+        }
+    }
+
+    fn built_scene() -> Scene {
+        let ground: Arc<dyn Material> = Arc::new(Lambertian::textured(Arc::new(Checker::new(
+            4.0,
+            Arc::new(SolidColor::new(Color::new(0.5, 0.5, 0.5))),
+            Arc::new(SolidColor::new(Color::new(0.2, 0.3, 0.1))),
+        ))));
+        let matte: Arc<dyn Material> = Arc::new(Lambertian::new(0.1, 0.2, 0.3));
+        let metal: Arc<dyn Material> = Arc::new(Metal::new((0.7, 0.6, 0.5), 0.1));
+        let glass: Arc<dyn Material> = Arc::new(Dielectric::new(1.5));
+        let light: Arc<dyn Material> = Arc::new(DiffuseLight::new(color::WHITE, 3.0));
+
+        let world: World = vec![
+            Arc::new(Sphere::new(
+                Vec3::new(0, -100.5, -1),
+                100,
+                Arc::clone(&ground),
+            )),
+            Arc::new(Sphere::new(Vec3::new(-1, 0, -1), 0.5, Arc::clone(&matte))),
+            Arc::new(Sphere::new(Vec3::new(0, 0, -1), 0.5, Arc::clone(&glass))),
+            Arc::new(Sphere::new(Vec3::new(0, 0, -1), -0.45, glass)),
+            Arc::new(Sphere::new(Vec3::new(1, 0, -1), 0.5, metal)),
+            Arc::new(MovingSphere::new(
+                Vec3::new(0.3, -0.4, -0.4),
+                Vec3::new(0.3, -0.2, -0.4),
+                0.0,
+                1.0,
+                0.1,
+                Arc::clone(&matte),
+            )),
+            Arc::new(Cuboid::new(
+                Vec3::new(-1.5, -0.5, -2),
+                Vec3::new(-0.8, 0.3, -1.6),
+                Arc::clone(&matte),
+            )),
+            Arc::new(Sphere::new(Vec3::new(0, 2, -1), 0.3, Arc::clone(&light))),
+            Arc::new(Plane::new(
+                Vec3::new(0, 0, -4),
+                Vec3::new(0, 0, 1),
+                Arc::clone(&ground),
+            )),
+            Arc::new(Quad::new(
+                Vec3::new(-0.5, 2.5, -1.5),
+                Vec3::new(1, 0, 0),
+                Vec3::new(0, 0.2, 1),
+                light,
+            )),
+            Arc::new(Csg::difference(
+                Arc::new(Sphere::new(Vec3::new(-1, 1, -2), 0.4, Arc::clone(&ground))),
+                Arc::new(Csg::union(
+                    Arc::new(Sphere::new(
+                        Vec3::new(-0.8, 1.1, -1.7),
+                        0.2,
+                        Arc::clone(&matte),
+                    )),
+                    Arc::new(Sphere::new(Vec3::new(-1.2, 1.1, -1.7), 0.2, matte)),
+                )),
+            )),
+            Arc::new(Disk::new(
+                Vec3::new(1.5, 0.5, -2),
+                Vec3::new(-1, 0, 0),
+                0.4,
+                Arc::clone(&ground),
+            )),
+            Arc::new(
+                Cylinder::new(
+                    Vec3::new(0.6, -0.5, -0.3),
+                    Vec3::new(0.6, -0.2, -0.3),
+                    0.1,
+                    Arc::clone(&ground),
+                )
+                .uncapped(),
+            ),
+            Arc::new(Cone::new(
+                Vec3::new(-0.6, -0.5, -0.3),
+                Vec3::new(-0.5, -0.1, -0.3),
+                0.15,
+                Arc::clone(&ground),
+            )),
+            Arc::new(Transformed::new(
+                Arc::new(Torus::new(
+                    Vec3::new(0, 0, 0),
+                    Vec3::new(0, 1, 0),
+                    0.2,
+                    0.05,
+                    Arc::clone(&ground),
+                )),
+                Mat4::translation(Vec3::new(0, -0.45, -0.3))
+                    * Mat4::rotation(Vec3::new(1, 0, 1), 20.0)
+                    * Mat4::scaling(Vec3::new(1, 2, 1)),
+            )),
+            Arc::new(ConstantMedium::new(
+                Arc::new(Sphere::new(
+                    Vec3::new(0.9, -0.35, -0.5),
+                    0.15,
+                    Arc::clone(&ground),
+                )),
+                5.0,
+                Arc::new(Isotropic::new(0.8, 0.8, 0.8)),
+            )),
+            Arc::new(HeterogeneousMedium::new(
+                Arc::new(Cuboid::new(
+                    Vec3::new(1.1, 0.6, -1.6),
+                    Vec3::new(1.5, 1.0, -1.2),
+                    Arc::clone(&ground),
+                )),
+                Arc::new(DensityGrid::new(
+                    Aabb::new(Vec3::new(1.1, 0.6, -1.6), Vec3::new(1.5, 1.0, -1.2)),
+                    [2, 2, 1],
+                    vec![0.0, 2.0, 4.0, 8.0],
+                )),
+                Arc::new(HenyeyGreenstein::new((0.9, 0.8, 0.7), 0.5)),
+            )),
+            Arc::new(Sdf::new(
+                Arc::new(shapes::SmoothUnion {
+                    a: Arc::new(shapes::Sphere {
+                        center: Vec3::new(1.2, 1, -1.8),
+                        radius: 0.2,
+                    }),
+                    b: Arc::new(shapes::Torus {
+                        center: Vec3::new(1.2, 1, -1.8),
+                        axis: Vec3::new(0, 1, 0),
+                        major_radius: 0.3,
+                        minor_radius: 0.05,
+                    }),
+                    smoothness: 0.1,
+                }),
+                Arc::clone(&ground),
+            )),
+            Arc::new(
+                Triangle::new(
+                    [
+                        Vec3::new(-2, -0.5, -2),
+                        Vec3::new(2, -0.5, -2),
+                        Vec3::new(0, 1.5, -2.5),
+                    ],
+                    ground,
+                )
+                .with_normals([
+                    Vec3::new(0, 0, 1),
+                    Vec3::new(0.2, 0, 1),
+                    Vec3::new(0, 0.2, 1),
+                ])
+                .with_uvs([(0.0, 0.0), (1.0, 0.0), (0.5, 1.0)]),
+            ),
+        ];
+        Scene {
+            image: ImageConfig {
+                width: 30,
+                height: 20,
+                samples_per_pixel: 4,
+                ray_bounce_limit: 8,
+                threads: 0,
+                seed: 0,
+                background: Arc::new(Gradient::sky()),
+            },
+            camera: CameraConfig {
+                look_from: Vec3::new(0.1, 0.3, 1.2),
+                look_at: Vec3::new(0, 0, -1),
+                vup: Vec3::new(0, 1, 0),
+                vfov_degrees: 37.5,
+                aspect_ratio: 1.5,
+                aperture_diameter: 0.05,
+                focus_dist: 2.2,
+                shutter_open: 0.0,
+                shutter_close: 0.5,
+            },
+            world,
+        }
+    }
+
+    fn render(scene: Scene) -> tracer::ImagePixels {
+        tracer::render(scene.image, Camera::new(scene.camera), scene.world)
+    }
+
+    #[test]
+    fn saved_scene_renders_identically() {
+        let text = to_string(&built_scene()).unwrap();
+        let loaded = parse(&text, Path::new("")).unwrap();
+
+        assert_eq!(render(built_scene()), render(loaded));
+    }
+
+    #[test]
+    fn each_object_round_trips() {
+        for (i, object) in built_scene().world.into_iter().enumerate() {
+            let scene = || Scene {
+                world: vec![Arc::clone(&object)],
+                ..built_scene()
+            };
+            let text = to_string(&scene()).unwrap();
+            let loaded = parse(&text, Path::new("")).unwrap();
+            assert_eq!(1, loaded.world.len(), "object {i}");
+            assert!(render(scene()) == render(loaded), "object {i}:\n{text}");
+        }
+    }
+
+    #[test]
+    fn shared_materials_are_saved_once() {
+        let text = to_string(&built_scene()).unwrap();
+        let table: toml::Table = toml::from_str(&text).unwrap();
+        let materials = table["materials"].as_table().unwrap();
+
+        let mut names: Vec<_> = materials.keys().map(String::as_str).collect();
+        names.sort();
+        assert_eq!(
+            vec![
+                "dielectric_1",
+                "diffuse_light_1",
+                "henyey_greenstein_1",
+                "isotropic_1",
+                "lambertian_1",
+                "lambertian_2",
+                "metal_1"
+            ],
+            names
+        );
+
+        let loaded = parse(&text, Path::new("")).unwrap();
+        let ray = Ray::new(Vec3::new(0, 0, 1), Vec3::new(0, 0, -1));
+        let outer = loaded.world[2].hit(&ray, 0.0, 10.0).unwrap().material;
+        let inner = loaded.world[3].hit(&ray, 0.0, 10.0).unwrap().material;
+        assert!(Arc::ptr_eq(&outer, &inner));
+    }
+
+    #[test]
+    fn lights_keep_their_intensity() {
+        let text = to_string(&built_scene()).unwrap();
+        let table: toml::Table = toml::from_str(&text).unwrap();
+        let light = &table["materials"]["diffuse_light_1"];
+        assert_eq!(Some(3.0), light["intensity"].as_float());
+        let color: Vec<_> = light["color"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c.as_float().unwrap())
+            .collect();
+        assert_eq!(vec![1.0, 1.0, 1.0], color);
+    }
+
+    #[test]
+    fn saved_file_loads_identically() {
+        let dir = env::temp_dir().join(format!("ray-tracing-scene-{}", process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("scene.toml");
+
+        save(&path, &built_scene()).unwrap();
+        let loaded = load(&path);
+        fs::remove_dir_all(&dir).unwrap();
+        assert_eq!(render(built_scene()), render(loaded.unwrap()));
+    }
+
+    #[test]
+    fn unsaveable_scenes() {
+        let mut scene = built_scene();
+        scene.image.background = Arc::new(EnvironmentMap::new(vec![vec![color::WHITE]], 0.0, 1.0));
+        assert!(to_string(&scene).is_err());
+
+        let mut scene = built_scene();
+        scene.camera.aspect_ratio = 1.0;
+        assert!(to_string(&scene).is_err());
+    }
+
+    #[test]
+    fn load_example_scene() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/spheres.toml");
+        let scene = load(path).unwrap();
+        assert_eq!(5, scene.world.len());
+
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/cornell.toml");
+        let scene = load(path).unwrap();
+        assert_eq!(8, scene.world.len());
+    }
+
+    #[test]
+    fn meshes_are_loaded_once() {
+        let gray: Arc<dyn Material> = Arc::new(Lambertian::new(0.5, 0.5, 0.5));
+        let materials = Materials::from([("gray".to_string(), gray)]);
+        let mut meshes = Meshes::new();
+        let mut build = || {
+            ObjectDescription::Mesh {
+                path: concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/pyramid.obj").into(),
+                material: "gray".into(),
+            }
+            .build(&materials, &mut meshes, Path::new(""))
+            .unwrap()
+        };
+
+        let first = build();
+        let second = build();
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(1, meshes.len());
+    }
+
+    #[test]
+    fn saved_mesh_scene_renders_identically() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/scenes/pyramid.toml");
+        let small = |mut scene: Scene| {
+            scene.image.width = 24;
+            scene.image.height = 18;
+            scene.image.samples_per_pixel = 4;
+            scene
+        };
+        let scene = small(load(path).unwrap());
+        let text = to_string(&scene).unwrap();
+        assert!(text.contains("pyramid.obj"), "{text}");
+
+        let loaded = small(parse(&text, Path::new("/nonexistent")).unwrap());
+        assert_eq!(render(scene), render(loaded));
+    }
+}
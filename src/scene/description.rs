@@ -0,0 +1,955 @@
+//! Descriptions of the parts of a scene, as written in the scene format, and how
+//! objects, materials and backgrounds are built from them.
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{self, Path, PathBuf},
+    sync::Arc,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    background::{environment::EnvironmentMap, Background, Gradient, Solid},
+    color::Color,
+    geometry::{
+        aabb::Aabb,
+        cone::Cone,
+        constant_medium::ConstantMedium,
+        csg::{self, Csg, Operation},
+        cuboid::Cuboid,
+        cylinder::Cylinder,
+        density::{DensityField, DensityGrid, NoiseDensity},
+        disk::Disk,
+        heterogeneous_medium::HeterogeneousMedium,
+        mat4::Mat4,
+        mesh,
+        moving_sphere::MovingSphere,
+        plane::Plane,
+        quad::Quad,
+        sdf::{self, shapes, DistanceFunction, Sdf},
+        sphere::Sphere,
+        torus::Torus,
+        transformed::Transformed,
+        triangle::Triangle,
+        vec3::Vec3,
+    },
+    material::{
+        Dielectric, DiffuseLight, HenyeyGreenstein, Isotropic, Lambertian, Material, Metal,
+    },
+    texture::{Checker, SolidColor, Texture},
+    tracer::ArcHittable,
+};
+
+/// Names a material of an object being described, adding it to the scene's
+/// materials if it hasn't been named yet.
+pub type NameMaterial<'a> = dyn FnMut(&Arc<dyn Material>) -> io::Result<String> + 'a;
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum BackgroundDescription {
+    Solid {
+        color: (f64, f64, f64),
+    },
+    Gradient {
+        bottom: (f64, f64, f64),
+        top: (f64, f64, f64),
+    },
+    Environment {
+        path: String,
+        #[serde(default)]
+        rotation: f64,
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+}
+
+fn default_intensity() -> f64 {
+    1.0
+}
+
+impl BackgroundDescription {
+    pub fn build(self, base_dir: &Path) -> io::Result<Arc<dyn Background>> {
+        Ok(match self {
+            BackgroundDescription::Solid { color } => Arc::new(Solid::new(Color::from(color))),
+            BackgroundDescription::Gradient { bottom, top } => {
+                Arc::new(Gradient::new(Color::from(bottom), Color::from(top)))
+            }
+            BackgroundDescription::Environment {
+                path,
+                rotation,
+                intensity,
+            } => Arc::new(EnvironmentMap::load(
+                base_dir.join(path),
+                rotation,
+                intensity,
+            )?),
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum MaterialDescription {
+    Lambertian {
+        albedo: TextureDescription,
+    },
+    Metal {
+        albedo: TextureDescription,
+        #[serde(default)]
+        fuzz: f64,
+    },
+    Dielectric {
+        refractive_index: f64,
+    },
+    DiffuseLight {
+        color: (f64, f64, f64),
+        #[serde(default = "default_intensity")]
+        intensity: f64,
+    },
+    Isotropic {
+        albedo: (f64, f64, f64),
+    },
+    HenyeyGreenstein {
+        albedo: (f64, f64, f64),
+        g: f64,
+    },
+}
+
+impl MaterialDescription {
+    /// The name of the type of material, as written in the scene format.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            MaterialDescription::Lambertian { .. } => "lambertian",
+            MaterialDescription::Metal { .. } => "metal",
+            MaterialDescription::Dielectric { .. } => "dielectric",
+            MaterialDescription::DiffuseLight { .. } => "diffuse_light",
+            MaterialDescription::Isotropic { .. } => "isotropic",
+            MaterialDescription::HenyeyGreenstein { .. } => "henyey_greenstein",
+        }
+    }
+
+    pub fn build(self) -> Result<Arc<dyn Material>, String> {
+        Ok(match self {
+            MaterialDescription::Lambertian { albedo } => {
+                Arc::new(Lambertian::textured(albedo.build()?))
+            }
+            MaterialDescription::Metal { albedo, fuzz } => {
+                if !(0.0..=1.0).contains(&fuzz) {
+                    return Err("fuzz must be between 0 and 1".into());
+                }
+                Arc::new(Metal::textured(albedo.build()?, fuzz))
+            }
+            MaterialDescription::Dielectric { refractive_index } => {
+                if refractive_index <= 0.0 {
+                    return Err("the refractive index must be positive".into());
+                }
+                Arc::new(Dielectric::new(refractive_index))
+            }
+            MaterialDescription::DiffuseLight { color, intensity } => {
+                Arc::new(DiffuseLight::new(Color::from(color), intensity))
+            }
+            MaterialDescription::Isotropic { albedo: (r, g, b) } => {
+                Arc::new(Isotropic::new(r, g, b))
+            }
+            MaterialDescription::HenyeyGreenstein { albedo, g } => {
+                if !(g > -1.0 && g < 1.0) {
+                    return Err("g must be between -1 and 1".into());
+                }
+                Arc::new(HenyeyGreenstein::new(albedo, g))
+            }
+        })
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ObjectDescription {
+    Sphere {
+        center: (f64, f64, f64),
+        radius: f64,
+        material: String,
+    },
+    /// A sphere moving in a straight line between two centers, over an interval
+    /// of time.
+    MovingSphere {
+        centers: [(f64, f64, f64); 2],
+        #[serde(default = "default_times")]
+        times: (f64, f64),
+        radius: f64,
+        material: String,
+    },
+    Triangle {
+        vertices: [(f64, f64, f64); 3],
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        normals: Option<[(f64, f64, f64); 3]>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        uvs: Option<[(f64, f64); 3]>,
+        material: String,
+    },
+    Plane {
+        point: (f64, f64, f64),
+        normal: (f64, f64, f64),
+        material: String,
+    },
+    Quad {
+        origin: (f64, f64, f64),
+        u: (f64, f64, f64),
+        v: (f64, f64, f64),
+        material: String,
+    },
+    Cuboid {
+        center: (f64, f64, f64),
+        size: (f64, f64, f64),
+        #[serde(default = "default_x_axis")]
+        x_axis: (f64, f64, f64),
+        #[serde(default = "default_y_axis")]
+        y_axis: (f64, f64, f64),
+        material: String,
+    },
+    Disk {
+        center: (f64, f64, f64),
+        normal: (f64, f64, f64),
+        radius: f64,
+        material: String,
+    },
+    Cylinder {
+        base: (f64, f64, f64),
+        top: (f64, f64, f64),
+        radius: f64,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String,
+    },
+    Cone {
+        base: (f64, f64, f64),
+        apex: (f64, f64, f64),
+        radius: f64,
+        #[serde(default = "default_capped")]
+        capped: bool,
+        material: String,
+    },
+    Torus {
+        center: (f64, f64, f64),
+        axis: (f64, f64, f64),
+        major_radius: f64,
+        minor_radius: f64,
+        material: String,
+    },
+    /// A mesh loaded from a file. Faces without a material of their own use
+    /// `material`.
+    Mesh { path: String, material: String },
+    /// Another object, moved by a list of transforms applied in order.
+    Transformed {
+        transform: Vec<TransformDescription>,
+        object: Box<ObjectDescription>,
+    },
+    /// Two spheres or CSG objects combined by a set operation.
+    Csg {
+        operation: CsgOperation,
+        left: Box<ObjectDescription>,
+        right: Box<ObjectDescription>,
+    },
+    /// A surface given by a signed distance function.
+    Sdf {
+        shape: ShapeDescription,
+        #[serde(default = "default_epsilon")]
+        epsilon: f64,
+        #[serde(default = "default_max_steps")]
+        max_steps: u32,
+        material: String,
+    },
+    /// A volume of constant density filling a convex boundary object.
+    ConstantMedium {
+        boundary: Box<ObjectDescription>,
+        density: f64,
+        material: String,
+    },
+    /// A volume of varying density filling a convex boundary object.
+    HeterogeneousMedium {
+        boundary: Box<ObjectDescription>,
+        density: DensityDescription,
+        material: String,
+    },
+}
+
+impl ObjectDescription {
+    /// Build the object, with materials looked up by name in `materials`. Meshes
+    /// already loaded into `meshes` are shared rather than loaded again.
+    pub fn build(
+        self,
+        materials: &Materials,
+        meshes: &mut Meshes,
+        base_dir: &Path,
+    ) -> Result<ArcHittable, String> {
+        Ok(match self {
+            solid @ (ObjectDescription::Sphere { .. }
+            | ObjectDescription::MovingSphere { .. }
+            | ObjectDescription::Csg { .. }) => solid.build_solid(materials)?,
+            ObjectDescription::Triangle {
+                vertices,
+                normals,
+                uvs,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let [v0, v1, v2] = vertices.map(Vec3::from);
+                if (v1 - v0).cross(v2 - v0).length_squared() == 0.0 {
+                    return Err("triangles must have a non-zero area".into());
+                }
+                let mut triangle = Triangle::new([v0, v1, v2], material);
+                if let Some(normals) = normals {
+                    triangle = triangle.with_normals(normals.map(Vec3::from));
+                }
+                if let Some(uvs) = uvs {
+                    triangle = triangle.with_uvs(uvs);
+                }
+                Arc::new(triangle)
+            }
+            ObjectDescription::Plane {
+                point,
+                normal,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let normal = Vec3::from(normal);
+                if normal.length_squared() == 0.0 {
+                    return Err("planes must have a non-zero normal".into());
+                }
+                Arc::new(Plane::new(Vec3::from(point), normal, material))
+            }
+            ObjectDescription::Quad {
+                origin,
+                u,
+                v,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let (u, v) = (Vec3::from(u), Vec3::from(v));
+                if u.cross(v).length_squared() == 0.0 {
+                    return Err("quads must have a non-zero area".into());
+                }
+                Arc::new(Quad::new(Vec3::from(origin), u, v, material))
+            }
+            ObjectDescription::Cuboid {
+                center,
+                size,
+                x_axis,
+                y_axis,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let (size, x_axis, y_axis) =
+                    (Vec3::from(size), Vec3::from(x_axis), Vec3::from(y_axis));
+                if size.x() <= 0.0 || size.y() <= 0.0 || size.z() <= 0.0 {
+                    return Err("cuboids must have a positive size".into());
+                }
+                if x_axis.cross(y_axis).length_squared() == 0.0 {
+                    return Err("the axes of a cuboid must not be parallel".into());
+                }
+                Arc::new(Cuboid::oriented(
+                    Vec3::from(center),
+                    size,
+                    x_axis,
+                    y_axis,
+                    material,
+                ))
+            }
+            ObjectDescription::Disk {
+                center,
+                normal,
+                radius,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let normal = Vec3::from(normal);
+                if normal.length_squared() == 0.0 {
+                    return Err("disks must have a non-zero normal".into());
+                }
+                if radius <= 0.0 {
+                    return Err("disks must have a positive radius".into());
+                }
+                Arc::new(Disk::new(Vec3::from(center), normal, radius, material))
+            }
+            ObjectDescription::Cylinder {
+                base,
+                top,
+                radius,
+                capped,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let (base, top) = (Vec3::from(base), Vec3::from(top));
+                if base == top {
+                    return Err("cylinders must have a non-zero height".into());
+                }
+                if radius <= 0.0 {
+                    return Err("cylinders must have a positive radius".into());
+                }
+                let cylinder = Cylinder::new(base, top, radius, material);
+                if capped {
+                    Arc::new(cylinder)
+                } else {
+                    Arc::new(cylinder.uncapped())
+                }
+            }
+            ObjectDescription::Cone {
+                base,
+                apex,
+                radius,
+                capped,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let (base, apex) = (Vec3::from(base), Vec3::from(apex));
+                if base == apex {
+                    return Err("cones must have a non-zero height".into());
+                }
+                if radius <= 0.0 {
+                    return Err("cones must have a positive radius".into());
+                }
+                let cone = Cone::new(base, apex, radius, material);
+                if capped {
+                    Arc::new(cone)
+                } else {
+                    Arc::new(cone.uncapped())
+                }
+            }
+            ObjectDescription::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                let axis = Vec3::from(axis);
+                if axis.length_squared() == 0.0 {
+                    return Err("tori must have a non-zero axis".into());
+                }
+                if minor_radius <= 0.0 || major_radius <= minor_radius {
+                    return Err(
+                        "tori must have a positive minor radius, less than the major radius".into(),
+                    );
+                }
+                Arc::new(Torus::new(
+                    Vec3::from(center),
+                    axis,
+                    major_radius,
+                    minor_radius,
+                    material,
+                ))
+            }
+            ObjectDescription::Mesh { path, material } => {
+                let key = (base_dir.join(path), material);
+                if let Some(mesh) = meshes.get(&key) {
+                    return Ok(Arc::clone(mesh));
+                }
+                let material = find_material(materials, &key.1)?;
+                let mesh: ArcHittable =
+                    Arc::new(mesh::load(&key.0, material).map_err(|e| e.to_string())?);
+                meshes.insert(key, Arc::clone(&mesh));
+                mesh
+            }
+            ObjectDescription::Transformed { transform, object } => {
+                let matrix = transform
+                    .into_iter()
+                    .fold(Mat4::IDENTITY, |matrix, step| step.matrix() * matrix);
+                if matrix.inverse().is_none() {
+                    return Err("transforms must be invertible".into());
+                }
+                Arc::new(Transformed::new(
+                    object.build(materials, meshes, base_dir)?,
+                    matrix,
+                ))
+            }
+            ObjectDescription::Sdf {
+                shape,
+                epsilon,
+                max_steps,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                if epsilon <= 0.0 {
+                    return Err("SDFs must have a positive epsilon".into());
+                }
+                if max_steps == 0 {
+                    return Err("SDFs must take at least one step".into());
+                }
+                Arc::new(
+                    Sdf::new(shape.build()?, material)
+                        .with_epsilon(epsilon)
+                        .with_max_steps(max_steps),
+                )
+            }
+            ObjectDescription::ConstantMedium {
+                boundary,
+                density,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                if density.is_nan() || density <= 0.0 {
+                    return Err("media must have a positive density".into());
+                }
+                Arc::new(ConstantMedium::new(
+                    boundary.build(materials, meshes, base_dir)?,
+                    density,
+                    material,
+                ))
+            }
+            ObjectDescription::HeterogeneousMedium {
+                boundary,
+                density,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                Arc::new(HeterogeneousMedium::new(
+                    boundary.build(materials, meshes, base_dir)?,
+                    density.build()?,
+                    material,
+                ))
+            }
+        })
+    }
+
+    /// Build an object that can be combined by CSG.
+    fn build_solid(self, materials: &Materials) -> Result<Arc<dyn csg::Solid>, String> {
+        Ok(match self {
+            ObjectDescription::Sphere {
+                center,
+                radius,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                // Negative radii are allowed, and give spheres with inward facing
+                // normals. These are useful for making hollow glass spheres.
+                if radius == 0.0 {
+                    return Err("spheres must have a non-zero radius".into());
+                }
+                Arc::new(Sphere::new(Vec3::from(center), radius, material))
+            }
+            ObjectDescription::MovingSphere {
+                centers,
+                times,
+                radius,
+                material,
+            } => {
+                let material = find_material(materials, &material)?;
+                if radius == 0.0 {
+                    return Err("spheres must have a non-zero radius".into());
+                }
+                if times.0 >= times.1 {
+                    return Err("moving spheres must start moving before they stop".into());
+                }
+                let [center0, center1] = centers.map(Vec3::from);
+                Arc::new(MovingSphere::new(
+                    center0, center1, times.0, times.1, radius, material,
+                ))
+            }
+            ObjectDescription::Csg {
+                operation,
+                left,
+                right,
+            } => {
+                // Spheres with inward facing normals would turn the intervals they
+                // enclose inside out.
+                for operand in [&left, &right] {
+                    if let ObjectDescription::Sphere { radius, .. }
+                    | ObjectDescription::MovingSphere { radius, .. } = operand.as_ref()
+                    {
+                        if *radius < 0.0 {
+                            return Err(
+                                "spheres combined by CSG must have a positive radius".into()
+                            );
+                        }
+                    }
+                }
+                Arc::new(Csg::new(
+                    operation.into(),
+                    left.build_solid(materials)?,
+                    right.build_solid(materials)?,
+                ))
+            }
+            _ => return Err("only spheres and CSG objects can be combined by CSG".into()),
+        })
+    }
+}
+
+/// Materials of a scene by name.
+pub type Materials = BTreeMap<String, Arc<dyn Material>>;
+
+/// Meshes loaded while building a scene, by their path and the name of their
+/// default material.
+pub type Meshes = BTreeMap<(PathBuf, String), ArcHittable>;
+
+fn find_material(materials: &Materials, name: &str) -> Result<Arc<dyn Material>, String> {
+    materials
+        .get(name)
+        .map(Arc::clone)
+        .ok_or_else(|| format!("unknown material {name:?}"))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl From<CsgOperation> for Operation {
+    fn from(operation: CsgOperation) -> Self {
+        match operation {
+            CsgOperation::Union => Operation::Union,
+            CsgOperation::Intersection => Operation::Intersection,
+            CsgOperation::Difference => Operation::Difference,
+        }
+    }
+}
+
+impl From<Operation> for CsgOperation {
+    fn from(operation: Operation) -> Self {
+        match operation {
+            Operation::Union => CsgOperation::Union,
+            Operation::Intersection => CsgOperation::Intersection,
+            Operation::Difference => CsgOperation::Difference,
+        }
+    }
+}
+
+/// The distance function of an SDF object.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum ShapeDescription {
+    Sphere {
+        center: (f64, f64, f64),
+        radius: f64,
+    },
+    Cuboid {
+        center: (f64, f64, f64),
+        size: (f64, f64, f64),
+    },
+    Torus {
+        center: (f64, f64, f64),
+        axis: (f64, f64, f64),
+        major_radius: f64,
+        minor_radius: f64,
+    },
+    Mandelbulb {
+        center: (f64, f64, f64),
+        scale: f64,
+        #[serde(default = "default_mandelbulb_power")]
+        power: f64,
+        #[serde(default = "default_mandelbulb_iterations")]
+        iterations: u32,
+    },
+    SmoothUnion {
+        a: Box<ShapeDescription>,
+        b: Box<ShapeDescription>,
+        smoothness: f64,
+    },
+    /// A shape part way from `a` to `b`, by `weight` from 0 to 1.
+    Blend {
+        a: Box<ShapeDescription>,
+        b: Box<ShapeDescription>,
+        weight: f64,
+    },
+}
+
+impl ShapeDescription {
+    pub fn build(self) -> Result<Arc<dyn DistanceFunction>, String> {
+        Ok(match self {
+            ShapeDescription::Sphere { center, radius } => {
+                if radius <= 0.0 {
+                    return Err("SDF spheres must have a positive radius".into());
+                }
+                Arc::new(shapes::Sphere {
+                    center: Vec3::from(center),
+                    radius,
+                })
+            }
+            ShapeDescription::Cuboid { center, size } => {
+                let size = Vec3::from(size);
+                if size.x() <= 0.0 || size.y() <= 0.0 || size.z() <= 0.0 {
+                    return Err("cuboids must have a positive size".into());
+                }
+                Arc::new(shapes::Cuboid {
+                    center: Vec3::from(center),
+                    half_size: 0.5 * size,
+                })
+            }
+            ShapeDescription::Torus {
+                center,
+                axis,
+                major_radius,
+                minor_radius,
+            } => {
+                let axis = Vec3::from(axis);
+                if axis.length_squared() == 0.0 {
+                    return Err("tori must have a non-zero axis".into());
+                }
+                if minor_radius <= 0.0 || major_radius <= minor_radius {
+                    return Err(
+                        "tori must have a positive minor radius, less than the major radius".into(),
+                    );
+                }
+                Arc::new(shapes::Torus {
+                    center: Vec3::from(center),
+                    axis: axis.normalized(),
+                    major_radius,
+                    minor_radius,
+                })
+            }
+            ShapeDescription::Mandelbulb {
+                center,
+                scale,
+                power,
+                iterations,
+            } => {
+                if scale <= 0.0 {
+                    return Err("mandelbulbs must have a positive scale".into());
+                }
+                if power < 2.0 {
+                    return Err("mandelbulbs must have a power of at least 2".into());
+                }
+                Arc::new(shapes::Mandelbulb {
+                    center: Vec3::from(center),
+                    scale,
+                    power,
+                    iterations,
+                })
+            }
+            ShapeDescription::SmoothUnion { a, b, smoothness } => {
+                if smoothness < 0.0 {
+                    return Err("smooth unions must not have a negative smoothness".into());
+                }
+                Arc::new(shapes::SmoothUnion {
+                    a: a.build()?,
+                    b: b.build()?,
+                    smoothness,
+                })
+            }
+            ShapeDescription::Blend { a, b, weight } => {
+                if !(0.0..=1.0).contains(&weight) {
+                    return Err("blends must have a weight from 0 to 1".into());
+                }
+                Arc::new(shapes::Blend {
+                    a: a.build()?,
+                    b: b.build()?,
+                    weight,
+                })
+            }
+        })
+    }
+}
+
+/// The albedo of a material, either a color or a texture.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum TextureDescription {
+    Color((f64, f64, f64)),
+    Texture(TextureKind),
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum TextureKind {
+    Checker {
+        scale: f64,
+        even: Box<TextureDescription>,
+        odd: Box<TextureDescription>,
+    },
+}
+
+impl TextureDescription {
+    pub fn build(self) -> Result<Arc<dyn Texture>, String> {
+        Ok(match self {
+            TextureDescription::Color(color) => Arc::new(SolidColor::new(Color::from(color))),
+            TextureDescription::Texture(TextureKind::Checker { scale, even, odd }) => {
+                if scale <= 0.0 {
+                    return Err("checkers must have a positive scale".into());
+                }
+                Arc::new(Checker::new(scale, even.build()?, odd.build()?))
+            }
+        })
+    }
+}
+
+/// The density field of a heterogeneous medium.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case", deny_unknown_fields)]
+pub enum DensityDescription {
+    /// Samples spanning the box from `min` to `max`, with `x` varying fastest.
+    Grid {
+        min: (f64, f64, f64),
+        max: (f64, f64, f64),
+        size: [usize; 3],
+        values: Vec<f64>,
+    },
+    Noise {
+        density: f64,
+        frequency: f64,
+        #[serde(default = "default_octaves")]
+        octaves: u32,
+        #[serde(default)]
+        seed: u64,
+    },
+}
+
+impl DensityDescription {
+    pub fn build(self) -> Result<Arc<dyn DensityField>, String> {
+        Ok(match self {
+            DensityDescription::Grid {
+                min,
+                max,
+                size,
+                values,
+            } => {
+                let (min, max) = (Vec3::from(min), Vec3::from(max));
+                if (0..3).any(|axis| min[axis] > max[axis]) {
+                    return Err("the minimum corner of a grid must be below its maximum".into());
+                }
+                if size.contains(&0) {
+                    return Err("grids must have samples along each axis".into());
+                }
+                let samples = size
+                    .iter()
+                    .try_fold(1_usize, |samples, &n| samples.checked_mul(n))
+                    .ok_or_else(|| format!("a grid of size {size:?} has too many samples"))?;
+                if samples != values.len() {
+                    return Err(format!(
+                        "a grid of size {size:?} must have {samples} values"
+                    ));
+                }
+                if values
+                    .iter()
+                    .any(|&value| !(value >= 0.0 && value.is_finite()))
+                {
+                    return Err("densities must be finite and not negative".into());
+                }
+                Arc::new(DensityGrid::new(Aabb::new(min, max), size, values))
+            }
+            DensityDescription::Noise {
+                density,
+                frequency,
+                octaves,
+                seed,
+            } => {
+                if density <= 0.0 {
+                    return Err("noise must have a positive density".into());
+                }
+                if frequency <= 0.0 {
+                    return Err("noise must have a positive frequency".into());
+                }
+                if octaves == 0 {
+                    return Err("noise must have at least one octave".into());
+                }
+                Arc::new(NoiseDensity {
+                    density,
+                    frequency,
+                    octaves,
+                    seed,
+                })
+            }
+        })
+    }
+}
+
+/// One step of an object's transform.
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum TransformDescription {
+    Translate((f64, f64, f64)),
+    /// A scaling about the origin, along each axis.
+    Scale((f64, f64, f64)),
+    /// A rotation about an axis through the origin, anticlockwise when looking back
+    /// along the axis.
+    Rotate {
+        axis: (f64, f64, f64),
+        degrees: f64,
+    },
+    /// The first three rows of the transform's matrix. The last row is always
+    /// `[0, 0, 0, 1]`.
+    Matrix([[f64; 4]; 3]),
+}
+
+impl TransformDescription {
+    pub fn describe(matrix: Mat4) -> Self {
+        let [r0, r1, r2, _] = matrix.rows();
+        TransformDescription::Matrix([r0, r1, r2])
+    }
+
+    fn matrix(&self) -> Mat4 {
+        match *self {
+            TransformDescription::Translate(offset) => Mat4::translation(Vec3::from(offset)),
+            TransformDescription::Scale(factors) => Mat4::scaling(Vec3::from(factors)),
+            TransformDescription::Rotate { axis, degrees } => {
+                Mat4::rotation(Vec3::from(axis), degrees)
+            }
+            TransformDescription::Matrix([r0, r1, r2]) => {
+                Mat4::from_rows([r0, r1, r2, [0.0, 0.0, 0.0, 1.0]])
+            }
+        }
+    }
+}
+
+fn default_times() -> (f64, f64) {
+    (0.0, 1.0)
+}
+
+fn default_epsilon() -> f64 {
+    sdf::DEFAULT_EPSILON
+}
+
+fn default_max_steps() -> u32 {
+    sdf::DEFAULT_MAX_STEPS
+}
+
+fn default_mandelbulb_power() -> f64 {
+    8.0
+}
+
+fn default_mandelbulb_iterations() -> u32 {
+    10
+}
+
+fn default_octaves() -> u32 {
+    4
+}
+
+fn default_capped() -> bool {
+    true
+}
+
+fn default_x_axis() -> (f64, f64, f64) {
+    (1.0, 0.0, 0.0)
+}
+
+fn default_y_axis() -> (f64, f64, f64) {
+    (0.0, 1.0, 0.0)
+}
+
+pub fn tuple(v: Vec3) -> (f64, f64, f64) {
+    (v.x(), v.y(), v.z())
+}
+
+pub fn rgb(color: Color) -> (f64, f64, f64) {
+    (color.red, color.green, color.blue)
+}
+
+/// The absolute path of a file referred to by a scene. The scene may be saved
+/// elsewhere, so relative paths would be broken.
+pub fn absolute_path(path: &Path) -> io::Result<String> {
+    let path = path::absolute(path)?;
+    path.to_str()
+        .map(str::to_string)
+        .ok_or_else(|| invalid_input(format!("path {path:?} is not valid UTF-8")))
+}
+
+pub fn invalid_input(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidInput, error.to_string())
+}
+
+pub fn invalid_data(error: impl ToString) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error.to_string())
+}
@@ -0,0 +1,113 @@
+//! Textures, which vary the color of a material over a surface.
+use std::{fmt::Debug, io, sync::Arc};
+
+use crate::{
+    color::Color,
+    geometry::vec3::Vec3,
+    scene::description::{self, TextureDescription, TextureKind},
+};
+
+/// A color that varies over a surface.
+pub trait Texture: Debug + Send + Sync {
+    /// The color at the texture coordinates `(u, v)` of a surface, where the surface
+    /// is at `point`. Surfaces without texture coordinates are given `(0, 0)`.
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Color;
+
+    /// Describe the texture for saving a scene. See [`crate::scene`].
+    #[doc(hidden)]
+    fn describe(&self) -> io::Result<TextureDescription> {
+        Err(description::invalid_input(
+            "only solid color and checker textures can be saved",
+        ))
+    }
+}
+
+/// The same color everywhere.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SolidColor {
+    color: Color,
+}
+
+impl SolidColor {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+
+    pub fn color(&self) -> Color {
+        self.color
+    }
+}
+
+impl Texture for SolidColor {
+    fn value(&self, _u: f64, _v: f64, _point: Vec3) -> Color {
+        self.color
+    }
+
+    fn describe(&self) -> io::Result<TextureDescription> {
+        Ok(TextureDescription::Color(description::rgb(self.color)))
+    }
+}
+
+/// A checkerboard of two textures, in texture coordinates. The square at the
+/// origin is `even`.
+#[derive(Debug)]
+pub struct Checker {
+    /// The number of squares per unit of `u` and `v`. A sphere has coordinates from
+    /// 0 to 1, and a plane's coordinates are distances along it.
+    pub scale: f64,
+
+    pub even: Arc<dyn Texture>,
+    pub odd: Arc<dyn Texture>,
+}
+
+impl Checker {
+    pub fn new(scale: f64, even: Arc<dyn Texture>, odd: Arc<dyn Texture>) -> Self {
+        Self { scale, even, odd }
+    }
+}
+
+impl Texture for Checker {
+    fn value(&self, u: f64, v: f64, point: Vec3) -> Color {
+        let square = (u * self.scale).floor() + (v * self.scale).floor();
+        if square.rem_euclid(2.0) == 0.0 {
+            self.even.value(u, v, point)
+        } else {
+            self.odd.value(u, v, point)
+        }
+    }
+
+    fn describe(&self) -> io::Result<TextureDescription> {
+        Ok(TextureDescription::Texture(TextureKind::Checker {
+            scale: self.scale,
+            even: Box::new(self.even.describe()?),
+            odd: Box::new(self.odd.describe()?),
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{Checker, SolidColor, Texture};
+    use crate::{color, geometry::vec3::Vec3};
+
+    #[test]
+    fn checker_alternates() {
+        let checker = Checker::new(
+            4.0,
+            Arc::new(SolidColor::new(color::WHITE)),
+            Arc::new(SolidColor::new(color::BLACK)),
+        );
+        let value = |u, v| checker.value(u, v, Vec3::new(0, 0, 0));
+
+        assert_eq!(color::WHITE, value(0.1, 0.1));
+        assert_eq!(color::BLACK, value(0.3, 0.1));
+        assert_eq!(color::BLACK, value(0.1, 0.3));
+        assert_eq!(color::WHITE, value(0.3, 0.3));
+
+        // Squares carry on in both directions from the origin.
+        assert_eq!(color::BLACK, value(-0.1, 0.1));
+        assert_eq!(color::WHITE, value(-0.1, -0.1));
+    }
+}
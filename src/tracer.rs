@@ -1,27 +1,56 @@
-use std::rc::Rc;
+use std::{
+    num::NonZeroUsize,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread,
+};
 
 use log::debug;
 
 use crate::{
+    background::Background,
     camera::Camera,
-    color::Color,
-    geometry::{hittable::Hittable, ray::Ray, vec3::Vec3},
+    color::{self, Color},
+    geometry::{
+        bvh::HittableBvh,
+        hittable::{HitRecord, Hittable},
+        ray::Ray,
+        vec3::Vec3,
+    },
     utils::rand::RTRng,
 };
 
+/// The width and height, in pixels, of the square tiles the image is split into
+/// when rendering.
+const TILE_SIZE: u32 = 16;
+
 /// Configuration to be used by the ray tracer.
 pub struct ImageConfig {
     pub width: u32,
     pub height: u32,
     pub samples_per_pixel: u32,
     pub ray_bounce_limit: u32,
+
+    /// The number of worker threads used to render the image. A value of `0` uses
+    /// as many threads as there are available cores. The rendered image does not
+    /// depend on the number of threads used.
+    pub threads: usize,
+
+    /// Seed for the random numbers used while rendering. Rendering the same scene with
+    /// the same seed gives the same image.
+    pub seed: u64,
+
+    /// The light arriving along rays that don't hit anything in the world.
+    pub background: Arc<dyn Background>,
 }
 
 /// Trait object that can be rendered.
-pub type RcHittable = Rc<dyn Hittable>;
+pub type ArcHittable = Arc<dyn Hittable>;
 
 /// A world to be rendered.
-pub type World = Vec<RcHittable>;
+pub type World = Vec<ArcHittable>;
 
 /// The color of each pixel in a scanline ordered left to right.
 pub type Scanline = Vec<Color>;
@@ -29,83 +58,550 @@ pub type Scanline = Vec<Color>;
 /// The scanlines of a rendered image ordered top to bottom.
 pub type ImagePixels = Vec<Scanline>;
 
+/// Arbitrary output variables (AOVs) describing the surfaces seen through each
+/// pixel of a rendered image. Each is stored as scanlines ordered top to bottom,
+/// like [`ImagePixels`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Aovs {
+    /// The distance from the camera to the first surface hit, averaged over the
+    /// samples that hit something. Infinite where nothing is hit.
+    pub depth: Vec<Vec<f64>>,
+
+    /// The surface normal at the first hit, facing the camera, averaged over the
+    /// samples. Zero where nothing is hit.
+    pub normal: Vec<Vec<Vec3>>,
+
+    /// The fraction of light reflected by the first surface hit, averaged over the
+    /// samples. Black where nothing is hit.
+    pub albedo: ImagePixels,
+
+    /// One more than the index in the world of the object hit by the first sample
+    /// of each pixel, or `0` where nothing is hit. IDs can't be averaged, so edges
+    /// are not antialiased.
+    pub object_id: Vec<Vec<u32>>,
+}
+
+/// Everything a ray can interact with while it is traced.
+struct Scene<'a> {
+    world: &'a HittableBvh,
+    background: &'a dyn Background,
+}
+
+/// The color and AOVs rendered for a single pixel.
+#[derive(Debug, Default, Clone, Copy)]
+struct Pixel {
+    color: Color,
+    depth: f64,
+    normal: Vec3,
+    albedo: Color,
+    object_id: u32,
+}
+
 struct PixelPos {
     row: u32,
     col: u32,
 }
 
+/// A rectangular region of the image, in image coordinates. That is, `y` is
+/// measured from the top of the image.
+struct Tile {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+impl ImageConfig {
+    fn thread_count(&self) -> usize {
+        match self.threads {
+            0 => thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            n => n,
+        }
+    }
+
+    fn tiles(&self) -> Vec<Tile> {
+        let mut tiles = vec![];
+        for y in (0..self.height).step_by(TILE_SIZE as usize) {
+            for x in (0..self.width).step_by(TILE_SIZE as usize) {
+                tiles.push(Tile {
+                    x,
+                    y,
+                    width: TILE_SIZE.min(self.width - x),
+                    height: TILE_SIZE.min(self.height - y),
+                });
+            }
+        }
+        tiles
+    }
+}
+
 /// Render a ray-traced scene.
+///
+/// The image is split into tiles which are rendered by a pool of worker threads.
+/// Each tile has its own random number generator seeded by the image seed and the
+/// tile's position in the image, so the output is identical regardless of how many
+/// threads are used.
+///
+/// The world is placed in a bounding volume hierarchy before rendering.
 pub fn render(config: ImageConfig, camera: Camera, world: World) -> ImagePixels {
-    // TODO: Make this faster with parallelism.
-    debug!("Generating pixels");
-    let rng = RTRng::new();
-
-    let get_row = |row| -> Vec<Color> {
-        (0..config.width)
-            .map(|col| {
-                get_multi_sampled_pixel_color(&camera, &world, PixelPos { row, col }, &config, &rng)
-            })
-            .collect()
+    map_pixels(&render_pixels(config, camera, world), |p| p.color)
+}
+
+/// Render a ray-traced scene as with [`render`], also collecting [`Aovs`] from the
+/// first surface hit by each camera ray. The rendered colors are identical to those
+/// returned by [`render`].
+pub fn render_with_aovs(config: ImageConfig, camera: Camera, world: World) -> (ImagePixels, Aovs) {
+    let pixels = render_pixels(config, camera, world);
+    let aovs = Aovs {
+        depth: map_pixels(&pixels, |p| p.depth),
+        normal: map_pixels(&pixels, |p| p.normal),
+        albedo: map_pixels(&pixels, |p| p.albedo),
+        object_id: map_pixels(&pixels, |p| p.object_id),
     };
+    (map_pixels(&pixels, |p| p.color), aovs)
+}
 
-    let pixels = (0..config.height)
-        .rev()
-        .inspect(|row| debug!("Scanlines remaining: {row} "))
-        .map(get_row)
-        .collect();
+fn map_pixels<T>(pixels: &[Vec<Pixel>], f: impl Fn(&Pixel) -> T) -> Vec<Vec<T>> {
+    pixels
+        .iter()
+        .map(|row| row.iter().map(&f).collect())
+        .collect()
+}
+
+fn render_pixels(config: ImageConfig, camera: Camera, world: World) -> Vec<Vec<Pixel>> {
+    debug!("Building BVH over {} hittables", world.len());
+    let world = HittableBvh::new(world);
+    let scene = Scene {
+        world: &world,
+        background: config.background.as_ref(),
+    };
+
+    let tiles = config.tiles();
+    let thread_count = config.thread_count().min(tiles.len()).max(1);
+    debug!(
+        "Rendering {} tiles with {thread_count} threads",
+        tiles.len()
+    );
+
+    let next_tile = AtomicUsize::new(0);
+    let worker = || {
+        let mut rendered = vec![];
+        loop {
+            let tile_index = next_tile.fetch_add(1, Ordering::Relaxed);
+            let Some(tile) = tiles.get(tile_index) else {
+                break;
+            };
+
+            debug!("Tiles remaining: {}", tiles.len() - tile_index);
+            let rng = RTRng::with_seed(tile_seed(config.seed, tile_index));
+            rendered.push((
+                tile_index,
+                render_tile(tile, &camera, &scene, &config, &rng),
+            ));
+        }
+        rendered
+    };
+
+    let rendered_tiles: Vec<_> = thread::scope(|s| {
+        let handles: Vec<_> = (0..thread_count).map(|_| s.spawn(worker)).collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().expect("Render thread panicked"))
+            .collect()
+    });
+
+    let mut pixels = vec![vec![Pixel::default(); config.width as usize]; config.height as usize];
+    for (tile_index, tile_pixels) in rendered_tiles {
+        let tile = &tiles[tile_index];
+        for (dy, tile_row) in tile_pixels.chunks(tile.width as usize).enumerate() {
+            let row = &mut pixels[(tile.y as usize) + dy];
+            row[tile.x as usize..(tile.x + tile.width) as usize].copy_from_slice(tile_row);
+        }
+    }
 
     debug!("Done generating pixels");
     pixels
 }
 
-fn get_multi_sampled_pixel_color(
+/// The seed of the random number generator used to render a tile. The image seed is
+/// spread out so that images rendered with nearby seeds don't share tiles.
+fn tile_seed(seed: u64, tile_index: usize) -> u64 {
+    seed.wrapping_mul(0x9E37_79B9_7F4A_7C15)
+        .wrapping_add(tile_index as u64)
+}
+
+/// Render the pixels of a tile, ordered top to bottom and left to right.
+fn render_tile(
+    tile: &Tile,
     camera: &Camera,
-    world: &World,
+    scene: &Scene,
+    config: &ImageConfig,
+    rng: &RTRng,
+) -> Vec<Pixel> {
+    let mut pixels = Vec::with_capacity((tile.width * tile.height) as usize);
+    for y in tile.y..tile.y + tile.height {
+        // Camera rows are counted from the bottom of the image.
+        let row = config.height - 1 - y;
+        for col in tile.x..tile.x + tile.width {
+            pixels.push(get_multi_sampled_pixel(
+                camera,
+                scene,
+                PixelPos { row, col },
+                config,
+                rng,
+            ));
+        }
+    }
+    pixels
+}
+
+fn get_multi_sampled_pixel(
+    camera: &Camera,
+    scene: &Scene,
     pixel_pos: PixelPos,
     config: &ImageConfig,
     rng: &RTRng,
-) -> Color {
-    let (mut r, mut g, mut b) = (0u32, 0u32, 0u32);
-    for _ in 0..config.samples_per_pixel {
+) -> Pixel {
+    let mut pixel = Pixel::default();
+    let mut total_depth = 0.0;
+    let mut hit_count = 0;
+    for sample in 0..config.samples_per_pixel {
         let u = (pixel_pos.col as f64 + rng.random_f64()) / (config.width - 1) as f64;
         let v = (pixel_pos.row as f64 + rng.random_f64()) / (config.height - 1) as f64;
         let ray = camera.get_ray(u, v, rng);
 
-        let color = ray_color(&ray, world, rng, config.ray_bounce_limit);
-        r += color.red as u32;
-        g += color.green as u32;
-        b += color.blue as u32;
-    }
+        // The first hit is found here, rather than in `ray_color`, so that the AOVs
+        // can be collected from it.
+        let hit = scene.world.hit_with_index(&ray, 0.001, f64::INFINITY);
+        if let Some((index, hit)) = &hit {
+            total_depth += hit.t * ray.direction().length();
+            hit_count += 1;
+            pixel.normal += hit.normal;
+            pixel.albedo += hit.material.attenuate(hit, color::WHITE);
+            if sample == 0 {
+                pixel.object_id = *index as u32 + 1;
+            }
+        }
 
-    Color {
-        red: (r / config.samples_per_pixel) as u8,
-        green: (g / config.samples_per_pixel) as u8,
-        blue: (b / config.samples_per_pixel) as u8,
+        if config.ray_bounce_limit > 0 {
+            let hit = hit.map(|(_, hit)| hit);
+            pixel.color += hit_color(&ray, hit, scene, rng, config.ray_bounce_limit, None);
+        }
     }
+
+    let samples = config.samples_per_pixel as f64;
+    pixel.color = pixel.color / samples;
+    pixel.normal /= samples;
+    pixel.albedo = pixel.albedo / samples;
+    pixel.depth = match hit_count {
+        0 => f64::INFINITY,
+        n => total_depth / n as f64,
+    };
+    pixel
 }
 
-/// Get the color of hittable closest to the ray.
-fn ray_color(ray: &Ray, world: &[RcHittable], rng: &RTRng, bounces_remaining: u32) -> Color {
+/// Get the color of the light arriving along a ray.
+///
+/// `scattering_pdf` is the density the ray was scattered with, if it was scattered
+/// from a surface that also samples the background directly. Background light
+/// found by the ray is then weighed against those direct samples.
+fn ray_color(
+    ray: &Ray,
+    scene: &Scene,
+    rng: &RTRng,
+    bounces_remaining: u32,
+    scattering_pdf: Option<f64>,
+) -> Color {
     // If we've exceeded the ray bounce limit, no more light is gathered.
     if bounces_remaining == 0 {
-        return (0, 0, 0).into();
+        return color::BLACK;
     }
 
-    match world.hit(ray, 0.001, f64::INFINITY) {
-        Some(hit) => match hit.material.scatter(ray, &hit, rng) {
-            Some(scattered) => {
-                let color = ray_color(&scattered, world, rng, bounces_remaining - 1);
-                hit.material.attenuate(color)
+    let hit = scene.world.hit(ray, 0.001, f64::INFINITY);
+    hit_color(ray, hit, scene, rng, bounces_remaining, scattering_pdf)
+}
+
+/// Get the color of the light arriving along a ray, given the closest hit of the ray
+/// with the world. See [`ray_color`].
+fn hit_color(
+    ray: &Ray,
+    hit: Option<HitRecord>,
+    scene: &Scene,
+    rng: &RTRng,
+    bounces_remaining: u32,
+    scattering_pdf: Option<f64>,
+) -> Color {
+    let Some(hit) = hit else {
+        let background = scene.background.color(ray);
+        return match scattering_pdf {
+            Some(pdf) => {
+                let light_pdf = scene.background.pdf(ray.direction());
+                background * power_heuristic(pdf, light_pdf)
             }
-            None => (0, 0, 0).into(),
-        },
-        None => {
-            let unit_dir = ray.direction().normalized();
-            let t = 0.5 * (unit_dir.y() + 1.0);
-
-            let c1 = Vec3::new(1, 1, 1);
-            let c2 = Vec3::new(0.5, 0.7, 1.0);
-            ((1.0 - t) * c1 + t * c2).try_into().unwrap()
+            None => background,
+        };
+    };
+
+    let mut color = hit.material.emitted(&hit);
+
+    // Light sampled directly from the background has bounced once more, so it
+    // can't be gathered on the last bounce.
+    if bounces_remaining > 1 {
+        color += sample_background(ray, &hit, scene, rng);
+    }
+
+    if let Some(scattered) = hit.material.scatter(ray, &hit, rng) {
+        let pdf = hit.material.scattering_pdf(&hit, scattered.direction());
+        let incoming = ray_color(&scattered, scene, rng, bounces_remaining - 1, pdf);
+        color += hit.material.attenuate(&hit, incoming);
+    }
+
+    color
+}
+
+/// Estimate the light reflected at a hit that arrives directly from the background,
+/// by sampling a direction towards it.
+fn sample_background(ray: &Ray, hit: &HitRecord, scene: &Scene, rng: &RTRng) -> Color {
+    let Some((direction, light_pdf)) = scene.background.sample(rng) else {
+        return color::BLACK;
+    };
+    let Some(scattering_pdf) = hit
+        .material
+        .scattering_pdf(hit, direction)
+        .filter(|&pdf| pdf > 0.0)
+    else {
+        return color::BLACK;
+    };
+
+    // Media between the hit and the background let some of its light through.
+    let shadow_ray = Ray::new(hit.point, direction).with_time(ray.time());
+    let transmittance = scene
+        .world
+        .transmittance(&shadow_ray, 0.001, f64::INFINITY, rng);
+    if transmittance <= 0.0 {
+        return color::BLACK;
+    }
+
+    let light = scene.background.color(&shadow_ray) * transmittance;
+    let weight = power_heuristic(light_pdf, scattering_pdf);
+    hit.material.attenuate(hit, light) * (scattering_pdf / light_pdf * weight)
+}
+
+/// Multiple importance sampling weight, using the power heuristic, of a sample
+/// taken with density `pdf` when `other_pdf` is the density of the other strategy.
+fn power_heuristic(pdf: f64, other_pdf: f64) -> f64 {
+    if other_pdf <= 0.0 {
+        return 1.0;
+    }
+
+    let (a, b) = (pdf * pdf, other_pdf * other_pdf);
+    a / (a + b)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::{ray_color, render, render_with_aovs, ImageConfig, Scene, World};
+    use crate::{
+        background::{environment::EnvironmentMap, Gradient, Solid},
+        camera::{Camera, Config},
+        color::{self, Color},
+        geometry::{bvh::HittableBvh, ray::Ray, sphere::Sphere, vec3::Vec3},
+        material::{Dielectric, DiffuseLight, Lambertian, Metal},
+        utils::rand::RTRng,
+    };
+
+    fn small_scene() -> (Camera, World) {
+        let camera = Camera::new(Config {
+            look_from: Vec3::new(0, 1, 5),
+            look_at: Vec3::new(0, 0, 0),
+            vup: Vec3::new(0, 1, 0),
+            vfov_degrees: 40.0,
+            aspect_ratio: 3.0 / 2.0,
+            aperture_diameter: 0.1,
+            focus_dist: 5.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        });
+        let world: World = vec![
+            Arc::new(Sphere::new(
+                Vec3::new(0, -100, 0),
+                99.5,
+                Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+            )),
+            Arc::new(Sphere::new(
+                Vec3::new(-1, 0, 0),
+                0.5,
+                Arc::new(Metal::new((0.8, 0.6, 0.2), 0.3)),
+            )),
+            Arc::new(Sphere::new(
+                Vec3::new(1, 0, 0),
+                0.5,
+                Arc::new(Dielectric::new(1.5)),
+            )),
+        ];
+        (camera, world)
+    }
+
+    fn image_config(threads: usize) -> ImageConfig {
+        ImageConfig {
+            width: 45,
+            height: 30,
+            samples_per_pixel: 4,
+            ray_bounce_limit: 10,
+            threads,
+            seed: 0,
+            background: Arc::new(Gradient::sky()),
         }
     }
+
+    #[test]
+    fn render_dimensions() {
+        let (camera, world) = small_scene();
+        let pixels = render(image_config(2), camera, world);
+
+        assert_eq!(30, pixels.len());
+        assert!(pixels.iter().all(|row| row.len() == 45));
+    }
+
+    #[test]
+    fn render_independent_of_thread_count() {
+        let (camera, world) = small_scene();
+        let single_threaded = render(image_config(1), camera, world);
+
+        for threads in [2, 3, 8] {
+            let (camera, world) = small_scene();
+            assert_eq!(
+                single_threaded,
+                render(image_config(threads), camera, world)
+            );
+        }
+    }
+
+    #[test]
+    fn render_depends_on_seed() {
+        let (camera, world) = small_scene();
+        let seeded = |seed| ImageConfig {
+            seed,
+            ..image_config(2)
+        };
+        let first = render(seeded(1), camera, world);
+
+        let (camera, world) = small_scene();
+        assert_eq!(first, render(seeded(1), camera, world));
+        let (camera, world) = small_scene();
+        assert_ne!(first, render(seeded(2), camera, world));
+    }
+
+    #[test]
+    fn emissive_material_adds_light() {
+        let light = DiffuseLight::new(Color::new(1.0, 0.5, 0.25), 4.0);
+        let world: World = vec![Arc::new(Sphere::new(
+            Vec3::new(0, 0, -2),
+            0.5,
+            Arc::new(light),
+        ))];
+        let world = HittableBvh::new(world);
+        let scene = Scene {
+            world: &world,
+            background: &Solid::black(),
+        };
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0, 0, -1));
+
+        let color = ray_color(&ray, &scene, &RTRng::new(), 10, None);
+        assert_eq!(Color::new(4.0, 2.0, 1.0), color);
+    }
+
+    #[test]
+    fn missed_rays_get_background_color() {
+        let world = HittableBvh::new(vec![]);
+        let background = Solid::new(Color::new(0.25, 0.5, 2.0));
+        let scene = Scene {
+            world: &world,
+            background: &background,
+        };
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(1, 2, 3));
+
+        let color = ray_color(&ray, &scene, &RTRng::new(), 10, None);
+        assert_eq!(Color::new(0.25, 0.5, 2.0), color);
+    }
+
+    #[test]
+    fn diffuse_sphere_in_environment_furnace() {
+        // A convex object with albedo `a` lit by a uniform white environment
+        // reflects exactly `a` of the light, however the light is sampled.
+        let world: World = vec![Arc::new(Sphere::new(
+            Vec3::new(0, 0, -2),
+            0.5,
+            Arc::new(Lambertian::new(0.5, 0.5, 0.5)),
+        ))];
+        let world = HittableBvh::new(world);
+        let background = EnvironmentMap::new(vec![vec![color::WHITE; 32]; 16], 0.0, 1.0);
+        let scene = Scene {
+            world: &world,
+            background: &background,
+        };
+        let ray = Ray::new(Vec3::new(0, 0, 0), Vec3::new(0.1, 0.2, -1));
+
+        let rng = RTRng::new();
+        let samples = 20_000;
+        let total: f64 = (0..samples)
+            .map(|_| ray_color(&ray, &scene, &rng, 10, None).green)
+            .sum();
+        let mean = total / samples as f64;
+        assert!((mean - 0.5).abs() < 0.01, "Mean radiance was {mean}");
+    }
+
+    #[test]
+    fn aovs_describe_first_hit() {
+        let camera = Camera::new(Config {
+            look_from: Vec3::new(0, 0, 0),
+            look_at: Vec3::new(0, 0, -1),
+            vup: Vec3::new(0, 1, 0),
+            vfov_degrees: 40.0,
+            aspect_ratio: 1.0,
+            aperture_diameter: 0.0,
+            focus_dist: 1.0,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+        });
+        let world: World = vec![Arc::new(Sphere::new(
+            Vec3::new(0, 0, -5),
+            1,
+            Arc::new(Lambertian::new(0.2, 0.4, 0.6)),
+        ))];
+        let config = ImageConfig {
+            width: 11,
+            height: 11,
+            ..image_config(2)
+        };
+
+        let (pixels, aovs) = render_with_aovs(config, camera, world);
+        assert_eq!(11, aovs.depth.len());
+        assert!(aovs.object_id.iter().all(|row| row.len() == 11));
+
+        // The centre pixel sees the sphere almost head on.
+        assert!((aovs.depth[5][5] - 4.0).abs() < 0.2);
+        assert!(aovs.normal[5][5].z() > 0.9);
+        assert!((aovs.albedo[5][5].blue - 0.6).abs() < 1e-9);
+        assert_eq!(1, aovs.object_id[5][5]);
+
+        // The corners see only the background.
+        assert_eq!(f64::INFINITY, aovs.depth[0][0]);
+        assert_eq!(Vec3::default(), aovs.normal[0][10]);
+        assert_eq!(color::BLACK, aovs.albedo[10][0]);
+        assert_eq!(0, aovs.object_id[10][10]);
+        assert_ne!(color::BLACK, pixels[10][10]);
+    }
+
+    #[test]
+    fn aovs_do_not_change_rendered_colors() {
+        let (camera, world) = small_scene();
+        let (pixels, aovs) = render_with_aovs(image_config(2), camera, world);
+        let (camera, world) = small_scene();
+
+        assert_eq!(render(image_config(2), camera, world), pixels);
+        assert!(aovs.object_id.iter().flatten().all(|&id| id <= 3));
+    }
 }
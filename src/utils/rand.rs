@@ -9,8 +9,14 @@ pub struct RTRng {
 
 impl RTRng {
     pub fn new() -> Self {
+        Self::with_seed(0)
+    }
+
+    /// Create a generator with a specific seed. Generators created with the same
+    /// seed produce the same sequence of numbers.
+    pub fn with_seed(seed: u64) -> Self {
         let rng = fastrand::Rng::new();
-        rng.seed(0);
+        rng.seed(seed);
 
         Self { rng }
     }
@@ -85,6 +91,28 @@ impl RTRng {
     }
 }
 
+/// A number in the range [0,1) found by hashing `values`. The same values always
+/// give the same number, so it can stand in for a random number where no generator
+/// is available, such as when a ray hits a hittable.
+pub fn hash_f64(values: &[f64]) -> f64 {
+    // The top 53 bits fill the mantissa of an `f64`.
+    (hash_u64(values) >> 11) as f64 / (1_u64 << 53) as f64
+}
+
+/// A hash of `values`, for example to seed an [`RTRng`] where [`hash_f64`] would
+/// need more than one number.
+pub fn hash_u64(values: &[f64]) -> u64 {
+    // Each value is mixed in with the SplitMix64 finalizer.
+    values
+        .iter()
+        .fold(0x9E37_79B9_7F4A_7C15_u64, |hash, value| {
+            let mut z = (hash ^ value.to_bits()).wrapping_add(0x9E37_79B9_7F4A_7C15);
+            z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+            z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+            z ^ (z >> 31)
+        })
+}
+
 impl Default for RTRng {
     fn default() -> Self {
         Self::new()